@@ -1,13 +1,38 @@
+use std::collections::HashMap;
+
 use crate::{
-    analysis_types::{EnityData, FuncData, Operator, SemanticScope, StructData, Symbol},
+    analysis_types::{EnityData, FuncData, Operator, SemanticScope, StructData, Symbol, TraitData},
     error::{SemErr, SemErrType},
     expression::{Expr, ExprType},
+    native::{Natives, OPTION, RESULT, STATIC_METHOD_TYPES, STR_METHODS},
     parse_types::BinaryOp,
-    statement::{Stmt, StmtType},
+    statement::{MatchArm, Pattern, Stmt, StmtType},
     token::{Literal, TokenType},
     value::ValueType,
 };
 
+// guards against generic functions that call themselves with ever growing types
+const MAX_INSTANCE_DEPTH: usize = 32;
+
+// how a resolved method call finds the method it calls
+enum Dispatch {
+    Struct,
+    Native(&'static str),
+    // looked up in the vtables of the instance at runtime
+    Trait(u8),
+}
+
+// 'impl Trait for Struct' with the names of the methods it defines
+struct Impl<'a> {
+    trait_name: &'a str,
+    struct_name: &'a str,
+    methods: Vec<&'a str>,
+    line: u32,
+}
+
+// the methods of an 'impl' with the struct they're added to and their index in it
+type ImplMethods<'a> = Vec<(&'a str, usize, Stmt<'a>)>;
+
 pub struct Analyser<'a> {
     entities: EnityData<'a>,
     symbols: SemanticScope<'a>,
@@ -15,6 +40,13 @@ pub struct Analyser<'a> {
     current_use_self: bool,
     return_stmt_found: bool,
     current_struct: Option<&'a str>,
+    // the type arguments of the instance of a generic function that is analysed
+    type_bindings: HashMap<String, ValueType>,
+    instance_depth: usize,
+    // how many loops and lambdas the analysed code is in, their bodies can run more than once
+    loop_depth: usize,
+    // the scope depth at which the body of the innermost lambda starts
+    lambda_depth: Option<usize>,
 }
 impl<'a> Analyser<'a> {
     fn new() -> Self {
@@ -25,33 +57,208 @@ impl<'a> Analyser<'a> {
             current_struct: None,
             return_stmt_found: false,
             current_use_self: false,
+            type_bindings: HashMap::new(),
+            instance_depth: 0,
+            loop_depth: 0,
+            lambda_depth: None,
         }
     }
-    pub fn analyse_stmts(stmts: &mut Vec<Stmt<'a>>) -> Option<EnityData<'a>> {
+    pub fn analyse_stmts(
+        stmts: &mut Vec<Stmt<'a>>,
+        natives: Natives<'a>,
+    ) -> Result<EnityData<'a>, SemErr> {
         let mut analyser = Analyser::new();
-        if let Err(err) = analyser.init_type_data(stmts) {
-            err.print();
-            return None;
+        analyser.entities.nat_funcs = natives.funcs;
+        analyser.entities.nat_structs = natives.structs;
+        let impls = merge_impls(stmts)?;
+        analyser.init_type_data(stmts)?;
+        analyser.check_impls(impls)?;
+
+        let Some(main) = analyser.entities.funcs.get("main") else {
+            let err_ty = SemErrType::NoMainFunc;
+            return Err(SemErr::new(0, err_ty));
+        };
+        // 'main' can take the command-line arguments
+        let str_arr = ValueType::Arr(Box::new(ValueType::Str));
+        match main.parameters.as_slice() {
+            [] => (),
+            [(ty, _)] if *ty == str_arr => (),
+            _ => return Err(SemErr::new(main.line, SemErrType::InvalidMainParams)),
         }
 
+        // globals are declared first so every function and method can use them
+        for stmt in stmts.iter_mut() {
+            match stmt.stmt {
+                StmtType::Var { .. } => analyser.analyse_stmt(stmt)?,
+                StmtType::Func { .. }
+                | StmtType::Struct { .. }
+                | StmtType::Enum { .. }
+                | StmtType::Trait { .. } => (),
+                _ => return Err(SemErr::new(stmt.line, SemErrType::InvalidTopLevelStmt)),
+            }
+        }
+        analyser.analyse_methods(stmts)?;
+
         for stmt in stmts {
-            if let Err(err) = analyser.analyse_stmt(stmt) {
-                err.print();
-                return None;
+            if !matches!(stmt.stmt, StmtType::Var { .. }) {
+                analyser.analyse_stmt(stmt)?;
             }
         }
 
-        Some(analyser.entities)
+        Ok(analyser.entities)
     }
 
-    fn init_type_data(&mut self, stmts: &mut Vec<Stmt<'a>>) -> Result<(), SemErr> {
-        let (nat_funcs, nat_structs) = crate::native::register();
-        self.entities.nat_funcs = nat_funcs;
-        self.entities.nat_structs = nat_structs;
+    /// Analyses a REPL input. There is no 'main' function, `stmts` run at the top level and they
+    /// and `decls` can use the global variables in `vars` that were declared by earlier inputs. `entities` holds the
+    /// declarations of earlier inputs, only `decls` are analysed. Expression statements are
+    /// turned into print statements, unless they are assignments or evaluate to null.
+    pub fn analyse_repl(
+        entities: EnityData<'a>,
+        decls: &mut Vec<Stmt<'a>>,
+        stmts: &mut [Stmt<'a>],
+        vars: &[Symbol<'a>],
+    ) -> Result<EnityData<'a>, SemErr> {
+        let mut analyser = Analyser::new();
+        analyser.entities = entities;
+        // the variables are globals, so the new functions can use them
+        for var in vars {
+            analyser.symbols.declare(var.clone(), 1)?;
+        }
+
+        let (mut impls, earlier_methods) = analyser.add_earlier_impls(decls)?;
+        impls.extend(merge_impls(decls)?);
+        analyser.init_type_data(decls)?;
+        analyser.check_impls(impls)?;
+        analyser.analyse_methods(decls)?;
+
+        for (struct_name, index, mut method) in earlier_methods {
+            analyser.current_struct = Some(struct_name);
+            analyser.analyse_stmt(&mut method)?;
+            let StmtType::Func { body, .. } = method.stmt else {
+                unreachable!()
+            };
+            analyser
+                .entities
+                .structs
+                .get_mut(struct_name)
+                .unwrap()
+                .methods[index]
+                .1
+                .body = body;
+            analyser.current_struct = None;
+        }
+
+        for decl in decls {
+            analyser.analyse_stmt(decl)?;
+        }
 
+        for stmt in stmts {
+            let StmtType::Expr(expr) = &mut stmt.stmt else {
+                analyser.analyse_stmt(stmt)?;
+                continue;
+            };
+
+            let is_assignment = matches!(
+                expr.expr,
+                ExprType::Assign { .. } | ExprType::DotAssign { .. } | ExprType::AssignIndex { .. }
+            );
+            if analyser.analyse_expr(expr)? != ValueType::Null && !is_assignment {
+                stmt.stmt = StmtType::Println(expr.clone());
+            }
+        }
+
+        Ok(analyser.entities)
+    }
+
+    // adds the methods of every 'impl' in `decls` for a struct of an earlier REPL input to that
+    // struct, they're returned with the struct and their index so they can be analysed
+    fn add_earlier_impls(
+        &mut self,
+        decls: &mut Vec<Stmt<'a>>,
+    ) -> Result<(Vec<Impl<'a>>, ImplMethods<'a>), SemErr> {
+        let mut impls = vec![];
+        let mut methods = vec![];
+        let mut i = 0;
+        while i < decls.len() {
+            let is_earlier = match decls[i].stmt {
+                StmtType::Impl { struct_name, .. } => {
+                    self.entities.structs.contains_key(struct_name)
+                        && !decls.iter().any(|decl| {
+                            matches!(decl.stmt, StmtType::Struct { name, .. } if name == struct_name)
+                        })
+                }
+                _ => false,
+            };
+            if !is_earlier {
+                i += 1;
+                continue;
+            }
+            let line = decls[i].line;
+            let StmtType::Impl {
+                trait_name,
+                struct_name,
+                methods: impl_methods,
+            } = decls.remove(i).stmt
+            else {
+                unreachable!()
+            };
+
+            let data = self.entities.structs.get_mut(struct_name).unwrap();
+            if !data.type_params.is_empty() {
+                let ty =
+                    SemErrType::InvalidImplTarget(trait_name.to_string(), struct_name.to_string());
+                return Err(SemErr::new(line, ty));
+            }
+            let mut names = vec![];
+            for method in impl_methods {
+                let StmtType::Func {
+                    name,
+                    parameters,
+                    return_ty,
+                    use_self,
+                    type_params,
+                    ..
+                } = &method.stmt
+                else {
+                    unreachable!()
+                };
+                if data.methods.iter().any(|(other, _)| other == name) {
+                    let ty = SemErrType::DuplicateMethod(struct_name.to_string(), name.to_string());
+                    return Err(SemErr::new(method.line, ty));
+                }
+                let func_data = FuncData {
+                    parameters: parameters.clone(),
+                    body: vec![],
+                    return_ty: return_ty.clone(),
+                    line: method.line,
+                    use_self: *use_self,
+                    type_params: type_params.clone(),
+                    instances: vec![],
+                };
+                names.push(*name);
+                data.methods.push((*name, func_data));
+                methods.push((struct_name, data.methods.len() - 1, method));
+            }
+            impls.push(Impl {
+                trait_name,
+                struct_name,
+                methods: names,
+                line,
+            });
+        }
+        Ok((impls, methods))
+    }
+
+    fn init_type_data(&mut self, stmts: &mut Vec<Stmt<'a>>) -> Result<(), SemErr> {
         for stmt in stmts {
             let line = stmt.line;
             if let StmtType::Enum { name, variants } = &stmt.stmt {
+                // the variant of an enum that holds values is stored in a byte
+                let holds_values = variants.iter().any(|(_, values)| !values.is_empty());
+                if holds_values && variants.len() > u8::MAX as usize + 1 {
+                    let err_ty = SemErrType::TooManyVariants(name.to_string());
+                    return Err(SemErr::new(line, err_ty));
+                }
                 if self
                     .entities
                     .enums
@@ -64,17 +271,25 @@ impl<'a> Analyser<'a> {
             } else if let StmtType::Func {
                 name,
                 parameters,
-                body: _,
+                body,
                 return_ty,
                 use_self,
+                type_params,
             } = &mut stmt.stmt
             {
                 let func_data = FuncData {
                     parameters: parameters.clone(),
-                    body: vec![],
+                    // the body of a generic function is analysed when it's instantiated
+                    body: if type_params.is_empty() {
+                        vec![]
+                    } else {
+                        body.clone()
+                    },
                     return_ty: return_ty.clone(),
                     line: stmt.line,
                     use_self: *use_self,
+                    type_params: type_params.clone(),
+                    instances: vec![],
                 };
 
                 if self.entities.funcs.insert(*name, func_data).is_some() {
@@ -85,6 +300,7 @@ impl<'a> Analyser<'a> {
                 name,
                 fields,
                 methods,
+                type_params,
             } = &mut stmt.stmt
             {
                 if self.current_struct.is_some() {
@@ -93,7 +309,7 @@ impl<'a> Analyser<'a> {
                     return Err(SemErr::new(line, ty));
                 }
                 self.current_struct = Some(name);
-                let struct_data = StructData::new(fields.clone());
+                let struct_data = StructData::new(fields.clone(), type_params.clone());
                 let mut method_data = vec![];
 
                 if self.entities.structs.insert(*name, struct_data).is_some() {
@@ -108,14 +324,18 @@ impl<'a> Analyser<'a> {
                         body: _,
                         return_ty,
                         use_self,
+                        type_params,
                     } = &method.stmt
                     {
+                        // methods aren't instantiated, their type parameters stay opaque
                         let func_data = FuncData {
                             parameters: parameters.clone(),
                             body: vec![],
                             return_ty: return_ty.clone(),
                             line: stmt.line,
                             use_self: *use_self,
+                            type_params: type_params.clone(),
+                            instances: vec![],
                         };
                         method_data.push((*name, func_data));
                     } else {
@@ -123,27 +343,147 @@ impl<'a> Analyser<'a> {
                     }
                 }
 
-                self.entities.structs.get_mut(name).unwrap().methods = method_data.clone();
+                self.entities.structs.get_mut(name).unwrap().methods = method_data;
+                self.current_struct = None;
+                // self.symbols.declare(Symbol::new("Foo", ValueType::Struct(())), line)
+            } else if let StmtType::Trait { name, methods } = &stmt.stmt {
+                let mut method_data = vec![];
+                for method in methods {
+                    if !method.use_self {
+                        let ty = SemErrType::TraitMethodWithoutSelf(
+                            name.to_string(),
+                            method.name.to_string(),
+                        );
+                        return Err(SemErr::new(method.line, ty));
+                    }
+                    let func_data = FuncData {
+                        parameters: method.parameters.clone(),
+                        body: vec![],
+                        return_ty: method.return_ty.clone(),
+                        line: method.line,
+                        use_self: true,
+                        type_params: vec![],
+                        instances: vec![],
+                    };
+                    method_data.push((method.name, func_data));
+                }
 
-                for (i, method) in methods.iter_mut().enumerate() {
-                    self.analyse_stmt(method)?;
+                let trait_data = TraitData {
+                    id: self.entities.traits.len() as u8,
+                    methods: method_data,
+                };
+                if self.entities.traits.insert(*name, trait_data).is_some() {
+                    let err_ty = SemErrType::AlreadyDefinedTrait(name.to_string());
+                    return Err(SemErr::new(line, err_ty));
+                }
+            }
+        }
 
-                    if let StmtType::Func { body, .. } = &method.stmt {
-                        method_data[i].1.body = body.clone();
-                    } else {
-                        unreachable!()
-                    }
+        Ok(())
+    }
+
+    // checks that every 'impl' defines the methods of its trait with the same signatures, and
+    // records which struct methods implement them
+    fn check_impls(&mut self, impls: Vec<Impl<'a>>) -> Result<(), SemErr> {
+        // the types of the trait methods can only be resolved once every type is known
+        let mut traits = std::mem::take(&mut self.entities.traits);
+        for data in traits.values_mut() {
+            for (_, method) in &mut data.methods {
+                for (ty, _) in &mut method.parameters {
+                    self.entities.resolve_value_ty(ty);
                 }
+                self.entities.resolve_value_ty(&mut method.return_ty);
+            }
+        }
+        self.entities.traits = traits;
 
-                self.entities.structs.get_mut(name).unwrap().methods = method_data;
-                self.current_struct = None;
-                // self.symbols.declare(Symbol::new("Foo", ValueType::Struct(())), line)
+        for imp in impls {
+            let Impl {
+                trait_name,
+                struct_name,
+                methods,
+                line,
+            } = imp;
+            let Some(trait_data) = self.entities.traits.get(trait_name) else {
+                let ty = SemErrType::UndefinedTrait(trait_name.to_string());
+                return Err(SemErr::new(line, ty));
+            };
+            if self.entities.implements(struct_name, trait_name) {
+                let ty =
+                    SemErrType::AlreadyImplemented(trait_name.to_string(), struct_name.to_string());
+                return Err(SemErr::new(line, ty));
+            }
+            if let Some(name) = methods
+                .iter()
+                .find(|name| trait_data.methods.iter().all(|(method, _)| method != *name))
+            {
+                let ty = SemErrType::NotTraitMethod(trait_name.to_string(), name.to_string());
+                return Err(SemErr::new(line, ty));
             }
+
+            let struct_data = &self.entities.structs[struct_name];
+            let mut indices = vec![];
+            for (name, expected) in &trait_data.methods {
+                let index = struct_data
+                    .methods
+                    .iter()
+                    .position(|(method, _)| method == name)
+                    .filter(|_| methods.contains(name));
+                let Some(index) = index else {
+                    let ty = SemErrType::MissingTraitMethod(
+                        trait_name.to_string(),
+                        struct_name.to_string(),
+                        name.to_string(),
+                    );
+                    return Err(SemErr::new(line, ty));
+                };
+
+                let found = &struct_data.methods[index].1;
+                let mut found_params: Vec<ValueType> =
+                    found.parameters.iter().map(|(ty, _)| ty.clone()).collect();
+                let mut found_return_ty = found.return_ty.clone();
+                for ty in found_params.iter_mut().chain([&mut found_return_ty]) {
+                    self.entities.resolve_value_ty(ty);
+                }
+                let expected_params = expected.parameters.iter().map(|(ty, _)| ty);
+
+                let matches = found.use_self
+                    && found.type_params.is_empty()
+                    && found_params.len() == expected.parameters.len()
+                    && found_params.iter().eq(expected_params)
+                    && found_return_ty == expected.return_ty;
+                if !matches {
+                    let ty =
+                        SemErrType::TraitMethodMismatch(trait_name.to_string(), name.to_string());
+                    return Err(SemErr::new(line, ty));
+                }
+                indices.push(index as u8);
+            }
+
+            let struct_data = self.entities.structs.get_mut(struct_name).unwrap();
+            struct_data.impls.push((trait_name, indices));
         }
+        Ok(())
+    }
 
-        if !self.entities.funcs.contains_key("main") {
-            let err_ty = SemErrType::NoMainFunc;
-            return Err(SemErr::new(0, err_ty));
+    fn analyse_methods(&mut self, stmts: &mut [Stmt<'a>]) -> Result<(), SemErr> {
+        for stmt in stmts {
+            let StmtType::Struct { name, methods, .. } = &mut stmt.stmt else {
+                continue;
+            };
+
+            self.current_struct = Some(name);
+            for (i, method) in methods.iter_mut().enumerate() {
+                self.analyse_stmt(method)?;
+
+                let StmtType::Func { body, .. } = &method.stmt else {
+                    unreachable!()
+                };
+                self.entities.structs.get_mut(name).unwrap().methods[i]
+                    .1
+                    .body = body.clone();
+            }
+            self.current_struct = None;
         }
         Ok(())
     }
@@ -154,41 +494,65 @@ impl<'a> Analyser<'a> {
             StmtType::Expr(expr) => {
                 self.analyse_expr(expr)?;
             }
-            StmtType::Var { name, value, ty } => {
-                if let ValueType::UnknownType(name) = ty {
+            StmtType::Var {
+                name,
+                value,
+                ty,
+                is_const,
+            } => {
+                if let ValueType::UnknownType(name) = ty.non_null() {
                     if !self.entities.structs.contains_key(name as &str)
                         && !self.entities.nat_structs.contains_key(name as &str)
                         && !self.entities.enums.contains_key(name as &str)
+                        && !self.entities.traits.contains_key(name as &str)
                     {
                         let err = SemErrType::UndefinedType(name.clone());
                         return Err(SemErr::new(line, err));
                     }
                 }
 
-                self.entities.resolve_value_ty(ty);
-                let value_ty = self.analyse_expr(value)?;
-
-                if value_ty != *ty
-                    && value_ty != ValueType::Null
-                    && value_ty != ValueType::Any
-                    && !try_coerce(&mut value.expr, ty)
-                {
-                    let err_ty = SemErrType::VarDeclTypeMismatch(ty.clone(), value_ty);
-                    return Err(SemErr::new(line, err_ty));
-                }
+                self.resolve_ty(ty);
+                self.check_type_args(ty, line)?;
+                let is_assigned = match value {
+                    Some(value) => {
+                        let value_ty = self.analyse_value(value, ty)?;
+                        if !self.fits_value(&value_ty, ty) && !try_coerce(&mut value.expr, ty) {
+                            let err_ty = SemErrType::VarDeclTypeMismatch(ty.clone(), value_ty);
+                            return Err(SemErr::new(line, err_ty));
+                        }
+                        true
+                    }
+                    // a nullable variable starts out as null
+                    None if matches!(ty, ValueType::Nullable(_)) => true,
+                    // a global could be read by any function, so it can't be assigned later
+                    None if self.current_return_ty.is_none() => {
+                        let err_ty = SemErrType::GlobalWithoutValue(name.to_string());
+                        return Err(SemErr::new(line, err_ty));
+                    }
+                    None => false,
+                };
 
-                self.symbols.declare(Symbol::new(name, ty.clone()), line)?;
+                let symbol = if *is_const {
+                    Symbol::constant(name, ty.clone())
+                } else if is_assigned {
+                    Symbol::new(name, ty.clone())
+                } else {
+                    Symbol::unassigned(name, ty.clone())
+                };
+                self.symbols.declare(symbol, line)?;
             }
             StmtType::Println(expr) => {
                 self.analyse_expr(expr)?;
             }
             StmtType::Return(expr) => {
                 self.return_stmt_found = true;
-                let return_ty = self.analyse_expr(expr)?;
+                let return_ty = match self.current_return_ty.clone() {
+                    Some(expected_return_ty) => self.analyse_value(expr, &expected_return_ty)?,
+                    None => self.analyse_expr(expr)?,
+                };
 
                 if let Some(expected_return_ty) = &self.current_return_ty {
-                    if return_ty != *expected_return_ty
-                        && return_ty != ValueType::Null
+                    if !self.fits_value(&return_ty, expected_return_ty)
                         && !try_coerce(&mut expr.expr, expected_return_ty)
                     {
                         let err_ty =
@@ -196,6 +560,7 @@ impl<'a> Analyser<'a> {
                         return Err(SemErr::new(line, err_ty));
                     }
                 }
+                self.symbols.assign_all();
             }
             StmtType::Block(stmts) => {
                 self.symbols.begin_scope();
@@ -215,9 +580,32 @@ impl<'a> Analyser<'a> {
                     return Err(SemErr::new(line, err_ty));
                 }
 
+                // a variable is only assigned after the 'if' when both branches assign it
+                let unassigned = self.symbols.unassigned();
+                let checked = self.null_checked(condition, true);
+                let unchecked = self.null_checked(condition, false);
+
+                self.symbols.begin_scope();
+                self.narrow(&checked);
                 self.analyse_stmt(body)?;
-                if let Some(final_else) = final_else {
+                self.symbols.end_scope();
+                let body_unassigned = self.symbols.unassigned();
+
+                self.symbols.set_unassigned(&unassigned);
+                if let Some(final_else) = final_else.as_deref_mut() {
+                    self.symbols.begin_scope();
+                    self.narrow(&unchecked);
                     self.analyse_stmt(final_else)?;
+                    self.symbols.end_scope();
+                }
+                self.symbols.set_unassigned(&body_unassigned);
+
+                // 'if x == null { return; }' checks 'x' for the rest of the block
+                if diverges(body) {
+                    self.narrow(&unchecked);
+                }
+                if final_else.as_deref().is_some_and(diverges) {
+                    self.narrow(&checked);
                 }
             }
             StmtType::While { condition, body } => {
@@ -227,7 +615,16 @@ impl<'a> Analyser<'a> {
                     return Err(SemErr::new(line, err_ty));
                 }
 
+                // 'while node != null' checks 'node' at the start of every iteration
+                let checked = self.null_checked(condition, true);
+                let unassigned = self.symbols.unassigned();
+                self.loop_depth += 1;
+                self.symbols.begin_scope();
+                self.narrow(&checked);
                 self.analyse_stmt(body)?;
+                self.symbols.end_scope();
+                self.loop_depth -= 1;
+                self.symbols.set_unassigned(&unassigned);
             }
             StmtType::For {
                 var,
@@ -237,22 +634,73 @@ impl<'a> Analyser<'a> {
                 self.symbols.begin_scope();
                 self.analyse_stmt(var)?;
                 self.analyse_expr(condition)?;
+                let unassigned = self.symbols.unassigned();
+                self.loop_depth += 1;
+                self.analyse_stmt(body)?;
+                self.loop_depth -= 1;
+                self.symbols.set_unassigned(&unassigned);
+                self.symbols.end_scope();
+            }
+            StmtType::Match { value, arms } => self.analyse_match(value, arms, line)?,
+            StmtType::Try {
+                body,
+                error,
+                catch_body,
+            } => {
+                // the body can be left at any point, so the catch can't rely on its assignments
+                let unassigned = self.symbols.unassigned();
                 self.analyse_stmt(body)?;
+                let body_unassigned = self.symbols.unassigned();
+                self.symbols.set_unassigned(&unassigned);
+
+                // anything can be thrown, runtime errors are caught as their message
+                self.symbols.begin_scope();
+                self.symbols
+                    .declare(Symbol::new(error, ValueType::Any), line)?;
+                self.analyse_stmt(catch_body)?;
                 self.symbols.end_scope();
+                self.symbols.set_unassigned(&body_unassigned);
+            }
+            StmtType::Throw(value) => {
+                self.analyse_expr(value)?;
+                self.symbols.assign_all();
             }
+            // generic functions are analysed when they're called
+            StmtType::Func { type_params, .. }
+                if !type_params.is_empty() && self.current_struct.is_none() => {}
             StmtType::Func {
                 name,
                 parameters,
                 body,
                 return_ty,
                 use_self,
+                ..
             } => {
                 self.analyse_func_stmt(return_ty.clone(), parameters, line, body, name, *use_self)?;
+                if self.current_struct.is_none() {
+                    if let Some(func) = self.entities.funcs.get_mut(name) {
+                        func.body = body.to_owned();
+                    }
+                }
             }
-            StmtType::Break => (),
-            StmtType::Continue => (),
+            StmtType::Break | StmtType::Continue => self.symbols.assign_all(),
             StmtType::Struct { .. } => (),
             StmtType::Enum { .. } => (),
+            StmtType::Trait { name, .. } => {
+                if self.current_return_ty.is_some() {
+                    let ty = SemErrType::TraitDefInFunc(name.to_string());
+                    return Err(SemErr::new(line, ty));
+                }
+            }
+            // the methods of an 'impl' at the top level were already added to its struct
+            StmtType::Impl {
+                trait_name,
+                struct_name,
+                ..
+            } => {
+                let ty = SemErrType::ImplInFunc(trait_name.to_string(), struct_name.to_string());
+                return Err(SemErr::new(line, ty));
+            }
         };
         Ok(())
     }
@@ -261,40 +709,109 @@ impl<'a> Analyser<'a> {
         let line = expr.line;
         let result = match &mut expr.expr {
             ExprType::Lit(lit) => lit.as_value_type(),
-            ExprType::Identifier(name) => match self.symbols.resolve(name) {
+            ExprType::Identifier(name) => match self.resolve_symbol(name) {
+                Some(symbol) if !symbol.is_assigned => {
+                    let ty = SemErrType::UnassignedVar(name.to_string());
+                    return Err(SemErr::new(line, ty));
+                }
                 Some(symbol) => symbol.ty,
                 None => {
-                    let ty = SemErrType::UndefinedVar(name.to_string());
-                    return Err(SemErr::new(line, ty));
+                    // a function that is used as a value
+                    let (ty, index) = self.get_func_ref_data(name, line)?;
+                    expr.expr = ExprType::FuncRef { name, index };
+                    ty
                 }
             },
+            ExprType::FuncCall { name, args, .. } if self.symbols.resolve(name).is_some() => {
+                // variables shadow functions, so this calls the function the variable holds
+                let callee = Box::new(Expr::new(ExprType::Identifier(name), line));
+                let args = std::mem::take(args);
+                expr.expr = ExprType::CallValue { callee, args };
+                return self.analyse_expr(expr);
+            }
+            ExprType::CallValue { callee, args } => {
+                let callee_ty = self.analyse_expr(callee)?;
+                check_non_null(&callee_ty, line)?;
+                let ValueType::Func {
+                    parameters,
+                    return_ty,
+                } = callee_ty
+                else {
+                    let ty = SemErrType::CallNonFunc(callee_ty);
+                    return Err(SemErr::new(line, ty));
+                };
+                self.check_if_params_and_args_correspond(args, parameters, "fn".to_string(), line)?;
+                *return_ty
+            }
+            ExprType::Lambda {
+                parameters,
+                return_ty,
+                body,
+            } => self.analyse_lambda(parameters, return_ty, body, line)?,
             ExprType::FuncCall { name, args, index } => {
-                if let Some(data) = self.entities.nat_funcs.remove(name) {
+                // cloned, an argument can call the same function
+                if let Some(data) = self.entities.nat_funcs.get(name).cloned() {
                     for (i, func) in data.iter().enumerate() {
                         let parameters = func.parameters.clone();
                         let return_ty = func.return_ty.clone();
 
-                        if let Ok(()) = self.check_if_params_and_args_correspond(
-                            args,
+                        // every overload is tried with the arguments as they were parsed
+                        let mut attempt = args.clone();
+                        let result = self.check_if_params_and_args_correspond(
+                            &mut attempt,
                             parameters,
                             name.to_string(),
                             line,
-                        ) {
-                            self.entities.nat_funcs.insert(name, data);
-                            *index = Some(i);
-                            return Ok(return_ty);
+                        );
+                        match result {
+                            Ok(bindings) => {
+                                *args = attempt;
+                                *index = Some(i);
+                                return Ok(return_ty.substitute(&bindings));
+                            }
+                            // without overloads the error is more precise
+                            Err(err) if data.len() == 1 => return Err(err),
+                            Err(_) => (),
                         }
                     }
-                    self.entities.nat_funcs.insert(name, data);
-
                     let err_ty = SemErrType::NatParamTypeMismatch(name.to_string());
                     return Err(SemErr::new(line, err_ty));
                 };
 
+                if STATIC_METHOD_TYPES.contains(name) {
+                    let ty = SemErrType::NoConstructor(name.to_string());
+                    return Err(SemErr::new(line, ty));
+                }
                 let (return_ty, parameters) = self.get_called_func_data(name, line)?;
-                self.check_if_params_and_args_correspond(args, parameters, name.to_string(), line)?;
+                let bindings = self.check_if_params_and_args_correspond(
+                    args,
+                    parameters,
+                    name.to_string(),
+                    line,
+                )?;
                 *index = Some(0);
-                return_ty
+
+                // the type arguments of a generic struct are inferred from its fields
+                let type_params = self.entities.type_params(name);
+                let is_struct = self.entities.structs.contains_key(name)
+                    || self.entities.nat_structs.contains_key(name);
+                if !is_struct {
+                    let is_generic = !self.entities.funcs[name].type_params.is_empty();
+                    if is_generic {
+                        let (instance, return_ty) = self.instantiate_func(name, bindings, line)?;
+                        *index = Some(instance);
+                        return Ok(return_ty);
+                    }
+                    return_ty
+                } else if type_params.is_empty() {
+                    return_ty
+                } else {
+                    let args = type_params
+                        .iter()
+                        .map(|param| bindings.get(*param).cloned().unwrap_or(ValueType::Any))
+                        .collect();
+                    ValueType::generic(name.to_string(), args)
+                }
             }
             ExprType::Assign {
                 name,
@@ -303,10 +820,14 @@ impl<'a> Analyser<'a> {
             ExprType::Unary { prefix, value } => self.analyse_unary(value, *prefix, line)?,
             ExprType::Binary { left, op, right } => self.analyse_binary(left, right, *op, line)?,
             ExprType::Array(values) => self.analyse_array_expr(values, line)?,
-            ExprType::Index { arr, index: _ } => {
+            ExprType::Index { arr, index } => {
                 let arr = self.analyse_expr(arr)?;
+                check_non_null(&arr, line)?;
+                self.analyse_index(index, line)?;
                 match arr {
                     ValueType::Arr(ty) => *ty,
+                    // the character at that position
+                    ValueType::Str => ValueType::Str,
                     _ => {
                         let ty = SemErrType::IndexNonArr(arr);
                         return Err(SemErr::new(line, ty));
@@ -315,9 +836,12 @@ impl<'a> Analyser<'a> {
             }
             ExprType::AssignIndex {
                 arr,
-                index: _,
+                index,
                 new_value: value,
-            } => self.analyse_assign_index(arr, value, line)?,
+            } => {
+                self.analyse_index(index, line)?;
+                self.analyse_assign_index(arr, value, line)?
+            }
             ExprType::Dot { inst, property } => {
                 let (return_ty, new_expr) = self.analyse_dot(None, inst, line, property)?;
                 expr.expr = new_expr;
@@ -333,44 +857,314 @@ impl<'a> Analyser<'a> {
                 expr.expr = new_expr;
                 return_ty
             }
+            ExprType::MethodCall {
+                inst,
+                property,
+                args,
+                is_static: true,
+            } if matches!(inst.expr, ExprType::Identifier(name) if self.entities.enums.contains_key(name)) =>
+            {
+                let (ty, tag) = self.get_enum_variant_data(inst, property, line)?;
+                let ValueType::DataEnum(name) = &ty else {
+                    let ty = SemErrType::VariantWithoutValues(ty, property.to_string());
+                    return Err(SemErr::new(line, ty));
+                };
+
+                self.analyse_variant_values(name, tag, args, line)?;
+                expr.expr = ExprType::EnumVariant {
+                    name: property,
+                    tag: tag as u8,
+                    args: args.clone(),
+                };
+                ty
+            }
             ExprType::MethodCall {
                 inst,
                 property,
                 args,
                 is_static,
             } => {
-                let (index, return_ty, use_self) =
+                let (index, return_ty, use_self, dispatch) =
                     self.analyse_method_call(inst, property, line, args, *is_static)?;
 
-                expr.expr = ExprType::MethodCallResolved {
-                    inst: inst.clone(),
-                    index,
-                    args: args.clone(),
-                    use_self,
+                expr.expr = match dispatch {
+                    Dispatch::Native(methods) => ExprType::NatMethodCall {
+                        methods,
+                        inst: inst.clone(),
+                        index,
+                        args: args.clone(),
+                    },
+                    Dispatch::Trait(trait_id) => ExprType::TraitMethodCall {
+                        trait_id,
+                        inst: inst.clone(),
+                        index,
+                        args: args.clone(),
+                    },
+                    Dispatch::Struct => ExprType::MethodCallResolved {
+                        inst: inst.clone(),
+                        index,
+                        args: args.clone(),
+                        use_self,
+                    },
                 };
                 return_ty
             }
             ExprType::Cast { value, target } => {
-                let value_ty = self.analyse_expr(value)?;
+                let mut value_ty = self.analyse_expr(value)?;
+                self.entities.resolve_value_ty(&mut value_ty);
+                check_non_null(&value_ty, line)?;
+                if *target == ValueType::Str {
+                    // a struct is converted by its 'display' method
+                    if let Some(index) = self.get_display_method(&value_ty) {
+                        expr.expr = ExprType::MethodCallResolved {
+                            inst: value.clone(),
+                            index,
+                            args: vec![],
+                            use_self: true,
+                        };
+                        return Ok(ValueType::Str);
+                    }
+                    let can_display = value_ty.is_num()
+                        || matches!(
+                            &value_ty,
+                            ValueType::Str | ValueType::Bool | ValueType::DataEnum(_)
+                        )
+                        || matches!(
+                            &value_ty,
+                            ValueType::Generic(generic) if generic.name == RESULT || generic.name == OPTION
+                        );
+                    if !can_display {
+                        let ty = SemErrType::InvalidCast(target.clone(), value_ty);
+                        return Err(SemErr::new(line, ty));
+                    }
+                    return Ok(ValueType::Str);
+                }
                 if !value_ty.is_num() || !target.is_num() {
                     let ty = SemErrType::InvalidCast(target.clone(), value_ty);
                     return Err(SemErr::new(line, ty));
                 }
                 target.clone()
             }
+            ExprType::Try(value) => self.analyse_try(value, line)?,
             ExprType::Colon { inst, property } => {
                 let (ty, index) = self.get_enum_variant_data(inst, property, line)?;
-                expr.expr = ExprType::Lit(Literal::U64(index));
+                if let ValueType::DataEnum(name) = &ty {
+                    // a variant without values of an enum that holds values
+                    self.analyse_variant_values(name, index, &mut [], line)?;
+                    expr.expr = ExprType::EnumVariant {
+                        name: property,
+                        tag: index as u8,
+                        args: vec![],
+                    };
+                } else {
+                    expr.expr = ExprType::Lit(Literal::U64(index));
+                }
                 ty
             }
+            ExprType::EnumVariant { .. } => unreachable!(),
+            ExprType::FuncRef { .. } => unreachable!(),
             ExprType::This => unreachable!(),
             ExprType::DotResolved { .. } => unreachable!(),
             ExprType::MethodCallResolved { .. } => unreachable!(),
+            ExprType::NatMethodCall { .. } => unreachable!(),
+            ExprType::TraitMethodCall { .. } => unreachable!(),
             ExprType::DotAssignResolved { .. } => unreachable!(),
         };
         Ok(result)
     }
 
+    fn analyse_match(
+        &mut self,
+        value: &mut Expr<'a>,
+        arms: &mut [MatchArm<'a>],
+        line: u32,
+    ) -> Result<(), SemErr> {
+        let value_ty = self.analyse_expr(value)?;
+        check_non_null(&value_ty, line)?;
+        let can_match = matches!(
+            value_ty,
+            ValueType::Bool | ValueType::Str | ValueType::Enum(_) | ValueType::DataEnum(_)
+        ) || value_ty.is_num();
+        if !can_match {
+            let ty = SemErrType::InvalidMatchTy(value_ty);
+            return Err(SemErr::new(line, ty));
+        }
+
+        let mut has_wildcard = false;
+        let mut matched_variants = vec![];
+        // a variable is assigned after the match when every arm assigns it
+        let unassigned = self.symbols.unassigned();
+        let mut arms_unassigned = vec![];
+        for arm in arms {
+            self.symbols.set_unassigned(&unassigned);
+            for pattern in &mut arm.patterns {
+                if let (Pattern::Value(expr), ValueType::DataEnum(name)) = (&*pattern, &value_ty) {
+                    *pattern = self.analyse_variant_pattern(expr, name, arm.line)?;
+                }
+
+                match pattern {
+                    Pattern::Wildcard => has_wildcard = true,
+                    Pattern::Variant { tag, .. } => matched_variants.push(*tag as u64),
+                    Pattern::Value(expr) => {
+                        self.analyse_pattern(expr, &value_ty, arm.line)?;
+                        if let ExprType::Lit(Literal::U64(index)) = expr.expr {
+                            matched_variants.push(index);
+                        }
+                    }
+                    Pattern::Range { start, end } => {
+                        if !value_ty.is_num() || matches!(value_ty, ValueType::Enum(_)) {
+                            let ty = SemErrType::InvalidRangePattern(value_ty);
+                            return Err(SemErr::new(arm.line, ty));
+                        }
+                        self.analyse_pattern(start, &value_ty, arm.line)?;
+                        self.analyse_pattern(end, &value_ty, arm.line)?;
+                    }
+                }
+            }
+
+            // the values of a variant can be used in the body of its arm
+            self.symbols.begin_scope();
+            let bindings = arm.patterns.iter().find_map(|pattern| match pattern {
+                Pattern::Variant { tag, bindings } if bindings.iter().any(Option::is_some) => {
+                    Some((*tag, bindings))
+                }
+                _ => None,
+            });
+            if let (Some((tag, bindings)), ValueType::DataEnum(name)) = (bindings, &value_ty) {
+                if arm.patterns.len() > 1 {
+                    let ty = SemErrType::BindingInAlternatives;
+                    return Err(SemErr::new(arm.line, ty));
+                }
+
+                let values = self.entities.enums[name as &str][tag as usize].1.clone();
+                for (binding, mut ty) in bindings.iter().zip(values) {
+                    if let Some(binding) = binding {
+                        self.entities.resolve_value_ty(&mut ty);
+                        self.symbols.declare(Symbol::new(binding, ty), arm.line)?;
+                    }
+                }
+            }
+            self.analyse_stmt(&mut arm.body)?;
+            self.symbols.end_scope();
+            arms_unassigned.extend(self.symbols.unassigned());
+        }
+        self.symbols.set_unassigned(&arms_unassigned);
+
+        // every variant of an enum has to be handled
+        let mut is_exhaustive = has_wildcard;
+        if let ValueType::Enum(name) | ValueType::DataEnum(name) = &value_ty {
+            let variants = &self.entities.enums[name as &str];
+            let missing: Vec<&str> = (0..variants.len())
+                .filter(|index| !matched_variants.contains(&(*index as u64)))
+                .map(|index| variants[index].0)
+                .collect();
+
+            if !has_wildcard && !missing.is_empty() {
+                let ty = SemErrType::NonExhaustiveMatch(name.clone(), missing.join(", "));
+                return Err(SemErr::new(line, ty));
+            }
+            is_exhaustive = true;
+        }
+        if !is_exhaustive {
+            self.symbols.set_unassigned(&unassigned);
+        }
+        Ok(())
+    }
+
+    // resolves 'Enum::Variant' or 'Enum::Variant(a, _)' for a match on an enum that holds values
+    fn analyse_variant_pattern(
+        &self,
+        pattern: &Expr<'a>,
+        enum_name: &str,
+        line: u32,
+    ) -> Result<Pattern<'a>, SemErr> {
+        let (inst, property, args) = match &pattern.expr {
+            ExprType::Colon { inst, property } => (inst, property, &vec![]),
+            ExprType::MethodCall {
+                inst,
+                property,
+                args,
+                is_static: true,
+            } => (inst, property, args),
+            _ => return Err(SemErr::new(line, SemErrType::NonConstantPattern)),
+        };
+
+        let (ty, tag) = self.get_enum_variant_data(inst, property, line)?;
+        if ty != ValueType::DataEnum(enum_name.to_string()) {
+            let value_ty = ValueType::DataEnum(enum_name.to_string());
+            let ty = SemErrType::PatternTypeMismatch(value_ty, ty);
+            return Err(SemErr::new(line, ty));
+        }
+
+        let values_len = self.entities.enums[enum_name][tag as usize].1.len();
+        if args.len() != values_len {
+            let name = format!("{enum_name}::{property}");
+            let ty = SemErrType::IncorrectArity(name, values_len as u8, args.len() as u8);
+            return Err(SemErr::new(line, ty));
+        }
+
+        let mut bindings = vec![];
+        for arg in args {
+            match arg.expr {
+                ExprType::Identifier("_") => bindings.push(None),
+                ExprType::Identifier(name) => bindings.push(Some(name)),
+                _ => return Err(SemErr::new(line, SemErrType::InvalidPatternBinding)),
+            }
+        }
+
+        let tag = tag as u8;
+        Ok(Pattern::Variant { tag, bindings })
+    }
+
+    // checks the values that are given to a variant of an enum
+    fn analyse_variant_values(
+        &mut self,
+        enum_name: &str,
+        tag: u64,
+        args: &mut [Expr<'a>],
+        line: u32,
+    ) -> Result<(), SemErr> {
+        let (variant, mut values) = self.entities.enums[enum_name][tag as usize].clone();
+        for ty in &mut values {
+            self.entities.resolve_value_ty(ty);
+        }
+
+        let name = format!("{enum_name}::{variant}");
+        self.check_if_params_and_args_correspond(args, values, name, line)?;
+        Ok(())
+    }
+
+    fn analyse_pattern(
+        &mut self,
+        pattern: &mut Expr<'a>,
+        value_ty: &ValueType,
+        line: u32,
+    ) -> Result<(), SemErr> {
+        let pattern_ty = self.analyse_expr(pattern)?;
+
+        // negative numbers are folded so they can be compared directly
+        if let ExprType::Unary {
+            prefix: TokenType::Minus,
+            value,
+        } = &pattern.expr
+        {
+            match value.expr {
+                ExprType::Lit(Literal::I64(n)) => pattern.expr = ExprType::Lit(Literal::I64(-n)),
+                ExprType::Lit(Literal::F64(n)) => pattern.expr = ExprType::Lit(Literal::F64(-n)),
+                _ => (),
+            }
+        }
+        if !matches!(pattern.expr, ExprType::Lit(_)) {
+            return Err(SemErr::new(line, SemErrType::NonConstantPattern));
+        }
+
+        if pattern_ty != *value_ty && !try_coerce(&mut pattern.expr, value_ty) {
+            let ty = SemErrType::PatternTypeMismatch(value_ty.clone(), pattern_ty);
+            return Err(SemErr::new(line, ty));
+        }
+        Ok(())
+    }
+
     fn get_enum_variant_data(
         &self,
         inst: &Expr<'a>,
@@ -385,9 +1179,11 @@ impl<'a> Analyser<'a> {
             let ty = SemErrType::InvalidStaticAccess;
             return Err(SemErr::new(line, ty));
         };
-        for (index, var) in variants.iter().enumerate() {
+        for (index, (var, _)) in variants.iter().enumerate() {
             if *var == property {
-                return Ok((ValueType::Enum(name.to_string()), index as u64));
+                let mut ty = ValueType::UnknownType(name.to_string());
+                self.entities.resolve_value_ty(&mut ty);
+                return Ok((ty, index as u64));
             }
         }
 
@@ -395,9 +1191,37 @@ impl<'a> Analyser<'a> {
         Err(SemErr::new(line, ty))
     }
 
+    // the value of 'value?', the error is returned so the function has to return the same kind of
+    // value with an error that fits
+    fn analyse_try(&mut self, value: &mut Expr<'a>, line: u32) -> Result<ValueType, SemErr> {
+        let mut value_ty = self.analyse_expr(value)?;
+        self.entities.resolve_value_ty(&mut value_ty);
+        check_non_null(&value_ty, line)?;
+        let ValueType::Generic(generic) = &value_ty else {
+            return Err(SemErr::new(line, SemErrType::InvalidTry(value_ty)));
+        };
+        if generic.name != RESULT && generic.name != OPTION {
+            return Err(SemErr::new(line, SemErrType::InvalidTry(value_ty)));
+        }
+
+        let return_ty = self.current_return_ty.clone().unwrap_or(ValueType::Null);
+        let returns_error = match &return_ty {
+            ValueType::Generic(returned) if returned.name == generic.name => {
+                // an Option has no error that has to fit
+                generic.name == OPTION || fits(&generic.args[1], &returned.args[1])
+            }
+            _ => false,
+        };
+        if !returns_error {
+            let ty = SemErrType::TryReturnMismatch(value_ty, return_ty);
+            return Err(SemErr::new(line, ty));
+        }
+        Ok(generic.args[0].clone())
+    }
+
     fn analyse_func_stmt(
         &mut self,
-        return_ty: ValueType,
+        mut return_ty: ValueType,
         parameters: &mut Vec<(ValueType, &'a str)>,
         line: u32,
         body: &mut [Stmt<'a>],
@@ -412,13 +1236,16 @@ impl<'a> Analyser<'a> {
         let prev_use_self = self.current_use_self;
         let return_ty_is_null = return_ty == ValueType::Null;
 
+        self.resolve_ty(&mut return_ty);
+        self.check_type_args(&return_ty, line)?;
         self.current_return_ty = Some(return_ty);
         self.current_use_self = use_self;
 
         self.symbols.begin_scope();
 
         for (ty, name) in parameters {
-            self.entities.resolve_value_ty(ty);
+            self.resolve_ty(ty);
+            self.check_type_args(ty, line)?;
             self.symbols.declare(Symbol::new(name, ty.clone()), line)?;
         }
         self.return_stmt_found = false;
@@ -427,10 +1254,6 @@ impl<'a> Analyser<'a> {
             self.analyse_stmt(stmt)?;
         }
 
-        if let Some(func) = self.entities.funcs.get_mut(name) {
-            func.body = body.to_owned();
-        }
-
         if !return_ty_is_null && !self.return_stmt_found {
             let ty =
                 SemErrType::NoReturnTy(name.to_string(), self.current_return_ty.clone().unwrap());
@@ -444,6 +1267,176 @@ impl<'a> Analyser<'a> {
         Ok(())
     }
 
+    fn analyse_lambda(
+        &mut self,
+        parameters: &mut [(ValueType, &'a str)],
+        return_ty: &mut ValueType,
+        body: &mut [Stmt<'a>],
+        line: u32,
+    ) -> Result<ValueType, SemErr> {
+        self.resolve_ty(return_ty);
+
+        // a lambda is analysed like a function that is nested in the current one
+        let prev_return_ty = self.current_return_ty.replace(return_ty.clone());
+        let prev_return_stmt_found = std::mem::replace(&mut self.return_stmt_found, false);
+        let prev_use_self = std::mem::replace(&mut self.current_use_self, false);
+
+        // the lambda can be called at any time, so its assignments don't count outside of it
+        let unassigned = self.symbols.unassigned();
+        self.loop_depth += 1;
+        self.symbols.begin_scope();
+        let prev_lambda_depth = self.lambda_depth.replace(self.symbols.depth() - 1);
+        for (ty, name) in parameters.iter_mut() {
+            self.resolve_ty(ty);
+            self.check_type_args(ty, line)?;
+            self.symbols.declare(Symbol::new(name, ty.clone()), line)?;
+        }
+        for stmt in body.iter_mut() {
+            self.analyse_stmt(stmt)?;
+        }
+        self.lambda_depth = prev_lambda_depth;
+        self.symbols.end_scope();
+        self.loop_depth -= 1;
+        self.symbols.set_unassigned(&unassigned);
+
+        if *return_ty != ValueType::Null && !self.return_stmt_found {
+            let ty = SemErrType::NoReturnTy("lambda".to_string(), return_ty.clone());
+            return Err(SemErr::new(line, ty));
+        }
+
+        self.current_return_ty = prev_return_ty;
+        self.return_stmt_found = prev_return_stmt_found;
+        self.current_use_self = prev_use_self;
+
+        Ok(ValueType::Func {
+            parameters: parameters.iter().map(|(ty, _)| ty.clone()).collect(),
+            return_ty: Box::new(return_ty.clone()),
+        })
+    }
+
+    // the type of a named function that is used as a value, natives with overloads can't be
+    fn get_func_ref_data(&self, name: &str, line: u32) -> Result<(ValueType, usize), SemErr> {
+        let (mut parameters, mut return_ty) = if let Some(data) = self.entities.funcs.get(name) {
+            if !data.type_params.is_empty() {
+                let ty = SemErrType::GenericFuncAsValue(name.to_string());
+                return Err(SemErr::new(line, ty));
+            }
+            let parameters = data.parameters.iter().map(|(ty, _)| ty.clone()).collect();
+            (parameters, data.return_ty.clone())
+        } else if let Some([data]) = self.entities.nat_funcs.get(name).map(Vec::as_slice) {
+            (data.parameters.clone(), data.return_ty.clone())
+        } else if self.entities.nat_funcs.contains_key(name) {
+            let ty = SemErrType::OverloadedFuncAsValue(name.to_string());
+            return Err(SemErr::new(line, ty));
+        } else {
+            let ty = SemErrType::UndefinedVar(name.to_string());
+            return Err(SemErr::new(line, ty));
+        };
+
+        for ty in parameters.iter_mut() {
+            self.entities.resolve_value_ty(ty);
+        }
+        self.entities.resolve_value_ty(&mut return_ty);
+        let ty = ValueType::Func {
+            parameters,
+            return_ty: Box::new(return_ty),
+        };
+        Ok((ty, 0))
+    }
+
+    // the index of the 'fn display(self): str' method of a user defined struct
+    fn get_display_method(&self, ty: &ValueType) -> Option<u8> {
+        let data = self.entities.structs.get(ty.struct_name()?)?;
+        let index = data.methods.iter().position(|(name, method)| {
+            *name == "display"
+                && method.use_self
+                && method.parameters.is_empty()
+                && method.return_ty == ValueType::Str
+        })?;
+        Some(index as u8)
+    }
+
+    fn resolve_ty(&self, ty: &mut ValueType) {
+        if !self.type_bindings.is_empty() {
+            *ty = ty.substitute(&self.type_bindings);
+        }
+        self.entities.resolve_value_ty(ty);
+    }
+
+    // returns the index of the instance of the generic function for the type arguments in
+    // 'bindings' and its return type, the instance is analysed if it doesn't exist yet
+    fn instantiate_func(
+        &mut self,
+        name: &'a str,
+        bindings: HashMap<String, ValueType>,
+        line: u32,
+    ) -> Result<(usize, ValueType), SemErr> {
+        let data = &self.entities.funcs[name];
+        let mut type_args = vec![];
+        for param in &data.type_params {
+            let Some(ty) = bindings.get(*param) else {
+                let ty = SemErrType::UninferredTypeParam(name.to_string(), param.to_string());
+                return Err(SemErr::new(line, ty));
+            };
+            type_args.push(ty.clone());
+        }
+
+        let mut return_ty = data.return_ty.substitute(&bindings);
+        self.entities.resolve_value_ty(&mut return_ty);
+        if let Some(index) = data
+            .instances
+            .iter()
+            .position(|(args, _)| *args == type_args)
+        {
+            return Ok((index, return_ty));
+        }
+        if self.instance_depth == MAX_INSTANCE_DEPTH {
+            let ty = SemErrType::InstanceDepth(name.to_string());
+            return Err(SemErr::new(line, ty));
+        }
+
+        let template_return_ty = data.return_ty.clone();
+        let mut parameters = data.parameters.clone();
+        let mut body = data.body.clone();
+        let func_line = data.line;
+
+        // added before the body is analysed so recursive calls use this instance
+        let instances = &mut self.entities.funcs.get_mut(name).unwrap().instances;
+        instances.push((type_args, vec![]));
+        let index = instances.len() - 1;
+
+        // the body can't see the locals of the function that calls it
+        let globals = self.symbols.globals();
+        let symbols = std::mem::replace(&mut self.symbols, globals);
+        let type_bindings = std::mem::replace(&mut self.type_bindings, bindings);
+        let current_return_ty = self.current_return_ty.take();
+        let return_stmt_found = self.return_stmt_found;
+        let current_use_self = self.current_use_self;
+        let current_struct = self.current_struct.take();
+
+        self.instance_depth += 1;
+        let result = self.analyse_func_stmt(
+            template_return_ty,
+            &mut parameters,
+            func_line,
+            &mut body,
+            name,
+            false,
+        );
+
+        self.instance_depth -= 1;
+        self.symbols = symbols;
+        self.type_bindings = type_bindings;
+        self.current_return_ty = current_return_ty;
+        self.return_stmt_found = return_stmt_found;
+        self.current_use_self = current_use_self;
+        self.current_struct = current_struct;
+        result?;
+
+        self.entities.funcs.get_mut(name).unwrap().instances[index].1 = body;
+        Ok((index, return_ty))
+    }
+
     fn analyse_assign(
         &mut self,
         name: &str,
@@ -451,16 +1444,40 @@ impl<'a> Analyser<'a> {
         line: u32,
     ) -> Result<ValueType, SemErr> {
         match self.symbols.resolve(name) {
+            Some(symbol) if symbol.is_const => {
+                let ty = SemErrType::ConstReassignment(name.to_string());
+                Err(SemErr::new(line, ty))
+            }
             Some(symbol) => {
-                let value_ty = self.analyse_expr(value)?;
-                if symbol.ty != value_ty
-                    && symbol.ty != ValueType::Any
-                    && !try_coerce(&mut value.expr, &symbol.ty)
+                let value_ty = self.analyse_value(value, &symbol.ty)?;
+                // a variable that was checked for null can be given its declared type again
+                let (ty, narrowed_depth) = match symbol.narrowed {
+                    Some((declared_ty, depth)) if !self.fits_value(&value_ty, &symbol.ty) => {
+                        (declared_ty, Some(depth))
+                    }
+                    _ => (symbol.ty, None),
+                };
+                if !self.fits_value(&value_ty, &ty)
+                    && ty != ValueType::Any
+                    && !try_coerce(&mut value.expr, &ty)
                 {
-                    let err_ty = SemErrType::VarDeclTypeMismatch(symbol.ty, value_ty);
+                    let err_ty = SemErrType::VarDeclTypeMismatch(ty, value_ty);
                     return Err(SemErr::new(line, err_ty));
                 }
-                Ok(symbol.ty)
+
+                if let Some(depth) = narrowed_depth {
+                    // the check isn't repeated when the loop starts over
+                    if depth < self.loop_depth {
+                        let ty = SemErrType::NullCheckedOutsideLoop(name.to_string());
+                        return Err(SemErr::new(line, ty));
+                    }
+                    self.symbols.widen(name);
+                }
+                self.symbols.assign(name);
+                if let Some(depth) = self.lambda_depth {
+                    self.symbols.assign_in_lambda(name, depth);
+                }
+                Ok(ty)
             }
             None => {
                 let ty = SemErrType::UndefinedVar(name.to_string());
@@ -476,24 +1493,34 @@ impl<'a> Analyser<'a> {
         line: u32,
         args: &mut [Expr<'a>],
         is_static: bool,
-    ) -> Result<(u8, ValueType, bool), SemErr> {
-        let name = self.get_inst_or_struct_name(inst, is_static, line)?;
-
-        for arg in args.iter_mut() {
-            self.analyse_expr(arg)?;
-        }
+    ) -> Result<(u8, ValueType, bool, Dispatch), SemErr> {
+        let (name, bindings) = self.get_inst_or_struct_name(inst, is_static, line)?;
+        let mut dispatch = match STATIC_METHOD_TYPES.into_iter().find(|ty| *ty == name) {
+            Some(methods) => Dispatch::Native(methods),
+            None => Dispatch::Struct,
+        };
 
         let (index, return_ty, use_self, parameters) =
             if let Some(data) = self.entities.structs.get(&name as &str) {
                 data.get_method_data(&name, property, line)?
             } else if let Some(data) = self.entities.nat_structs.get(&name as &str) {
                 data.get_method_data(&name, property, line)?
+            } else if let Some(data) = self.entities.traits.get(&name as &str) {
+                dispatch = Dispatch::Trait(data.id);
+                data.get_method_data(&name, property, line)?
             } else {
                 let ty = SemErrType::UndefinedType(name);
                 return Err(SemErr::new(line, ty));
             };
 
-        self.check_if_params_and_args_correspond(args, parameters, name, line)?;
+        let parameters = parameters
+            .iter()
+            .map(|ty| ty.substitute(&bindings))
+            .collect();
+        let return_ty = return_ty.substitute(&bindings);
+        // binds the type parameters of the method itself
+        let bindings = self.check_if_params_and_args_correspond(args, parameters, name, line)?;
+        let return_ty = return_ty.substitute(&bindings);
 
         if is_static && use_self {
             let ty = SemErrType::SelfOnStaticMethod;
@@ -504,14 +1531,14 @@ impl<'a> Analyser<'a> {
             return Err(SemErr::new(line, ty));
         }
 
-        Ok((index, return_ty, use_self))
+        Ok((index, return_ty, use_self, dispatch))
     }
     fn get_inst_or_struct_name(
         &mut self,
         inst: &mut Box<Expr<'a>>,
         is_static: bool,
         line: u32,
-    ) -> Result<String, SemErr> {
+    ) -> Result<(String, HashMap<String, ValueType>), SemErr> {
         if let ExprType::This = inst.expr {
             let Some(name) = self.current_struct else {
                 let ty = SemErrType::SelfOutsideStruct;
@@ -527,20 +1554,31 @@ impl<'a> Analyser<'a> {
                 let ty = SemErrType::SelfInMethodWithoutSelfParam;
                 return Err(SemErr::new(line, ty));
             }
-            return Ok(name.to_string());
+            return Ok((name.to_string(), HashMap::new()));
         }
         // dbg!(&inst);
         if let ExprType::Identifier(name) = inst.expr {
             if self.entities.structs.contains_key(name)
                 || self.entities.nat_structs.contains_key(name)
             {
-                return Ok(name.to_string());
+                return Ok((name.to_string(), HashMap::new()));
             }
         }
         let mut inst_ty = self.analyse_expr(inst)?;
         self.entities.resolve_value_ty(&mut inst_ty);
+        check_non_null(&inst_ty, line)?;
+        if inst_ty == ValueType::Str {
+            return Ok((STR_METHODS.to_string(), HashMap::new()));
+        }
+        if let ValueType::Trait(name) = inst_ty {
+            if is_static {
+                let ty = SemErrType::StaticMethodOnInstance(name);
+                return Err(SemErr::new(line, ty));
+            }
+            return Ok((name, HashMap::new()));
+        }
 
-        let ValueType::Struct(name) = inst_ty.clone() else {
+        let Some(name) = inst_ty.struct_name().map(str::to_string) else {
             let ty = SemErrType::InvalidTypeMethodAccess(inst_ty);
             return Err(SemErr::new(line, ty));
         };
@@ -550,7 +1588,8 @@ impl<'a> Analyser<'a> {
             return Err(SemErr::new(line, ty));
         }
 
-        Ok(name)
+        let bindings = self.entities.type_bindings(&name, &inst_ty);
+        Ok((name, bindings))
     }
 
     fn analyse_assign_index(
@@ -560,10 +1599,11 @@ impl<'a> Analyser<'a> {
         line: u32,
     ) -> Result<ValueType, SemErr> {
         let arr = self.analyse_expr(arr)?;
+        check_non_null(&arr, line)?;
         Ok(match arr {
             ValueType::Arr(ty) => {
                 let value_ty = self.analyse_expr(value)?;
-                if value_ty != *ty {
+                if !self.fits_value(&value_ty, &ty) {
                     let ty = SemErrType::AssignArrTypeMismatch(*ty, value_ty);
                     return Err(SemErr::new(line, ty));
                 }
@@ -576,6 +1616,15 @@ impl<'a> Analyser<'a> {
         })
     }
 
+    fn analyse_index(&mut self, index: &mut Expr<'a>, line: u32) -> Result<(), SemErr> {
+        let index_ty = self.analyse_expr(index)?;
+        if index_ty != ValueType::I64 && index_ty != ValueType::U64 {
+            let ty = SemErrType::InvalidIndexTy(index_ty);
+            return Err(SemErr::new(line, ty));
+        }
+        Ok(())
+    }
+
     fn analyse_array_expr(
         &mut self,
         values: &mut [Expr<'a>],
@@ -584,14 +1633,24 @@ impl<'a> Analyser<'a> {
         if values.is_empty() {
             return Ok(ValueType::Arr(Box::new(ValueType::Any)));
         }
-        let el_ty = self.analyse_expr(&mut values[0])?;
+        let mut el_ty = self.analyse_expr(&mut values[0])?;
         for el in values.iter_mut().skip(1) {
             let next_el_ty = self.analyse_expr(el)?;
+            if next_el_ty == el_ty || try_coerce(&mut el.expr, &el_ty) {
+                continue;
+            }
 
-            if next_el_ty != el_ty && !try_coerce(&mut el.expr, &el_ty) {
+            // null mixed with values makes an array of the nullable type
+            let nullable_ty = match (&el_ty, &next_el_ty) {
+                (ValueType::Null, ty) | (ty, ValueType::Null) => ValueType::nullable(ty.clone()),
+                (ValueType::Nullable(_), _) => el_ty.clone(),
+                _ => next_el_ty.clone(),
+            };
+            if !fits(&el_ty, &nullable_ty) || !fits(&next_el_ty, &nullable_ty) {
                 let err_ty = SemErrType::ArrElTypeMismatch(el_ty, next_el_ty);
                 return Err(SemErr::new(line, err_ty));
             }
+            el_ty = nullable_ty;
         }
         Ok(ValueType::Arr(Box::new(el_ty)))
     }
@@ -604,11 +1663,40 @@ impl<'a> Analyser<'a> {
         line: u32,
     ) -> Result<ValueType, SemErr> {
         let mut left_ty = self.analyse_expr(left)?;
+
+        // 'x != null && x > 0' only reads the second 'x' after the check, and the right side
+        // might not run at all
+        let checked = match op {
+            BinaryOp::And => self.null_checked(left, true),
+            BinaryOp::Or => self.null_checked(left, false),
+            _ => vec![],
+        };
+        let unassigned = self.symbols.unassigned();
+        self.symbols.begin_scope();
+        self.narrow(&checked);
         let mut right_ty = self.analyse_expr(right)?;
+        self.symbols.end_scope();
+        if matches!(op, BinaryOp::And | BinaryOp::Or) {
+            self.symbols.set_unassigned(&unassigned);
+        }
+
         self.entities.resolve_value_ty(&mut left_ty);
         self.entities.resolve_value_ty(&mut right_ty);
 
+        // a nullable value can be compared with null or a value of its type, but nothing else
+        if matches!(op, BinaryOp::Equal | BinaryOp::NotEqual) {
+            left_ty = left_ty.non_null().clone();
+            right_ty = right_ty.non_null().clone();
+        } else {
+            check_non_null(&left_ty, line)?;
+            check_non_null(&right_ty, line)?;
+        }
+
+        // any value can be compared with null
+        let is_null_check = matches!(op, BinaryOp::Equal | BinaryOp::NotEqual)
+            && (left_ty == ValueType::Null || right_ty == ValueType::Null);
         if left_ty != right_ty
+            && !is_null_check
             && !try_coerce(&mut right.expr, &left_ty)
             && !try_coerce(&mut left.expr, &right_ty)
         {
@@ -645,6 +1733,7 @@ impl<'a> Analyser<'a> {
         line: u32,
     ) -> Result<ValueType, SemErr> {
         let value_ty = self.analyse_expr(value)?;
+        check_non_null(&value_ty, line)?;
 
         match prefix {
             TokenType::Minus => {
@@ -673,7 +1762,7 @@ impl<'a> Analyser<'a> {
         parameters: Vec<ValueType>,
         name: String,
         line: u32,
-    ) -> Result<(), SemErr> {
+    ) -> Result<HashMap<String, ValueType>, SemErr> {
         if args.len() != parameters.len() {
             let err_ty = SemErrType::IncorrectArity(
                 name.to_string(),
@@ -683,13 +1772,26 @@ impl<'a> Analyser<'a> {
             return Err(SemErr::new(line, err_ty));
         }
 
+        // the types that type parameters are bound to by the arguments
+        let mut bindings = HashMap::new();
         for (i, arg) in args.iter_mut().enumerate() {
-            let arg_ty = self.analyse_expr(arg)?;
+            let mut param_ty = parameters[i].clone();
+            self.entities.resolve_value_ty(&mut param_ty);
+            let param_ty = &param_ty;
+
+            let arg_ty = self.analyse_value(arg, param_ty)?;
             // self.entities.resolve_value_ty(&mut arg_ty);
 
-            let param_ty = &parameters[i];
+            if param_ty.has_type_params() {
+                if !bind_type_params(param_ty, &arg_ty, &mut bindings) {
+                    let param_ty = param_ty.substitute(&bindings);
+                    let err_ty = SemErrType::ParamTypeMismatch(name.to_string(), param_ty, arg_ty);
+                    return Err(SemErr::new(line, err_ty));
+                }
+                continue;
+            }
 
-            let is_exact_match = arg_ty == *param_ty;
+            let is_exact_match = self.fits_value(&arg_ty, param_ty);
             let is_any = *param_ty == ValueType::Any;
             let is_array_match = matches!(param_ty, ValueType::Arr(inner) if **inner == ValueType::Any)
                 && matches!(arg_ty, ValueType::Arr(_));
@@ -697,19 +1799,18 @@ impl<'a> Analyser<'a> {
             let is_type_match = match (param_ty, &arg_ty) {
                 (ValueType::UnknownType(name_1), ValueType::Struct(name_2)) => name_1 == name_2,
                 (ValueType::UnknownType(name_1), ValueType::Enum(name_2)) => name_1 == name_2,
+                (ValueType::UnknownType(name_1), ValueType::DataEnum(name_2)) => name_1 == name_2,
                 _ => false,
             };
             let can_coerce = !try_coerce(&mut arg.expr, param_ty);
 
             if !is_exact_match && !is_any && !is_array_match && can_coerce && !is_type_match {
-                dbg!(param_ty);
-                dbg!(&arg_ty);
                 let err_ty =
                     SemErrType::ParamTypeMismatch(name.to_string(), param_ty.clone(), arg_ty);
                 return Err(SemErr::new(line, err_ty));
             }
         }
-        Ok(())
+        Ok(bindings)
     }
 
     fn analyse_dot(
@@ -719,6 +1820,7 @@ impl<'a> Analyser<'a> {
         line: u32,
         property: &str,
     ) -> Result<(ValueType, ExprType<'a>), SemErr> {
+        let mut bindings = HashMap::new();
         let name = if let ExprType::This = inst.expr {
             let Some(name) = self.current_struct else {
                 let ty = SemErrType::SelfOutsideStruct;
@@ -733,24 +1835,34 @@ impl<'a> Analyser<'a> {
         } else {
             let mut inst_ty = self.analyse_expr(inst)?;
             self.entities.resolve_value_ty(&mut inst_ty);
+            check_non_null(&inst_ty, line)?;
 
-            let ValueType::Struct(name) = inst_ty else {
+            let Some(name) = inst_ty.struct_name() else {
                 let ty = SemErrType::InvalidTypeFieldAccess(inst_ty);
                 return Err(SemErr::new(line, ty));
             };
-            name
+            bindings = self.entities.type_bindings(name, &inst_ty);
+            name.to_string()
         };
-        let Some(data) = self.entities.structs.get(&name as &str) else {
+        let (index, mut field_ty) = if let Some(data) = self.entities.structs.get(&name as &str) {
+            let index = data.get_field_index(name, property, line)?;
+            let field_ty = data.fields[index as usize].0.substitute(&bindings);
+            (index, field_ty)
+        } else if let Some(data) = self.entities.nat_structs.get(&name as &str) {
+            let index = data.get_field_index(name, property, line)?;
+            let field_ty = data.fields[index as usize].0.substitute(&bindings);
+            (index, field_ty)
+        } else {
             let ty = SemErrType::UndefinedType(name);
             return Err(SemErr::new(line, ty));
         };
-
-        let index = data.get_field_index(name, property, line)?;
-        let field_ty = data.fields[index as usize].clone().0;
+        self.entities.resolve_value_ty(&mut field_ty);
 
         let expr = if let Some(new_value) = new_value {
-            let new_value_ty = self.analyse_expr(new_value)?;
-            if new_value_ty != field_ty && !try_coerce(&mut new_value.expr, &field_ty) {
+            let new_value_ty = self.analyse_value(new_value, &field_ty)?;
+            if !self.fits_value(&new_value_ty, &field_ty)
+                && !try_coerce(&mut new_value.expr, &field_ty)
+            {
                 let err_ty = SemErrType::FieldTypeMismatch(field_ty, new_value_ty);
                 return Err(SemErr::new(line, err_ty));
             }
@@ -769,6 +1881,189 @@ impl<'a> Analyser<'a> {
         Ok((field_ty, expr))
     }
 
+    // like 'fits', but an instance of a struct also fits a trait the struct implements
+    fn fits_value(&self, value: &ValueType, target: &ValueType) -> bool {
+        match (value, target) {
+            (ValueType::Struct(name), ValueType::Trait(trait_name)) => {
+                self.entities.implements(name, trait_name)
+            }
+            (ValueType::Nullable(value), ValueType::Nullable(target)) => {
+                self.fits_value(value, target)
+            }
+            (value, ValueType::Nullable(target)) if *value != ValueType::Null => {
+                self.fits_value(value, target)
+            }
+            _ => fits(value, target),
+        }
+    }
+
+    // an array literal that is stored where an array of a trait is expected gets that type, so
+    // it can hold instances of different structs
+    fn analyse_value(
+        &mut self,
+        value: &mut Expr<'a>,
+        target: &ValueType,
+    ) -> Result<ValueType, SemErr> {
+        // a generic struct takes the type arguments of the target, so its fields can hold structs
+        // that implement a trait, like 'Vec<Named> v = Vec([B(1)])'
+        if let (ExprType::FuncCall { name, args, index }, ValueType::Generic(generic)) =
+            (&mut value.expr, target.non_null())
+        {
+            // 'Result' and 'Option' have no constructor
+            let is_struct = self.entities.structs.contains_key(name)
+                || (self.entities.nat_structs.contains_key(name)
+                    && !STATIC_METHOD_TYPES.contains(name));
+            if generic.name == *name && is_struct && self.symbols.resolve(name).is_none() {
+                let (_, parameters) = self.get_called_func_data(name, value.line)?;
+                let bindings = self.entities.type_bindings(name, target.non_null());
+                let parameters = parameters
+                    .iter()
+                    .map(|ty| ty.substitute(&bindings))
+                    .collect();
+
+                // when the arguments don't fit, the type arguments are inferred from them instead
+                let mut attempt = args.clone();
+                let result = self.check_if_params_and_args_correspond(
+                    &mut attempt,
+                    parameters,
+                    name.to_string(),
+                    value.line,
+                );
+                if result.is_ok() {
+                    *args = attempt;
+                    *index = Some(0);
+                    return Ok(target.non_null().clone());
+                }
+            }
+        }
+
+        let (ExprType::Array(values), ValueType::Arr(el_ty)) = (&mut value.expr, target.non_null())
+        else {
+            return self.analyse_expr(value);
+        };
+        if values.is_empty() || !matches!(el_ty.non_null(), ValueType::Trait(_)) {
+            return self.analyse_expr(value);
+        }
+
+        for el in values {
+            let value_ty = self.analyse_expr(el)?;
+            if !self.fits_value(&value_ty, el_ty) {
+                let ty = SemErrType::ArrElTypeMismatch(*el_ty.clone(), value_ty);
+                return Err(SemErr::new(value.line, ty));
+            }
+        }
+        Ok(target.non_null().clone())
+    }
+
+    // generic structs need exactly as many type arguments as they have type parameters
+    fn check_type_args(&self, ty: &ValueType, line: u32) -> Result<(), SemErr> {
+        match ty {
+            ValueType::Struct(name) if !self.entities.type_params(name).is_empty() => {
+                let count = self.entities.type_params(name).len() as u8;
+                let ty = SemErrType::TypeArgCount(name.clone(), count, 0);
+                Err(SemErr::new(line, ty))
+            }
+            ValueType::Generic(generic) => {
+                let name = &generic.name;
+                if !self.entities.structs.contains_key(name as &str)
+                    && !self.entities.nat_structs.contains_key(name as &str)
+                {
+                    let ty = SemErrType::UndefinedType(name.clone());
+                    return Err(SemErr::new(line, ty));
+                }
+                let count = self.entities.type_params(name).len() as u8;
+                let found = generic.args.len() as u8;
+                if count != found {
+                    let ty = SemErrType::TypeArgCount(name.clone(), count, found);
+                    return Err(SemErr::new(line, ty));
+                }
+                for arg in &generic.args {
+                    self.check_type_args(arg, line)?;
+                }
+                Ok(())
+            }
+            ValueType::Arr(inner) | ValueType::Nullable(inner) => self.check_type_args(inner, line),
+            ValueType::Func {
+                parameters,
+                return_ty,
+            } => {
+                for ty in parameters {
+                    self.check_type_args(ty, line)?;
+                }
+                self.check_type_args(return_ty, line)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    // the nullable variables that hold a value when 'condition' evaluates to 'when', from checks
+    // like 'x != null' that are combined with '&&', '||' and '!'
+    fn null_checked(&self, condition: &Expr<'a>, when: bool) -> Vec<(&'a str, ValueType)> {
+        match &condition.expr {
+            ExprType::Binary { left, op, right } => match op {
+                BinaryOp::Equal | BinaryOp::NotEqual
+                    if matches!(op, BinaryOp::NotEqual) == when =>
+                {
+                    let name = match (&left.expr, &right.expr) {
+                        (ExprType::Identifier(name), ExprType::Lit(Literal::Null))
+                        | (ExprType::Lit(Literal::Null), ExprType::Identifier(name)) => *name,
+                        _ => return vec![],
+                    };
+                    match self.resolve_symbol(name) {
+                        Some(Symbol {
+                            ty: ValueType::Nullable(ty),
+                            ..
+                        }) => vec![(name, *ty)],
+                        _ => vec![],
+                    }
+                }
+                BinaryOp::And if when => {
+                    let mut checked = self.null_checked(left, true);
+                    checked.extend(self.null_checked(right, true));
+                    checked
+                }
+                BinaryOp::Or if !when => {
+                    let mut checked = self.null_checked(left, false);
+                    checked.extend(self.null_checked(right, false));
+                    checked
+                }
+                _ => vec![],
+            },
+            ExprType::Unary {
+                prefix: TokenType::Bang,
+                value,
+            } => self.null_checked(value, !when),
+            _ => vec![],
+        }
+    }
+
+    // a lambda can be called after a variable that was checked outside of it is set to null, so
+    // inside a lambda such a variable has its declared type
+    fn resolve_symbol(&self, name: &str) -> Option<Symbol<'a>> {
+        match self.lambda_depth {
+            Some(depth) => self.symbols.resolve_from(name, depth),
+            None => self.symbols.resolve(name),
+        }
+    }
+
+    // gives checked variables their type without null in the current scope, globals are left
+    // alone in functions and variables that lambdas assign are left alone everywhere, because
+    // any call could set them to null
+    fn narrow(&mut self, checked: &[(&'a str, ValueType)]) {
+        for (name, ty) in checked {
+            let is_closure_assigned = self
+                .symbols
+                .resolve(name)
+                .is_some_and(|symbol| symbol.is_closure_assigned);
+            if is_closure_assigned {
+                continue;
+            }
+            if self.current_return_ty.is_none() || !self.symbols.is_global(name) {
+                self.symbols.narrow(name, ty.clone(), self.loop_depth);
+            }
+        }
+    }
+
     fn get_called_func_data(
         &mut self,
         name: &'a str,
@@ -788,7 +2083,8 @@ impl<'a> Analyser<'a> {
 
         if let Some(data) = self.entities.funcs.get(name) {
             let parameters = data.parameters.iter().map(|p| p.0.clone()).collect();
-            let return_ty = data.return_ty.clone();
+            let mut return_ty = data.return_ty.clone();
+            self.entities.resolve_value_ty(&mut return_ty);
 
             return Ok((return_ty, parameters));
         };
@@ -798,9 +2094,177 @@ impl<'a> Analyser<'a> {
     }
 }
 
+// moves the methods of every 'impl' at the top level into the struct it implements the trait for,
+// they're analysed and called like the other methods of the struct
+fn merge_impls<'a>(stmts: &mut Vec<Stmt<'a>>) -> Result<Vec<Impl<'a>>, SemErr> {
+    let mut impls = vec![];
+    let mut i = 0;
+    while i < stmts.len() {
+        if !matches!(stmts[i].stmt, StmtType::Impl { .. }) {
+            i += 1;
+            continue;
+        }
+        let line = stmts[i].line;
+        let StmtType::Impl {
+            trait_name,
+            struct_name,
+            methods,
+        } = stmts.remove(i).stmt
+        else {
+            unreachable!()
+        };
+
+        let target = stmts.iter_mut().find_map(|stmt| match &mut stmt.stmt {
+            StmtType::Struct {
+                name,
+                methods,
+                type_params,
+                ..
+            } if *name == struct_name && type_params.is_empty() => Some(methods),
+            _ => None,
+        });
+        let Some(struct_methods) = target else {
+            let ty = SemErrType::InvalidImplTarget(trait_name.to_string(), struct_name.to_string());
+            return Err(SemErr::new(line, ty));
+        };
+
+        let mut names = vec![];
+        for method in methods {
+            let StmtType::Func { name, .. } = method.stmt else {
+                unreachable!()
+            };
+            let is_duplicate = struct_methods.iter().any(
+                |existing| matches!(existing.stmt, StmtType::Func { name: other, .. } if other == name),
+            );
+            if is_duplicate {
+                let ty = SemErrType::DuplicateMethod(struct_name.to_string(), name.to_string());
+                return Err(SemErr::new(method.line, ty));
+            }
+            names.push(name);
+            struct_methods.push(method);
+        }
+
+        impls.push(Impl {
+            trait_name,
+            struct_name,
+            methods: names,
+            line,
+        });
+    }
+    Ok(impls)
+}
+
+// whether the code after 'stmt' can't be reached from it
+fn diverges(stmt: &Stmt) -> bool {
+    match &stmt.stmt {
+        StmtType::Return(_) | StmtType::Throw(_) | StmtType::Break | StmtType::Continue => true,
+        StmtType::Block(stmts) => stmts.iter().any(diverges),
+        StmtType::If {
+            body,
+            final_else: Some(final_else),
+            ..
+        } => diverges(body) && diverges(final_else),
+        _ => false,
+    }
+}
+
+// nullable values have to be checked with '!= null' before they're used
+fn check_non_null(ty: &ValueType, line: u32) -> Result<(), SemErr> {
+    match ty {
+        ValueType::Nullable(_) => Err(SemErr::new(line, SemErrType::PossiblyNull(ty.clone()))),
+        _ => Ok(()),
+    }
+}
+
+// whether a value of type 'value' can be stored where a 'target' is expected, empty arrays
+// have elements of type 'Any' so they fit any array type, and null fits any nullable type
+fn fits(value: &ValueType, target: &ValueType) -> bool {
+    match (value, target) {
+        (ValueType::Any, _) => true,
+        (ValueType::Null, ValueType::Nullable(_)) => true,
+        (ValueType::Nullable(value), ValueType::Nullable(target)) => fits(value, target),
+        (value, ValueType::Nullable(target)) => fits(value, target),
+        (ValueType::Arr(value), ValueType::Arr(target)) => fits(value, target),
+        // the open type arguments of 'ok(1)' and 'none()' are 'Any', but a native struct without
+        // type arguments, like 'Vec', only fits other untyped ones, otherwise either side could
+        // add elements of the wrong type to the other
+        (ValueType::Generic(value), ValueType::Generic(target)) => {
+            let is_open = value.name == RESULT || value.name == OPTION;
+            value.name == target.name
+                && value.args.len() == target.args.len()
+                && value.args.iter().zip(&target.args).all(|(value, target)| {
+                    let is_untyped = *value == ValueType::Any || *target == ValueType::Any;
+                    if is_untyped && !is_open {
+                        value == target
+                    } else {
+                        fits(value, target)
+                    }
+                })
+        }
+        _ => value == target,
+    }
+}
+
+// binds the type parameters in 'param' to the matching parts of 'arg', a parameter that is
+// already bound has to fit with what it is bound to
+fn bind_type_params(
+    param: &ValueType,
+    arg: &ValueType,
+    bindings: &mut HashMap<String, ValueType>,
+) -> bool {
+    match (param, arg) {
+        (ValueType::TypeParam(name), _) => match bindings.get(name) {
+            Some(bound) if fits(arg, bound) => true,
+            // the parameter was bound by an empty array before
+            Some(bound) if fits(bound, arg) => {
+                bindings.insert(name.clone(), arg.clone());
+                true
+            }
+            Some(_) => false,
+            None => {
+                bindings.insert(name.clone(), arg.clone());
+                true
+            }
+        },
+        (ValueType::Arr(param), ValueType::Arr(arg)) => bind_type_params(param, arg, bindings),
+        (ValueType::Nullable(_), ValueType::Null) => true,
+        (ValueType::Nullable(param), ValueType::Nullable(arg)) => {
+            bind_type_params(param, arg, bindings)
+        }
+        (ValueType::Nullable(param), _) => bind_type_params(param, arg, bindings),
+        (ValueType::Generic(param), ValueType::Generic(arg)) => {
+            param.name == arg.name
+                && param.args.len() == arg.args.len()
+                && param
+                    .args
+                    .iter()
+                    .zip(&arg.args)
+                    .all(|(param, arg)| bind_type_params(param, arg, bindings))
+        }
+        (
+            ValueType::Func {
+                parameters: params,
+                return_ty: param_return_ty,
+            },
+            ValueType::Func {
+                parameters: args,
+                return_ty: arg_return_ty,
+            },
+        ) => {
+            params.len() == args.len()
+                && params
+                    .iter()
+                    .zip(args)
+                    .all(|(param, arg)| bind_type_params(param, arg, bindings))
+                && bind_type_params(param_return_ty, arg_return_ty, bindings)
+        }
+        _ => fits(arg, param),
+    }
+}
+
 fn try_coerce(expr: &mut ExprType, target: &ValueType) -> bool {
     match expr {
-        ExprType::Lit(lit) => match (&lit, target) {
+        ExprType::Lit(lit) => match (&lit, target.non_null()) {
             (Literal::I64(n), ValueType::U64) => {
                 *lit = Literal::U64(*n as u64);
                 true
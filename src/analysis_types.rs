@@ -57,18 +57,24 @@ pub struct FuncData<'a> {
     pub return_ty: ValueType,
     pub line: u32,
     pub use_self: bool,
+    pub type_params: Vec<&'a str>,
+    // a generic function is analysed for every list of type arguments it's called with, the
+    // body of each instance is compiled separately
+    pub instances: Vec<(Vec<ValueType>, Vec<Stmt<'a>>)>,
 }
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct NatFuncData {
     pub parameters: Vec<ValueType>,
     pub func: NativeFunc,
     pub return_ty: ValueType,
     pub use_self: bool,
 }
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct NatStructData<'a> {
     pub fields: Vec<(ValueType, &'a str)>,
     pub methods: Vec<(&'a str, NatFuncData)>,
+    // the fields and methods use these as 'ValueType::TypeParam'
+    pub type_params: Vec<&'a str>,
 }
 impl<'a> NatStructData<'a> {
     pub fn get_method_data(
@@ -86,17 +92,36 @@ impl<'a> NatStructData<'a> {
         let ty = SemErrType::InvalidMethod(name.to_string(), property.to_string());
         Err(SemErr::new(line, ty))
     }
+
+    pub fn get_field_index(&self, name: String, property: &str, line: u32) -> Result<u8, SemErr> {
+        match self
+            .fields
+            .iter()
+            .position(|(_, field_name)| *field_name == property)
+        {
+            Some(index) => Ok(index as u8),
+            None => {
+                let ty = SemErrType::InvalidPubField(name, property.to_string());
+                Err(SemErr::new(line, ty))
+            }
+        }
+    }
 }
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct StructData<'a> {
     pub fields: Vec<(ValueType, &'a str)>,
     pub methods: Vec<(&'a str, FuncData<'a>)>,
+    pub type_params: Vec<&'a str>,
+    // every implemented trait with the index of the struct method for each of its methods
+    pub impls: Vec<(&'a str, Vec<u8>)>,
 }
 impl<'a> StructData<'a> {
-    pub fn new(fields: Vec<(ValueType, &'a str)>) -> Self {
+    pub fn new(fields: Vec<(ValueType, &'a str)>, type_params: Vec<&'a str>) -> Self {
         Self {
             fields,
             methods: vec![],
+            type_params,
+            impls: vec![],
         }
     }
 
@@ -132,12 +157,39 @@ impl<'a> StructData<'a> {
     }
 }
 
+#[derive(Debug, Clone)]
+pub struct TraitData<'a> {
+    // the index of the trait in the vtables of an instance
+    pub id: u8,
+    pub methods: Vec<(&'a str, FuncData<'a>)>,
+}
+impl<'a> TraitData<'a> {
+    pub fn get_method_data(
+        &self,
+        name: &str,
+        property: &str,
+        line: u32,
+    ) -> Result<(u8, ValueType, bool, Vec<ValueType>), SemErr> {
+        for (index, (method_name, data)) in self.methods.iter().enumerate() {
+            if *method_name == property {
+                let params = data.parameters.iter().map(|p| p.0.clone()).collect();
+                return Ok((index as u8, data.return_ty.clone(), data.use_self, params));
+            }
+        }
+        let ty = SemErrType::NotTraitMethod(name.to_string(), property.to_string());
+        Err(SemErr::new(line, ty))
+    }
+}
+
+#[derive(Clone)]
 pub struct EnityData<'a> {
     pub funcs: HashMap<&'a str, FuncData<'a>>,
     pub nat_funcs: HashMap<&'a str, Vec<NatFuncData>>,
     pub structs: HashMap<&'a str, StructData<'a>>,
     pub nat_structs: HashMap<&'a str, NatStructData<'a>>,
-    pub enums: HashMap<&'a str, Vec<&'a str>>,
+    // every variant with the types of the values it holds
+    pub enums: HashMap<&'a str, Vec<(&'a str, Vec<ValueType>)>>,
+    pub traits: HashMap<&'a str, TraitData<'a>>,
 }
 impl<'a> EnityData<'a> {
     pub fn new() -> Self {
@@ -147,30 +199,124 @@ impl<'a> EnityData<'a> {
             structs: HashMap::new(),
             nat_structs: HashMap::new(),
             enums: HashMap::new(),
+            traits: HashMap::new(),
         }
     }
 
+    pub fn implements(&self, struct_name: &str, trait_name: &str) -> bool {
+        self.structs.get(struct_name).is_some_and(|data| {
+            data.impls
+                .iter()
+                .any(|(implemented, _)| *implemented == trait_name)
+        })
+    }
+
     pub fn resolve_value_ty(&self, ty: &mut ValueType) {
-        if let ValueType::UnknownType(name) = ty {
-            if self.structs.contains_key(name as &str)
+        if let ValueType::Arr(inner) | ValueType::Nullable(inner) = ty {
+            self.resolve_value_ty(inner);
+        } else if let ValueType::Generic(generic) = ty {
+            for ty in &mut generic.args {
+                self.resolve_value_ty(ty);
+            }
+        } else if let ValueType::Func {
+            parameters,
+            return_ty,
+        } = ty
+        {
+            for ty in parameters {
+                self.resolve_value_ty(ty);
+            }
+            self.resolve_value_ty(return_ty);
+        } else if let ValueType::UnknownType(name) = ty {
+            // the type parameters of a native struct that is used without type arguments can
+            // be anything, like the elements of the untyped 'Vec' that came before them
+            let nat_type_params = self
+                .nat_structs
+                .get(name as &str)
+                .map(|d| d.type_params.len());
+            if let Some(count @ 1..) = nat_type_params {
+                *ty = ValueType::generic(name.clone(), vec![ValueType::Any; count])
+            } else if self.structs.contains_key(name as &str)
                 || self.nat_structs.contains_key(name as &str)
             {
                 *ty = ValueType::Struct(name.clone())
-            } else if self.enums.contains_key(name as &str) {
-                *ty = ValueType::Enum(name.clone())
+            } else if self.traits.contains_key(name as &str) {
+                *ty = ValueType::Trait(name.clone())
+            } else if let Some(variants) = self.enums.get(name as &str) {
+                let holds_values = variants.iter().any(|(_, values)| !values.is_empty());
+                *ty = if holds_values {
+                    ValueType::DataEnum(name.clone())
+                } else {
+                    ValueType::Enum(name.clone())
+                };
             }
         }
     }
+
+    pub fn type_params(&self, name: &str) -> &[&'a str] {
+        if let Some(data) = self.structs.get(name) {
+            &data.type_params
+        } else if let Some(data) = self.nat_structs.get(name) {
+            &data.type_params
+        } else {
+            &[]
+        }
+    }
+
+    /// Binds the type parameters of the struct `name` to the type arguments of `ty`.
+    pub fn type_bindings(&self, name: &str, ty: &ValueType) -> HashMap<String, ValueType> {
+        let ValueType::Generic(generic) = ty else {
+            return HashMap::new();
+        };
+        let params = self.type_params(name).iter().map(|param| param.to_string());
+        params.zip(generic.args.iter().cloned()).collect()
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct Symbol<'a> {
-    name: &'a str,
+    pub name: &'a str,
     pub ty: ValueType,
+    pub is_const: bool,
+    // false for a variable that was declared without a value and can't be read yet
+    pub is_assigned: bool,
+    // the declared type of a variable that was checked for null, and the loop depth of the check
+    pub narrowed: Option<(ValueType, usize)>,
+    // true for a variable that a lambda assigns, the lambda could set it to null at any call
+    pub is_closure_assigned: bool,
 }
 impl<'a> Symbol<'a> {
     pub fn new(name: &'a str, ty: ValueType) -> Self {
-        Self { name, ty }
+        Self {
+            name,
+            ty,
+            is_const: false,
+            is_assigned: true,
+            narrowed: None,
+            is_closure_assigned: false,
+        }
+    }
+
+    pub fn constant(name: &'a str, ty: ValueType) -> Self {
+        Self {
+            name,
+            ty,
+            is_const: true,
+            is_assigned: true,
+            narrowed: None,
+            is_closure_assigned: false,
+        }
+    }
+
+    pub fn unassigned(name: &'a str, ty: ValueType) -> Self {
+        Self {
+            name,
+            ty,
+            is_const: false,
+            is_assigned: false,
+            narrowed: None,
+            is_closure_assigned: false,
+        }
     }
 }
 
@@ -204,6 +350,13 @@ impl<'a> SemanticScope<'a> {
         Ok(())
     }
 
+    /// A scope that only holds the global variables.
+    pub fn globals(&self) -> Self {
+        Self {
+            stack: vec![self.stack[0].clone()],
+        }
+    }
+
     pub fn resolve(&self, name: &str) -> Option<Symbol<'a>> {
         for scope in self.stack.iter().rev() {
             if let Some(sym) = scope.get(name) {
@@ -212,4 +365,113 @@ impl<'a> SemanticScope<'a> {
         }
         None
     }
+
+    /// The number of scopes that have begun and haven't ended yet.
+    pub fn depth(&self) -> usize {
+        self.stack.len()
+    }
+
+    /// Marks the variable `name` as assigned by a lambda if it's declared outside the lambda,
+    /// whose scopes start at depth `lambda_depth`.
+    pub fn assign_in_lambda(&mut self, name: &str, lambda_depth: usize) {
+        let innermost = self
+            .stack
+            .iter()
+            .rposition(|scope| scope.contains_key(name));
+        if innermost.is_some_and(|depth| depth < lambda_depth) {
+            for scope in self.stack.iter_mut() {
+                if let Some(symbol) = scope.get_mut(name) {
+                    symbol.is_closure_assigned = true;
+                }
+            }
+        }
+    }
+
+    /// Like `resolve`, but a variable that was narrowed in a scope below `depth` has its declared
+    /// type again.
+    pub fn resolve_from(&self, name: &str, depth: usize) -> Option<Symbol<'a>> {
+        let (index, symbol) = self
+            .stack
+            .iter()
+            .enumerate()
+            .rev()
+            .find_map(|(index, scope)| scope.get(name).map(|symbol| (index, symbol)))?;
+        let mut symbol = symbol.clone();
+        if index < depth {
+            if let Some((declared_ty, _)) = symbol.narrowed.take() {
+                symbol.ty = declared_ty;
+            }
+        }
+        Some(symbol)
+    }
+
+    pub fn is_global(&self, name: &str) -> bool {
+        let innermost = self
+            .stack
+            .iter()
+            .rposition(|scope| scope.contains_key(name));
+        innermost == Some(0)
+    }
+
+    /// The variables that haven't been assigned a value yet, with the depth of their scope.
+    pub fn unassigned(&self) -> Vec<(usize, &'a str)> {
+        let mut unassigned = vec![];
+        for (depth, scope) in self.stack.iter().enumerate() {
+            for symbol in scope.values().filter(|symbol| !symbol.is_assigned) {
+                unassigned.push((depth, symbol.name));
+            }
+        }
+        unassigned
+    }
+
+    /// Marks the variables from `unassigned` as unassigned again, after analysing a branch that
+    /// might not run. Variables from scopes that have ended are skipped.
+    pub fn set_unassigned(&mut self, unassigned: &[(usize, &'a str)]) {
+        for (depth, name) in unassigned {
+            if let Some(symbol) = self.stack.get_mut(*depth).and_then(|s| s.get_mut(name)) {
+                symbol.is_assigned = false;
+            }
+        }
+    }
+
+    pub fn assign(&mut self, name: &str) {
+        for scope in self.stack.iter_mut().rev() {
+            if let Some(symbol) = scope.get_mut(name) {
+                symbol.is_assigned = true;
+                return;
+            }
+        }
+    }
+
+    /// After a statement like 'return' the following code can't be reached, so every variable
+    /// counts as assigned there.
+    pub fn assign_all(&mut self) {
+        for symbol in self.stack.iter_mut().flat_map(HashMap::values_mut) {
+            symbol.is_assigned = true;
+        }
+    }
+
+    /// Shadows the variable `name` in the current scope with a copy of type `ty`, used after a
+    /// null check at loop depth `depth`.
+    pub fn narrow(&mut self, name: &'a str, ty: ValueType, depth: usize) {
+        if let Some(mut symbol) = self.resolve(name) {
+            let declared_ty = std::mem::replace(&mut symbol.ty, ty);
+            symbol.narrowed = Some((declared_ty, depth));
+            self.stack.last_mut().unwrap().insert(name, symbol);
+        }
+    }
+
+    /// Gives every copy of the narrowed variable `name` its declared type again, after it's
+    /// assigned a value that might be null.
+    pub fn widen(&mut self, name: &str) {
+        for scope in self.stack.iter_mut().rev() {
+            let Some(symbol) = scope.get_mut(name) else {
+                continue;
+            };
+            let Some((declared_ty, _)) = symbol.narrowed.take() else {
+                return;
+            };
+            symbol.ty = declared_ty;
+        }
+    }
 }
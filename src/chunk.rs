@@ -1,4 +1,4 @@
-use crate::{OpCode, StackValue};
+use crate::{module::decode_line, object::Object, OpCode, StackValue};
 
 #[derive(Debug)]
 pub struct Chunk {
@@ -24,94 +24,184 @@ impl Chunk {
         self.lines.push(line);
     }
 
+    pub fn get_line(&self, offset: usize) -> u32 {
+        self.lines[offset]
+    }
+
     pub fn add_constant(&mut self, value: StackValue) -> usize {
         self.constants.push(value);
         self.constants.len() - 1
     }
 
-    // pub fn disassemble(&mut self, name: &str) {
-    //     println!("== {} ==", name);
-    //
-    //     let mut offset = 0;
-    //     while offset < self.code.len() {
-    //         offset = self.disassemble_instruction(offset);
-    //     }
-    // }
+    pub fn disassemble(&self, name: &str) -> String {
+        let mut out = format!("== {name} ==\n");
+
+        let mut offset = 0;
+        while offset < self.code.len() {
+            offset = self.disassemble_instruction(offset, &mut out);
+        }
+        out
+    }
 
-    pub fn disassemble_instruction(&self, offset: usize) -> usize {
+    pub fn disassemble_instruction(&self, offset: usize, out: &mut String) -> usize {
+        out.push_str(&format!("{offset:04} "));
         if offset > 0 && self.lines[offset] == self.lines[offset - 1] {
-            print!("   | ");
+            out.push_str("   | ");
         } else {
-            print!("\n{} ", self.lines[offset]);
+            out.push_str(&format!("{:>4} ", decode_line(self.lines[offset]).1));
         }
 
         let instruction = self.code[offset];
-        // dbg!(instruction);
         match instruction.into() {
-            OpCode::Return => Self::simple_instruction("OP_RETURN", offset),
-            OpCode::Constant => self.constant_instruction("OP_CONSTANT", offset),
-            OpCode::Pop => Self::simple_instruction("OP_POP", offset),
-            // TODO: update for jump and vars and loop and call
-            OpCode::Jump => Self::simple_instruction("OP_POP", offset),
-            OpCode::JumpIfFalse => Self::simple_instruction("OP_POP", offset),
-            OpCode::Loop => Self::simple_instruction("OP_POP", offset),
-
-            OpCode::AllocInstance => Self::simple_instruction("OP_ALLOC_INSTANCE", offset),
-            OpCode::GetPubField => Self::simple_instruction("OP_GET_PROPERTY", offset),
-            OpCode::SetPubField => Self::simple_instruction("OP_SET_PROPERTY", offset),
-
-            OpCode::AllocArr => Self::simple_instruction("OP_ALLOC_ARRAY", offset),
-            OpCode::IndexArr => Self::simple_instruction("OP_INDEX_ARRAY", offset),
-            OpCode::AssignIndex => Self::simple_instruction("OP_ASSIGN_INDEX", offset),
-
-            OpCode::Print => Self::simple_instruction("OP_PRINT", offset),
-
-            OpCode::FuncCall => Self::simple_instruction("OP_CALL", offset),
-            OpCode::PushMethod => Self::simple_instruction("OP_METHOD_CALL", offset),
-
-            OpCode::GetLocal => self.constant_instruction("OP_GET_LOCAL", offset),
-            OpCode::SetLocal => self.constant_instruction("OP_SET_LOCAL", offset),
-            OpCode::GetSelfField => self.constant_instruction("OP_GET_FIELD", offset),
-            OpCode::SetSelfField => self.constant_instruction("OP_SET_FIELD", offset),
-
-            OpCode::CastToF64 => self.constant_instruction("OP_CAST_TO_F64", offset),
-            OpCode::CastToU64 => self.constant_instruction("OP_CAST_TO_U64", offset),
-            OpCode::CastToI64 => self.constant_instruction("OP_CAST_TO_I64", offset),
-
-            OpCode::Null => Self::simple_instruction("OP_NULL", offset),
-            OpCode::True => Self::simple_instruction("OP_TRUE", offset),
-            OpCode::False => Self::simple_instruction("OP_FALSE", offset),
-
-            OpCode::Negate => Self::simple_instruction("OP_NEGATE", offset),
-            OpCode::Not => Self::simple_instruction("OP_NOT", offset),
-
-            OpCode::Add => Self::simple_instruction("OP_ADD", offset),
-            OpCode::Sub => Self::simple_instruction("OP_SUB", offset),
-            OpCode::Mul => Self::simple_instruction("OP_MUL", offset),
-            OpCode::Div => Self::simple_instruction("OP_DIV", offset),
-
-            OpCode::And => Self::simple_instruction("OP_AND", offset),
-            OpCode::Or => Self::simple_instruction("OP_OR", offset),
-
-            OpCode::Equal => Self::simple_instruction("OP_EQUAL", offset),
-            OpCode::NotEqual => Self::simple_instruction("OP_BANG_EQUAL", offset),
-            OpCode::Greater => Self::simple_instruction("OP_GREATER", offset),
-            OpCode::GreaterEqual => Self::simple_instruction("OP_GREATER_EQUAL", offset),
-            OpCode::Less => Self::simple_instruction("OP_LESS", offset),
-            OpCode::LessEqual => Self::simple_instruction("OP_LESS_EQUAL", offset),
+            OpCode::Return => Self::simple_instruction("OP_RETURN", offset, out),
+            OpCode::Constant => self.constant_instruction("OP_CONSTANT", offset, out),
+            OpCode::Pop => Self::simple_instruction("OP_POP", offset, out),
+
+            OpCode::Jump => self.jump_instruction("OP_JUMP", true, offset, out),
+            OpCode::JumpIfFalse => self.jump_instruction("OP_JUMP_IF_FALSE", true, offset, out),
+            OpCode::Loop => self.jump_instruction("OP_LOOP", false, offset, out),
+            OpCode::TryUnwrap => self.jump_instruction("OP_TRY_UNWRAP", true, offset, out),
+            OpCode::PushHandler => self.jump_instruction("OP_PUSH_HANDLER", true, offset, out),
+            OpCode::PopHandler => Self::simple_instruction("OP_POP_HANDLER", offset, out),
+            OpCode::Throw => Self::simple_instruction("OP_THROW", offset, out),
+
+            OpCode::AllocInstance => self.alloc_instance_instruction(offset, out),
+            OpCode::AllocEnum => self.alloc_enum_instruction(offset, out),
+            OpCode::GetEnumTag => Self::simple_instruction("OP_GET_ENUM_TAG", offset, out),
+            OpCode::GetEnumValue => self.byte_instruction("OP_GET_ENUM_VALUE", offset, out),
+            OpCode::GetPubField => self.byte_instruction("OP_GET_PROPERTY", offset, out),
+            OpCode::SetPubField => self.byte_instruction("OP_SET_PROPERTY", offset, out),
+
+            OpCode::AllocArr => Self::simple_instruction("OP_ALLOC_ARRAY", offset, out),
+            OpCode::IndexArr => Self::simple_instruction("OP_INDEX_ARRAY", offset, out),
+            OpCode::AssignIndex => Self::simple_instruction("OP_ASSIGN_INDEX", offset, out),
+
+            OpCode::Print => Self::simple_instruction("OP_PRINT", offset, out),
+
+            // the argument count includes the called function itself
+            OpCode::FuncCall => self.byte_instruction("OP_CALL", offset, out),
+            OpCode::PushMethod => self.byte_instruction("OP_METHOD_CALL", offset, out),
+            OpCode::PushTraitMethod => self.trait_method_instruction(offset, out),
+
+            OpCode::GetLocal => self.byte_instruction("OP_GET_LOCAL", offset, out),
+            OpCode::SetLocal => self.byte_instruction("OP_SET_LOCAL", offset, out),
+            OpCode::GetGlobal => self.byte_instruction("OP_GET_GLOBAL", offset, out),
+            OpCode::SetGlobal => self.byte_instruction("OP_SET_GLOBAL", offset, out),
+            OpCode::GetUpvalue => self.byte_instruction("OP_GET_UPVALUE", offset, out),
+            OpCode::SetUpvalue => self.byte_instruction("OP_SET_UPVALUE", offset, out),
+            OpCode::CloseUpvalue => Self::simple_instruction("OP_CLOSE_UPVALUE", offset, out),
+            OpCode::Closure => self.closure_instruction(offset, out),
+            OpCode::GetSelfField => self.byte_instruction("OP_GET_FIELD", offset, out),
+            OpCode::SetSelfField => self.byte_instruction("OP_SET_FIELD", offset, out),
+
+            OpCode::CastToF64 => Self::simple_instruction("OP_CAST_TO_F64", offset, out),
+            OpCode::CastToStr => Self::simple_instruction("OP_CAST_TO_STR", offset, out),
+            OpCode::CastToU64 => Self::simple_instruction("OP_CAST_TO_U64", offset, out),
+            OpCode::CastToI64 => Self::simple_instruction("OP_CAST_TO_I64", offset, out),
+
+            OpCode::Null => Self::simple_instruction("OP_NULL", offset, out),
+            OpCode::True => Self::simple_instruction("OP_TRUE", offset, out),
+            OpCode::False => Self::simple_instruction("OP_FALSE", offset, out),
+
+            OpCode::Negate => Self::simple_instruction("OP_NEGATE", offset, out),
+            OpCode::Not => Self::simple_instruction("OP_NOT", offset, out),
+
+            OpCode::Add => Self::simple_instruction("OP_ADD", offset, out),
+            OpCode::Sub => Self::simple_instruction("OP_SUB", offset, out),
+            OpCode::Mul => Self::simple_instruction("OP_MUL", offset, out),
+            OpCode::Div => Self::simple_instruction("OP_DIV", offset, out),
+
+            OpCode::And => Self::simple_instruction("OP_AND", offset, out),
+            OpCode::Or => Self::simple_instruction("OP_OR", offset, out),
+
+            OpCode::Equal => Self::simple_instruction("OP_EQUAL", offset, out),
+            OpCode::NotEqual => Self::simple_instruction("OP_BANG_EQUAL", offset, out),
+            OpCode::Greater => Self::simple_instruction("OP_GREATER", offset, out),
+            OpCode::GreaterEqual => Self::simple_instruction("OP_GREATER_EQUAL", offset, out),
+            OpCode::Less => Self::simple_instruction("OP_LESS", offset, out),
+            OpCode::LessEqual => Self::simple_instruction("OP_LESS_EQUAL", offset, out),
         }
     }
 
-    fn simple_instruction(name: &str, offset: usize) -> usize {
-        println!("{name}");
+    fn simple_instruction(name: &str, offset: usize, out: &mut String) -> usize {
+        out.push_str(&format!("{name}\n"));
         offset + 1
     }
 
-    fn constant_instruction(&self, name: &str, offset: usize) -> usize {
+    fn byte_instruction(&self, name: &str, offset: usize, out: &mut String) -> usize {
+        let operand = self.code[offset + 1];
+        out.push_str(&format!("{name:<18} {operand}\n"));
+        offset + 2
+    }
+
+    fn constant_instruction(&self, name: &str, offset: usize, out: &mut String) -> usize {
         let constant_index = self.code[offset + 1];
-        print!("{name}  {constant_index}:");
-        // println!(" '{}'", self.constants[constant_index as usize].display());
-        // println!();
+        let constant = self.constants[constant_index as usize].display();
+        out.push_str(&format!("{name:<18} {constant_index} '{constant}'\n"));
         offset + 2
     }
+
+    fn jump_instruction(
+        &self,
+        name: &str,
+        forward: bool,
+        offset: usize,
+        out: &mut String,
+    ) -> usize {
+        let jump = ((self.code[offset + 1] as usize) << 8) | self.code[offset + 2] as usize;
+        let target = if forward {
+            offset + 3 + jump
+        } else {
+            (offset + 3).saturating_sub(jump)
+        };
+        out.push_str(&format!("{name:<18} {offset} -> {target}\n"));
+        offset + 3
+    }
+
+    fn alloc_instance_instruction(&self, offset: usize, out: &mut String) -> usize {
+        let methods_len = self.code[offset + 1];
+        let fields_len = self.code[offset + 2];
+        let name = "OP_ALLOC_INSTANCE";
+        out.push_str(&format!(
+            "{name:<18} {methods_len} methods, {fields_len} fields\n"
+        ));
+        offset + 3
+    }
+
+    fn trait_method_instruction(&self, offset: usize, out: &mut String) -> usize {
+        let trait_id = self.code[offset + 1];
+        let index = self.code[offset + 2];
+        let name = "OP_TRAIT_METHOD";
+        out.push_str(&format!("{name:<18} trait {trait_id}, method {index}\n"));
+        offset + 3
+    }
+
+    // followed by a pair of bytes for every upvalue, whether it's a local and its index
+    fn closure_instruction(&self, offset: usize, out: &mut String) -> usize {
+        let mut offset = self.constant_instruction("OP_CLOSURE", offset, out);
+        let constant_index = self.code[offset - 1] as usize;
+        let StackValue::Obj(Object::Func(func)) = self.constants[constant_index] else {
+            unreachable!()
+        };
+
+        for _ in 0..func.data.upvalue_count {
+            let kind = if self.code[offset] == 1 {
+                "local"
+            } else {
+                "upvalue"
+            };
+            let index = self.code[offset + 1];
+            out.push_str(&format!("{offset:04}    |   {kind} {index}\n"));
+            offset += 2;
+        }
+        offset
+    }
+
+    fn alloc_enum_instruction(&self, offset: usize, out: &mut String) -> usize {
+        let tag = self.code[offset + 1];
+        let values_len = self.code[offset + 2];
+        let name = "OP_ALLOC_ENUM";
+        out.push_str(&format!("{name:<18} tag {tag}, {values_len} values\n"));
+        offset + 3
+    }
 }
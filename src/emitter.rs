@@ -1,14 +1,16 @@
 use std::{borrow::BorrowMut, collections::HashMap};
 
 use crate::{
-    analysis_types::{EnityData, FuncData},
-    error::{print_error, EmitErr},
+    analysis_types::EnityData,
+    error::EmitErr,
     expression::{Expr, ExprType},
     func_compiler::FuncCompilerStack,
     heap::Heap,
-    object::{ObjFunc, ObjNative, Object},
+    native::program_args,
+    object::{ObjArr, ObjFunc, ObjNative, Object},
     op_code::OpCode,
-    statement::{Stmt, StmtType},
+    parse_types::BinaryOp,
+    statement::{MatchArm, Pattern, Stmt, StmtType},
     token::{Literal, TokenType},
     value::{StackValue, ValueType},
 };
@@ -18,6 +20,12 @@ pub struct Emitter<'a> {
     comps: FuncCompilerStack<'a>,
     funcs: HashMap<&'a str, Vec<StackValue>>,
     structs: HashMap<&'a str, Vec<(&'a str, StackValue)>>,
+    // the vtables of the structs that implement traits, an array with the methods of every
+    // trait indexed by trait id
+    vtables: HashMap<&'a str, StackValue>,
+    // declarations of the globals, they're initialised at the start of 'main'
+    globals: Vec<Stmt<'a>>,
+    global_slots: HashMap<&'a str, u8>,
 }
 impl<'a> Emitter<'a> {
     fn new() -> Self {
@@ -26,29 +34,104 @@ impl<'a> Emitter<'a> {
             comps: FuncCompilerStack::new(),
             funcs: HashMap::new(),
             structs: HashMap::new(),
+            vtables: HashMap::new(),
+            globals: vec![],
+            global_slots: HashMap::new(),
         }
     }
-    pub fn compile(stmts: Vec<Stmt>, entities: EnityData) -> Option<(ObjFunc, Heap)> {
+    pub fn compile(
+        stmts: Vec<Stmt<'a>>,
+        entities: EnityData<'a>,
+    ) -> Result<(ObjFunc, Heap), EmitErr> {
         let mut comp = Emitter::new();
-        let func = match comp.init_funcs(entities) {
-            Ok(func) => func,
-            Err(err) => {
-                print_error(err.line, &err.msg);
-                return None;
+
+        let (globals, stmts): (Vec<Stmt>, Vec<Stmt>) = stmts
+            .into_iter()
+            .partition(|stmt| matches!(stmt.stmt, StmtType::Var { .. }));
+        for global in &globals {
+            let StmtType::Var { name, .. } = global.stmt else {
+                unreachable!()
+            };
+            comp.add_global(name, global.line)?;
+        }
+        comp.globals = globals;
+
+        // the analyser made sure there is a main function
+        let func = comp.init_funcs(entities)?.unwrap();
+
+        for stmt in stmts {
+            comp.emit_stmt(stmt)?;
+        }
+
+        Ok((func, comp.heap))
+    }
+
+    /// Compiles a REPL input into a function whose first locals are the slots of `globals`, the
+    /// variables declared by earlier inputs. The variables it declares at the top level are
+    /// globals as well. Also returns the amount of globals it has at the end.
+    pub fn compile_repl(
+        stmts: Vec<Stmt<'a>>,
+        globals: &[&'a str],
+        entities: EnityData<'a>,
+    ) -> Result<(ObjFunc, Heap, usize), EmitErr> {
+        let mut comp = Emitter::new();
+
+        for name in globals {
+            comp.add_global(name, 1)?;
+        }
+        for stmt in &stmts {
+            if let StmtType::Var { name, .. } = stmt.stmt {
+                comp.add_global(name, stmt.line)?;
             }
-        };
+        }
+
+        // 'main' isn't special in the REPL, it can be called like any other function
+        if let Some(main) = comp.init_funcs(entities)? {
+            let StackValue::Obj(Object::Func(mut func)) = comp.funcs["main"][0] else {
+                unreachable!()
+            };
+            func.data = main;
+        }
+
+        comp.comps.push("repl".to_string());
+        comp.comps.begin_scope();
+        // 'const' is a keyword, so the slots of the globals are only accessed through
+        // 'global_slots' and lambdas don't capture them
+        for _ in globals {
+            comp.comps.add_local("const", 1)?;
+        }
 
+        let line = stmts.last().map_or(1, |stmt| stmt.line);
         for stmt in stmts {
-            if let Err(err) = comp.emit_stmt(stmt) {
-                print_error(err.line, &err.msg);
-                return None;
+            match stmt.stmt {
+                StmtType::Var { value, .. } => {
+                    comp.comps.add_local("const", stmt.line)?;
+                    match value {
+                        Some(value) => comp.emit_expr(&value)?,
+                        None => comp.comps.emit_byte(OpCode::Null as u8, stmt.line),
+                    }
+                }
+                _ => comp.emit_stmt(stmt)?,
             }
         }
 
-        Some((func, comp.heap))
+        let global_count = comp.comps.get_local_count() - 1;
+        let func = comp.comps.end_compiler(line);
+
+        Ok((func, comp.heap, global_count))
     }
 
-    fn init_funcs(&mut self, mut entities: EnityData<'a>) -> Result<ObjFunc, EmitErr> {
+    // the globals are the first locals of the function that runs first, after the function itself
+    fn add_global(&mut self, name: &'a str, line: u32) -> Result<(), EmitErr> {
+        let slot = self.global_slots.len() + 1;
+        if slot >= u8::MAX as usize {
+            return Err(EmitErr::new(line, "Too many globals."));
+        }
+        self.global_slots.insert(name, slot as u8);
+        Ok(())
+    }
+
+    fn init_funcs(&mut self, mut entities: EnityData<'a>) -> Result<Option<ObjFunc>, EmitErr> {
         for (name, data) in entities.nat_funcs.drain() {
             let mut values = vec![];
             for data in data {
@@ -60,18 +143,29 @@ impl<'a> Emitter<'a> {
             self.funcs.insert(name, values);
         }
 
-        // insert dummy function objects for recursion
+        // insert dummy function objects for recursion, a generic function gets one for every
+        // instance
         let mut func_objs = Vec::new();
-        let func_data: Vec<(&'a str, FuncData<'a>)> = entities.funcs.drain().collect();
-        for (name, _) in func_data.iter() {
-            let dummy = ObjFunc::new(name.to_string());
-            let (func_obj, _) = self.heap.alloc_permanent(dummy, Object::Func);
+        let mut func_data = Vec::new();
+        for (name, mut data) in entities.funcs.drain() {
+            let bodies = if data.type_params.is_empty() {
+                vec![std::mem::take(&mut data.body)]
+            } else {
+                data.instances.drain(..).map(|(_, body)| body).collect()
+            };
+
+            let mut values = vec![];
+            for body in bodies {
+                let dummy = ObjFunc::new(name.to_string());
+                let (func_obj, _) = self.heap.alloc_permanent(dummy, Object::Func);
 
-            self.funcs.insert(name, vec![StackValue::Obj(func_obj)]);
-            func_objs.push(func_obj);
+                values.push(StackValue::Obj(func_obj));
+                func_objs.push(func_obj);
+                func_data.push((name, data.parameters.clone(), body, data.line));
+            }
+            self.funcs.insert(name, values);
         }
 
-        let mut method_objs = Vec::new();
         for (struct_name, data) in &entities.structs {
             let mut methods = vec![];
             for (name, _) in &data.methods {
@@ -79,11 +173,25 @@ impl<'a> Emitter<'a> {
                 let (func_obj, _) = self.heap.alloc_permanent(dummy, Object::Func);
 
                 methods.push((*name, StackValue::Obj(func_obj)));
-                method_objs.push(func_obj);
             }
             self.structs.insert(struct_name, methods);
         }
 
+        for (struct_name, data) in &entities.structs {
+            if data.impls.is_empty() {
+                continue;
+            }
+            let mut vtables = vec![StackValue::Null; entities.traits.len()];
+            for (trait_name, indices) in &data.impls {
+                let methods = &self.structs[struct_name];
+                let vtable = indices.iter().map(|i| methods[*i as usize].1).collect();
+                let (vtable, _) = self.heap.alloc_permanent(ObjArr::new(vtable), Object::Arr);
+                vtables[entities.traits[trait_name].id as usize] = StackValue::Obj(vtable);
+            }
+            let (vtables, _) = self.heap.alloc_permanent(ObjArr::new(vtables), Object::Arr);
+            self.vtables.insert(struct_name, StackValue::Obj(vtables));
+        }
+
         for (struct_name, data) in entities.nat_structs {
             let mut methods = vec![];
             for (name, data) in &data.methods {
@@ -96,16 +204,20 @@ impl<'a> Emitter<'a> {
         }
 
         let mut main_func_obj = None;
-        for (i, (name, data)) in func_data.into_iter().enumerate() {
-            let line = data.line;
-
+        for (i, (name, parameters, body, line)) in func_data.into_iter().enumerate() {
             self.comps.push(name.to_string());
             self.comps.begin_scope();
-            for (_, name) in data.parameters {
+            if name == "main" {
+                self.emit_globals(line)?;
+                if !parameters.is_empty() {
+                    self.emit_program_args(line)?;
+                }
+            }
+            for (_, name) in parameters {
                 self.comps.add_local(name, line)?;
             }
 
-            for stmt in data.body {
+            for stmt in body {
                 self.emit_stmt(stmt)?;
             }
 
@@ -123,12 +235,16 @@ impl<'a> Emitter<'a> {
             }
         }
 
-        for (_, data) in entities.structs {
+        for (struct_name, data) in entities.structs {
             for (i, (name, data)) in data.methods.into_iter().enumerate() {
                 let line = data.line;
 
                 self.comps.push(name.to_string());
                 self.comps.begin_scope();
+                // the instance is passed in the slot after the method itself
+                if data.use_self {
+                    self.comps.add_local("self", line)?;
+                }
                 for (_, name) in data.parameters {
                     self.comps.add_local(name, line)?;
                 }
@@ -140,14 +256,57 @@ impl<'a> Emitter<'a> {
                 self.comps.emit_return(line);
 
                 let compiled_func = self.comps.end_compiler(line);
-                if let Object::Func(ref mut func) = method_objs[i].borrow_mut() {
+                let StackValue::Obj(mut method_obj) = self.structs[struct_name][i].1 else {
+                    unreachable!()
+                };
+                if let Object::Func(ref mut func) = method_obj.borrow_mut() {
                     func.data = compiled_func;
                 } else {
                     unreachable!()
                 }
             }
         }
-        Ok(main_func_obj.unwrap())
+        Ok(main_func_obj)
+    }
+
+    // every global is set to null before the first one is initialised, so functions that are
+    // called by an initialiser can't overwrite the slots of the globals after it
+    fn emit_globals(&mut self, line: u32) -> Result<(), EmitErr> {
+        let globals = std::mem::take(&mut self.globals);
+        for global in &globals {
+            let StmtType::Var { name, .. } = global.stmt else {
+                unreachable!()
+            };
+            self.comps.add_local(name, line)?;
+            self.comps.emit_byte(OpCode::Null as u8, line);
+        }
+
+        for global in globals {
+            // a global without a value is nullable, so it can stay null
+            let StmtType::Var {
+                name,
+                value: Some(value),
+                ..
+            } = global.stmt
+            else {
+                continue;
+            };
+            self.emit_expr(&value)?;
+            let slot = self.global_slots[name];
+            self.comps
+                .emit_bytes(OpCode::SetLocal as u8, slot, global.line);
+            self.comps.emit_byte(OpCode::Pop as u8, global.line);
+        }
+        Ok(())
+    }
+
+    // pushes the command-line arguments for 'fn main(str[] args)'
+    fn emit_program_args(&mut self, line: u32) -> Result<(), EmitErr> {
+        let func = ObjNative::new("args".to_string(), program_args);
+        let (func, _) = self.heap.alloc_permanent(func, Object::Native);
+        self.comps.emit_constant(StackValue::Obj(func), line)?;
+        self.comps.emit_bytes(OpCode::FuncCall as u8, 1, line);
+        Ok(())
     }
 
     fn emit_stmt(&mut self, stmt: Stmt<'a>) -> Result<(), EmitErr> {
@@ -162,9 +321,12 @@ impl<'a> Emitter<'a> {
                 self.emit_expr(&expr)?;
                 self.comps.emit_byte(OpCode::Print as u8, line);
             }
-            StmtType::Var { name, value, ty: _ } => {
+            StmtType::Var { name, value, .. } => {
                 self.comps.add_local(name, line)?;
-                self.emit_expr(&value)?;
+                match value {
+                    Some(value) => self.emit_expr(&value)?,
+                    None => self.comps.emit_byte(OpCode::Null as u8, line),
+                }
             }
             StmtType::Block(stmts) => {
                 self.comps.begin_scope();
@@ -247,14 +409,39 @@ impl<'a> Emitter<'a> {
                 self.comps.patch_breaks()?;
 
                 // necessary so the variable goes out of scope again
-                self.comps.emit_byte(OpCode::Pop as u8, line);
-                self.comps.decrement_local_count();
+                self.comps.pop_local(line);
             }
+            StmtType::Match { value, arms } => self.emit_match(value, arms, line)?,
             StmtType::Func { .. } => {}
             StmtType::Return(value) => {
                 self.emit_expr(&value)?;
                 self.comps.emit_byte(OpCode::Return as u8, line);
             }
+            // the handler jumps to the catch body with the thrown value as its first local
+            StmtType::Try {
+                body,
+                error,
+                catch_body,
+            } => {
+                let handler_jump = self.comps.emit_jump(OpCode::PushHandler, line);
+                self.comps.begin_try();
+                self.emit_stmt(*body)?;
+                self.comps.end_try();
+                self.comps.emit_byte(OpCode::PopHandler as u8, line);
+                let end_jump = self.comps.emit_jump(OpCode::Jump, line);
+
+                self.comps.patch_jump(handler_jump)?;
+                self.comps.begin_scope();
+                self.comps.add_local(error, line)?;
+                self.emit_stmt(*catch_body)?;
+                self.comps.end_scope();
+
+                self.comps.patch_jump(end_jump)?;
+            }
+            StmtType::Throw(value) => {
+                self.emit_expr(&value)?;
+                self.comps.emit_byte(OpCode::Throw as u8, line);
+            }
             StmtType::Break => {
                 self.comps.add_break(line)?;
             }
@@ -263,16 +450,140 @@ impl<'a> Emitter<'a> {
             }
             StmtType::Struct { .. } => (),
             StmtType::Enum { .. } => (),
+            StmtType::Trait { .. } => (),
+            StmtType::Impl { .. } => (),
+        }
+        Ok(())
+    }
+
+    // the value is stored in a hidden local, every arm compares it against its patterns and
+    // jumps to the next arm if none of them match
+    fn emit_match(
+        &mut self,
+        value: Expr<'a>,
+        arms: Vec<MatchArm<'a>>,
+        line: u32,
+    ) -> Result<(), EmitErr> {
+        self.comps.begin_scope();
+        // 'match' is a keyword, so it can't clash with a variable
+        self.comps.add_local("match", line)?;
+        self.emit_expr(&value)?;
+        let slot = self.comps.get_local_count() as u8 - 1;
+
+        let mut end_jumps = vec![];
+        for arm in arms {
+            let line = arm.line;
+            let mut body_jumps = vec![];
+            let mut next_arm_jump = None;
+
+            // the values a variant binds for the body, only allowed for a single pattern
+            let bindings = match arm.patterns.as_slice() {
+                [Pattern::Variant { bindings, .. }] => bindings.clone(),
+                _ => vec![],
+            };
+
+            let pattern_count = arm.patterns.len();
+            for (i, pattern) in arm.patterns.into_iter().enumerate() {
+                match pattern {
+                    // always matches, falls through into the body
+                    Pattern::Wildcard => break,
+                    Pattern::Variant { tag, .. } => {
+                        self.comps.emit_bytes(OpCode::GetLocal as u8, slot, line);
+                        self.comps.emit_byte(OpCode::GetEnumTag as u8, line);
+                        self.comps
+                            .emit_constant(StackValue::U64(tag as u64), line)?;
+                        self.comps.emit_byte(OpCode::Equal as u8, line);
+                    }
+                    Pattern::Value(expr) => {
+                        self.comps.emit_bytes(OpCode::GetLocal as u8, slot, line);
+                        self.emit_expr(&expr)?;
+                        self.comps.emit_byte(OpCode::Equal as u8, line);
+                    }
+                    Pattern::Range { start, end } => {
+                        self.comps.emit_bytes(OpCode::GetLocal as u8, slot, line);
+                        self.emit_expr(&start)?;
+                        self.comps.emit_byte(OpCode::GreaterEqual as u8, line);
+                        self.comps.emit_bytes(OpCode::GetLocal as u8, slot, line);
+                        self.emit_expr(&end)?;
+                        self.comps.emit_byte(OpCode::Less as u8, line);
+                        self.comps.emit_byte(OpCode::And as u8, line);
+                    }
+                }
+
+                let no_match_jump = self.comps.emit_jump(OpCode::JumpIfFalse, line);
+                self.comps.emit_byte(OpCode::Pop as u8, line);
+                if i == pattern_count - 1 {
+                    next_arm_jump = Some(no_match_jump);
+                } else {
+                    body_jumps.push(self.comps.emit_jump(OpCode::Jump, line));
+                    self.comps.patch_jump(no_match_jump)?;
+                    self.comps.emit_byte(OpCode::Pop as u8, line);
+                }
+            }
+
+            for jump in body_jumps {
+                self.comps.patch_jump(jump)?;
+            }
+            self.comps.begin_scope();
+            for (index, binding) in bindings.into_iter().enumerate() {
+                if let Some(name) = binding {
+                    self.comps.add_local(name, line)?;
+                    self.comps.emit_bytes(OpCode::GetLocal as u8, slot, line);
+                    self.comps
+                        .emit_bytes(OpCode::GetEnumValue as u8, index as u8, line);
+                }
+            }
+            self.emit_stmt(arm.body)?;
+            self.comps.end_scope();
+            end_jumps.push(self.comps.emit_jump(OpCode::Jump, line));
+
+            if let Some(jump) = next_arm_jump {
+                self.comps.patch_jump(jump)?;
+                self.comps.emit_byte(OpCode::Pop as u8, line);
+            }
+        }
+
+        for jump in end_jumps {
+            self.comps.patch_jump(jump)?;
         }
+        self.comps.end_scope();
         Ok(())
     }
 
+    // the body is compiled into its own function, which is wrapped in a closure at runtime
+    fn emit_lambda(
+        &mut self,
+        parameters: &[(ValueType, &'a str)],
+        body: &[Stmt<'a>],
+        line: u32,
+    ) -> Result<(), EmitErr> {
+        self.comps.push("lambda".to_string());
+        self.comps.begin_scope();
+        for (_, name) in parameters {
+            self.comps.add_local(name, line)?;
+        }
+        for stmt in body {
+            self.emit_stmt(stmt.clone())?;
+        }
+        self.comps.emit_return(line);
+
+        let upvalues = self.comps.get_upvalues().to_vec();
+        let func = self.comps.end_compiler(line);
+        let (func, _) = self.heap.alloc_permanent(func, Object::Func);
+        self.comps
+            .emit_closure(StackValue::Obj(func), &upvalues, line)
+    }
+
     fn emit_expr(&mut self, expr: &Expr<'a>) -> Result<(), EmitErr> {
         let line = expr.line;
         match &expr.expr {
             ExprType::FuncCall { name, args, index } => {
                 if let Some(methods) = self.structs.get(name) {
                     let method_len = methods.len() as u8;
+                    match self.vtables.get(name) {
+                        Some(vtables) => self.comps.emit_constant(*vtables, line)?,
+                        None => self.comps.emit_byte(OpCode::Null as u8, line),
+                    }
                     for (_, value) in methods.iter().rev() {
                         self.comps.emit_constant(*value, line)?;
                     }
@@ -295,6 +606,30 @@ impl<'a> Emitter<'a> {
                         .emit_bytes(OpCode::FuncCall as u8, args.len() as u8 + 1, line);
                 }
             }
+            ExprType::CallValue { callee, args } => {
+                self.emit_expr(callee)?;
+                for arg in args {
+                    self.emit_expr(arg)?;
+                }
+                self.comps
+                    .emit_bytes(OpCode::FuncCall as u8, args.len() as u8 + 1, line);
+            }
+            ExprType::FuncRef { name, index } => {
+                let func = self.funcs[name][*index];
+                self.comps.emit_constant(func, line)?;
+            }
+            ExprType::Lambda {
+                parameters, body, ..
+            } => self.emit_lambda(parameters, body, line)?,
+            ExprType::EnumVariant { name, tag, args } => {
+                let (name, _) = self.heap.alloc_permanent(name.to_string(), Object::Str);
+                self.comps.emit_constant(StackValue::Obj(name), line)?;
+                for arg in args {
+                    self.emit_expr(arg)?;
+                }
+                self.comps.emit_bytes(OpCode::AllocEnum as u8, *tag, line);
+                self.comps.emit_byte(args.len() as u8, line);
+            }
             ExprType::Array(arr) => {
                 let arr_len = arr.len() as u64;
                 for value in arr.iter().rev() {
@@ -382,6 +717,39 @@ impl<'a> Emitter<'a> {
 
                 //self.comps.emit_byte(OpCode::Pop as u8, line);
             }
+            // the value is passed to the method like 'self'
+            ExprType::NatMethodCall {
+                methods,
+                inst,
+                index,
+                args,
+            } => {
+                let methods = &self.structs[methods];
+                self.comps.emit_constant(methods[*index as usize].1, line)?;
+                self.emit_expr(inst)?;
+                for arg in args {
+                    self.emit_expr(arg)?;
+                }
+                self.comps
+                    .emit_bytes(OpCode::FuncCall as u8, args.len() as u8 + 2, line);
+            }
+            // the method is taken from the vtables of the instance and gets it as 'self'
+            ExprType::TraitMethodCall {
+                trait_id,
+                inst,
+                index,
+                args,
+            } => {
+                self.emit_expr(inst)?;
+                self.comps
+                    .emit_bytes(OpCode::PushTraitMethod as u8, *trait_id, line);
+                self.comps.emit_byte(*index, line);
+                for arg in args {
+                    self.emit_expr(arg)?;
+                }
+                self.comps
+                    .emit_bytes(OpCode::FuncCall as u8, args.len() as u8 + 2, line);
+            }
             ExprType::Lit(lit) => match lit {
                 Literal::None => unreachable!(),
                 Literal::Str(str) => {
@@ -400,6 +768,10 @@ impl<'a> Emitter<'a> {
             ExprType::Identifier(name) => {
                 if let Some(arg) = self.comps.resolve_local(name) {
                     self.comps.emit_bytes(OpCode::GetLocal as u8, arg, line);
+                } else if let Some(index) = self.comps.resolve_upvalue(name, line)? {
+                    self.comps.emit_bytes(OpCode::GetUpvalue as u8, index, line);
+                } else if let Some(slot) = self.global_slots.get(name) {
+                    self.comps.emit_bytes(OpCode::GetGlobal as u8, *slot, line);
                 } else if let Some(methods) = self.structs.get(name) {
                     self.comps.emit_constant(methods[0].1, line)?;
 
@@ -409,11 +781,15 @@ impl<'a> Emitter<'a> {
                 }
             }
             ExprType::Assign { name, new_value } => {
-                let Some(arg) = self.comps.resolve_local(name) else {
-                    unreachable!()
-                };
                 self.emit_expr(new_value)?;
-                self.comps.emit_bytes(OpCode::SetLocal as u8, arg, line);
+                if let Some(arg) = self.comps.resolve_local(name) {
+                    self.comps.emit_bytes(OpCode::SetLocal as u8, arg, line);
+                } else if let Some(index) = self.comps.resolve_upvalue(name, line)? {
+                    self.comps.emit_bytes(OpCode::SetUpvalue as u8, index, line);
+                } else {
+                    let slot = self.global_slots[name];
+                    self.comps.emit_bytes(OpCode::SetGlobal as u8, slot, line);
+                }
             }
             ExprType::Unary {
                 prefix,
@@ -426,6 +802,32 @@ impl<'a> Emitter<'a> {
                     _ => unreachable!(),
                 }
             }
+            // the right side of '&&' and '||' only runs when it decides the result, so
+            // 'x != null && x > 0' never reads 'x' when it's null
+            ExprType::Binary {
+                left,
+                op: BinaryOp::And,
+                right,
+            } => {
+                self.emit_expr(left)?;
+                let end_jump = self.comps.emit_jump(OpCode::JumpIfFalse, line);
+                self.comps.emit_byte(OpCode::Pop as u8, line);
+                self.emit_expr(right)?;
+                self.comps.patch_jump(end_jump)?;
+            }
+            ExprType::Binary {
+                left,
+                op: BinaryOp::Or,
+                right,
+            } => {
+                self.emit_expr(left)?;
+                let else_jump = self.comps.emit_jump(OpCode::JumpIfFalse, line);
+                let end_jump = self.comps.emit_jump(OpCode::Jump, line);
+                self.comps.patch_jump(else_jump)?;
+                self.comps.emit_byte(OpCode::Pop as u8, line);
+                self.emit_expr(right)?;
+                self.comps.patch_jump(end_jump)?;
+            }
             ExprType::Binary { left, op, right } => {
                 self.emit_expr(left)?;
                 self.emit_expr(right)?;
@@ -438,9 +840,16 @@ impl<'a> Emitter<'a> {
                     ValueType::F64 => self.comps.emit_byte(OpCode::CastToF64 as u8, line),
                     ValueType::I64 => self.comps.emit_byte(OpCode::CastToI64 as u8, line),
                     ValueType::U64 => self.comps.emit_byte(OpCode::CastToU64 as u8, line),
+                    ValueType::Str => self.comps.emit_byte(OpCode::CastToStr as u8, line),
                     _ => unreachable!(),
                 }
             }
+            ExprType::Try(value) => {
+                self.emit_expr(value)?;
+                let unwrapped_jump = self.comps.emit_jump(OpCode::TryUnwrap, line);
+                self.comps.emit_byte(OpCode::Return as u8, line);
+                self.comps.patch_jump(unwrapped_jump)?;
+            }
             ExprType::Dot { .. } => unreachable!(),
             ExprType::DotAssign { .. } => unreachable!(),
             ExprType::MethodCall { .. } => unreachable!(),
@@ -1,56 +1,293 @@
+use std::fmt;
+
 use colored::Colorize;
 
-use crate::{analysis_types::Operator, value::ValueType};
+use crate::{analysis_types::Operator, module::decode_line, value::ValueType};
 
-pub const PRINT_TOKENS: bool = false;
-pub const PRINT_PARSE_TREE: bool = false;
-pub const DEBUG_TRACE_EXECUTION: bool = false;
-pub const PRINT_HEAP: bool = false;
+/// Debug output that is printed while compiling and running a program.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DebugOptions {
+    /// Print the tokens produced by the scanner.
+    pub tokens: bool,
+    /// Print the statements produced by the parser.
+    pub ast: bool,
+    /// Print the bytecode of every compiled function.
+    pub disasm: bool,
+    /// Print the stack and every instruction while it's being executed.
+    pub trace: bool,
+    /// Print the heap after every garbage collection.
+    pub heap: bool,
+}
 
-pub fn print_error(line: u32, msg: &str) {
-    let l = "[line ".blue();
-    // let line = line.to_string().magenta();
-    let closing_bracket = "]".blue();
+pub fn print_error(file: &Option<String>, line: u32, msg: &str) {
+    let location = format!("[{}]", location(file, line)).blue();
     let i = " Error: ".bright_red();
     let msg = msg.yellow();
-    println!("{l}{line}{closing_bracket}{i}{msg}");
+    println!("{location}{i}{msg}");
+}
+
+// errors in imported modules also mention the file they occurred in
+fn location(file: &Option<String>, line: u32) -> String {
+    match file {
+        Some(file) => format!("line {line} in {file}"),
+        None => format!("line {line}"),
+    }
+}
+
+/// Any error that can occur while compiling or running a Crust program.
+#[derive(Debug)]
+pub enum CrustErr {
+    Scan(Vec<ScanErr>),
+    Parse(Vec<ParseErr>),
+    Sem(SemErr),
+    Emit(EmitErr),
+    Runtime(RuntimeErr),
+    /// The program called 'exit' with this code.
+    Exit(i32),
+}
+impl CrustErr {
+    pub fn print(&self) {
+        match self {
+            CrustErr::Scan(errs) => {
+                for err in errs {
+                    print_error(&err.file, err.line, &err.msg);
+                }
+                let msg = "Scan error(s) detected.";
+                println!("{}", msg.purple());
+            }
+            CrustErr::Parse(errs) => {
+                for err in errs {
+                    print_error(&err.file, err.line, &err.msg);
+                }
+                let msg = "Parse error(s) detected.";
+                println!("{}", msg.purple());
+            }
+            CrustErr::Sem(err) => err.print(),
+            CrustErr::Emit(err) => print_error(&err.file, err.line, &err.msg),
+            CrustErr::Runtime(err) => err.print(),
+            CrustErr::Exit(_) => (),
+        }
+    }
+
+    pub fn is_runtime_err(&self) -> bool {
+        matches!(self, CrustErr::Runtime(_))
+    }
+
+    /// Splits the encoded lines of the error into the line and the file they refer to, `files`
+    /// holds the name of every imported file by index. See [`crate::module::encode_line`].
+    pub(crate) fn locate(mut self, files: &[Option<String>]) -> Self {
+        let locate = |line: &mut u32, file: &mut Option<String>| {
+            let (index, local_line) = decode_line(*line);
+            *line = local_line;
+            *file = files[index].clone();
+        };
+
+        match &mut self {
+            CrustErr::Scan(errs) => {
+                for err in errs {
+                    locate(&mut err.line, &mut err.file);
+                }
+            }
+            CrustErr::Parse(errs) => {
+                for err in errs {
+                    locate(&mut err.line, &mut err.file);
+                }
+            }
+            CrustErr::Sem(err) => locate(&mut err.line, &mut err.file),
+            CrustErr::Emit(err) => locate(&mut err.line, &mut err.file),
+            CrustErr::Runtime(err) => {
+                locate(&mut err.line, &mut err.file);
+                for (_, line) in &mut err.trace {
+                    *line = decode_line(*line).1;
+                }
+            }
+            CrustErr::Exit(_) => (),
+        }
+        self
+    }
+}
+impl fmt::Display for CrustErr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CrustErr::Scan(errs) => {
+                for err in errs {
+                    writeln!(f, "{err}")?;
+                }
+                Ok(())
+            }
+            CrustErr::Parse(errs) => {
+                for err in errs {
+                    writeln!(f, "{err}")?;
+                }
+                Ok(())
+            }
+            CrustErr::Sem(err) => write!(f, "{err}"),
+            CrustErr::Emit(err) => write!(f, "{err}"),
+            CrustErr::Runtime(err) => write!(f, "{err}"),
+            CrustErr::Exit(code) => write!(f, "Exited with code {code}."),
+        }
+    }
+}
+impl std::error::Error for CrustErr {}
+
+#[derive(Debug)]
+pub struct ScanErr {
+    pub msg: String,
+    pub line: u32,
+    pub file: Option<String>,
+}
+impl ScanErr {
+    pub fn new(line: u32, msg: &str) -> Self {
+        Self {
+            msg: msg.to_string(),
+            line,
+            file: None,
+        }
+    }
+}
+impl fmt::Display for ScanErr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "[{}] Error: {}",
+            location(&self.file, self.line),
+            self.msg
+        )
+    }
 }
 
 #[derive(Debug)]
 pub struct ParseErr {
     pub msg: String,
     pub line: u32,
+    pub file: Option<String>,
 }
 impl ParseErr {
     pub fn new(line: u32, msg: &str) -> Self {
         Self {
             msg: msg.to_string(),
             line,
+            file: None,
         }
     }
 }
+impl fmt::Display for ParseErr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "[{}] Error: {}",
+            location(&self.file, self.line),
+            self.msg
+        )
+    }
+}
 
 #[derive(Debug)]
 pub struct EmitErr {
     pub msg: String,
     pub line: u32,
+    pub file: Option<String>,
 }
 impl EmitErr {
     pub fn new(line: u32, msg: &str) -> Self {
         Self {
             msg: msg.to_string(),
             line,
+            file: None,
         }
     }
 }
+impl fmt::Display for EmitErr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "[{}] Error: {}",
+            location(&self.file, self.line),
+            self.msg
+        )
+    }
+}
+
+#[derive(Debug)]
+pub struct RuntimeErr {
+    pub msg: String,
+    pub line: u32,
+    pub file: Option<String>,
+    // (function name, line) for every active call frame, innermost first
+    pub trace: Vec<(String, u32)>,
+}
+impl RuntimeErr {
+    pub fn new(msg: String, trace: Vec<(String, u32)>) -> Self {
+        let line = trace.first().map_or(0, |(_, line)| *line);
+        Self {
+            msg,
+            line,
+            file: None,
+            trace,
+        }
+    }
+
+    pub fn print(&self) {
+        let l = "[line ".blue();
+        let closing_bracket = "]".blue();
+        let i = " Runtime error: ".bright_red();
+        let msg = self.msg.yellow();
+        let location = format!("[{}]", location(&self.file, self.line)).blue();
+        println!("{location}{i}{msg}");
+
+        for (name, line, repeated) in self.collapsed_trace() {
+            print!("    {l}{line}{closing_bracket} in {}()", name.green());
+            if repeated > 1 {
+                print!(" {}", format!("(repeated {repeated} times)").purple());
+            }
+            println!();
+        }
+    }
+
+    // collapse recursive calls so a stack overflow doesn't print thousands of frames
+    fn collapsed_trace(&self) -> Vec<(&str, u32, usize)> {
+        let mut collapsed = vec![];
+        let mut i = 0;
+        while i < self.trace.len() {
+            let (name, line) = &self.trace[i];
+            let repeated = self.trace[i..]
+                .iter()
+                .take_while(|frame| frame == &&self.trace[i])
+                .count();
+
+            collapsed.push((name as &str, *line, repeated));
+            i += repeated;
+        }
+        collapsed
+    }
+}
+impl fmt::Display for RuntimeErr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let location = location(&self.file, self.line);
+        write!(f, "[{location}] Runtime error: {}", self.msg)?;
+        for (name, line, repeated) in self.collapsed_trace() {
+            write!(f, "\n    [line {line}] in {name}()")?;
+            if repeated > 1 {
+                write!(f, " (repeated {repeated} times)")?;
+            }
+        }
+        Ok(())
+    }
+}
+
 #[derive(Debug)]
 pub struct SemErr {
     ty: SemErrType,
-    line: u32,
+    pub line: u32,
+    pub file: Option<String>,
 }
 impl SemErr {
     pub fn new(line: u32, ty: SemErrType) -> Self {
-        Self { ty, line }
+        Self {
+            ty,
+            line,
+            file: None,
+        }
     }
 }
 #[derive(Debug)]
@@ -66,11 +303,13 @@ pub enum SemErrType {
     FuncDefInFunc(String),
     UndefinedFunc(String),
     IndexNonArr(ValueType),
+    InvalidIndexTy(ValueType),
     StructDefInFunc(String),
     UndefinedType(String),
     AlreadyDefinedVar(String),
     AlreadyDefinedFunc(String),
     AlreadyDefinedEnum(String),
+    TooManyVariants(String),
     AlreadyDefinedStruct(String),
     NatParamTypeMismatch(String),
     StaticMethodOnInstance(String),
@@ -78,6 +317,41 @@ pub enum SemErrType {
     NoSelfOnMethod,
     InvalidIfCondition(ValueType),
     InvalidWhileCondition(ValueType),
+    InvalidMatchTy(ValueType),
+    InvalidRangePattern(ValueType),
+    NonConstantPattern,
+    PatternTypeMismatch(ValueType, ValueType),
+    NonExhaustiveMatch(String, String),
+    InvalidPatternBinding,
+    InvalidTopLevelStmt,
+    ConstReassignment(String),
+    CallNonFunc(ValueType),
+    TypeArgCount(String, u8, u8),
+    UninferredTypeParam(String, String),
+    InvalidMainParams,
+    InvalidTry(ValueType),
+    TryReturnMismatch(ValueType, ValueType),
+    NoConstructor(String),
+    UnassignedVar(String),
+    GlobalWithoutValue(String),
+    PossiblyNull(ValueType),
+    NullCheckedOutsideLoop(String),
+    TraitDefInFunc(String),
+    ImplInFunc(String, String),
+    AlreadyDefinedTrait(String),
+    UndefinedTrait(String),
+    TraitMethodWithoutSelf(String, String),
+    InvalidImplTarget(String, String),
+    AlreadyImplemented(String, String),
+    MissingTraitMethod(String, String, String),
+    NotTraitMethod(String, String),
+    TraitMethodMismatch(String, String),
+    DuplicateMethod(String, String),
+    InstanceDepth(String),
+    GenericFuncAsValue(String),
+    OverloadedFuncAsValue(String),
+    BindingInAlternatives,
+    VariantWithoutValues(ValueType, String),
     InvalidTypeFieldAccess(ValueType),
     InvalidTypeMethodAccess(ValueType),
     NoReturnTy(String, ValueType),
@@ -96,8 +370,11 @@ pub enum SemErrType {
 }
 impl SemErr {
     pub fn print(&self) {
-        //dbg!(&self.ty);
-        let msg = match &self.ty {
+        print_error(&self.file, self.line, &self.msg());
+    }
+
+    fn msg(&self) -> String {
+        match &self.ty {
             SemErrType::InvalidPrefix => "invalid prefix.".to_string(),
             SemErrType::InvalidInfix => "invalid infix.".to_string(),
             SemErrType::InvalidStaticAccess => "You can only use the '::' syntax for static methods.".to_string(),
@@ -106,6 +383,41 @@ impl SemErr {
             SemErrType::InvalidCast(expected, found) => format!("You can't cast an expression of type '{found}' to type '{expected}'."),
             SemErrType::InvalidIfCondition(found) => format!("If statement only accepts condition of type 'bool', found '{found}'."),
             SemErrType::InvalidWhileCondition(found) => format!("While statement only accepts condition of type 'bool', found '{found}'."),
+            SemErrType::InvalidMatchTy(found) => format!("Match statement only accepts numbers, strings, booleans and enums, found '{found}'."),
+            SemErrType::InvalidRangePattern(found) => format!("Range patterns can only be used to match numbers, found '{found}'."),
+            SemErrType::NonConstantPattern => "Match patterns have to be literals or enum variants.".to_string(),
+            SemErrType::PatternTypeMismatch(expected, found) => format!("Match value is of type '{expected}', but found a pattern of type '{found}'."),
+            SemErrType::NonExhaustiveMatch(name, missing) => format!("Match on enum '{}' doesn't handle the variant(s) {missing}, add them or a '_' pattern.", name.green()),
+            SemErrType::InvalidPatternBinding => "The values of an enum variant in a pattern can only be bound to names or ignored with '_'.".to_string(),
+            SemErrType::BindingInAlternatives => "Patterns that bind values can't be combined with '|'.".to_string(),
+            SemErrType::VariantWithoutValues(ty, variant) => format!("Variant '{variant}' of '{ty}' doesn't hold any values."),
+            SemErrType::InvalidTopLevelStmt => "Only functions, structs, enums, traits, variables and constants can be declared at the top level.".to_string(),
+            SemErrType::ConstReassignment(name) => format!("Cannot assign to '{}', it is a constant.", name.green()),
+            SemErrType::TypeArgCount(name, expected, found) => format!("Type '{}' expects {expected} type argument(s), but found {found}.", name.green()),
+            SemErrType::InvalidTry(found) => format!("The '?' operator can only be used on a Result or an Option, found '{found}'."),
+            SemErrType::TryReturnMismatch(value, returned) => format!("The '?' operator returns the error of '{value}' from the function, but it returns '{returned}'."),
+            SemErrType::NoConstructor(name) => format!("Values of type '{name}' can't be constructed directly."),
+            SemErrType::UnassignedVar(name) => format!("The variable '{}' is read before it's assigned a value.", name.green()),
+            SemErrType::GlobalWithoutValue(name) => format!("The global '{}' needs a value, or a nullable type like 'int?'.", name.green()),
+            SemErrType::PossiblyNull(ty) => format!("A value of type '{ty}' may be null, check it with '!= null' first."),
+            SemErrType::NullCheckedOutsideLoop(name) => format!("The variable '{}' was checked for null outside of this loop or lambda, so it can't be given a value that may be null here.", name.green()),
+            SemErrType::TraitDefInFunc(name) => format!("You attempted to define the trait '{}' inside a function, which is illegal.", name.green()),
+            SemErrType::ImplInFunc(trait_name, struct_name) => format!("You attempted to implement '{}' for '{}' inside a function, which is illegal.", trait_name.green(), struct_name.green()),
+            SemErrType::AlreadyDefinedTrait(name) => format!("Trait with name '{}' has already been defined.", name.green()),
+            SemErrType::UndefinedTrait(name) => format!("Trait '{}' has not been defined.", name.green()),
+            SemErrType::TraitMethodWithoutSelf(name, method) => format!("Method '{method}' of trait '{}' has to take 'self' as its first parameter.", name.green()),
+            SemErrType::InvalidImplTarget(trait_name, name) => format!("Trait '{}' can only be implemented for structs without type parameters, found '{name}'.", trait_name.green()),
+            SemErrType::AlreadyImplemented(trait_name, name) => format!("Trait '{}' is already implemented for '{name}'.", trait_name.green()),
+            SemErrType::MissingTraitMethod(trait_name, name, method) => format!("The implementation of trait '{}' for '{name}' is missing the method '{method}'.", trait_name.green()),
+            SemErrType::NotTraitMethod(trait_name, method) => format!("Method '{method}' is not a method of trait '{}'.", trait_name.green()),
+            SemErrType::TraitMethodMismatch(trait_name, method) => format!("Method '{method}' doesn't match its signature in trait '{}'.", trait_name.green()),
+            SemErrType::DuplicateMethod(name, method) => format!("Struct '{name}' already has a method named '{method}'."),
+            SemErrType::InvalidMainParams => "The function 'main' can only take the command-line arguments, 'fn main(str[] args)'.".to_string(),
+            SemErrType::UninferredTypeParam(name, param) => format!("Can't infer type parameter '{param}' of function '{}' from its arguments.", name.green()),
+            SemErrType::InstanceDepth(name) => format!("Too many nested instances of generic function '{}'.", name.green()),
+            SemErrType::GenericFuncAsValue(name) => format!("The generic function '{}' can't be used as a value.", name.green()),
+            SemErrType::CallNonFunc(found) => format!("Only functions can be called, found a value of type '{found}'."),
+            SemErrType::OverloadedFuncAsValue(name) => format!("The native function '{}' has several overloads, so it can't be used as a value.", name.green()),
             SemErrType::NoMainFunc => {
                 "You have to define a function with the name 'main' as entry point for the program."
                     .to_string()
@@ -144,6 +456,9 @@ impl SemErr {
             SemErrType::IndexNonArr(ty) => format!(
                 "You can only index arrays, but you tried to index the type '{ty}'."
             ),
+            SemErrType::InvalidIndexTy(ty) => format!(
+                "Arrays can only be indexed with integers, but you tried to index with the type '{ty}'."
+            ),
 
             SemErrType::AssignArrTypeMismatch(expected, found) => {
                 format!(
@@ -199,6 +514,12 @@ impl SemErr {
                     name.green()
                 )
             }
+            SemErrType::TooManyVariants(name) => {
+                format!(
+                    "Enum '{}' holds values, so it can't have more than 256 variants.",
+                    name.green()
+                )
+            }
             SemErrType::AlreadyDefinedStruct(name) => {
                 format!(
                     "Struct with name '{}' has already been defined.",
@@ -237,7 +558,16 @@ impl SemErr {
                     "Not all elements in the array are of the same type. Array expected type '{expected}', but found type '{found}'."
                 )
             }
-        };
-        print_error(self.line, &msg);
+        }
+    }
+}
+impl fmt::Display for SemErr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "[{}] Error: {}",
+            location(&self.file, self.line),
+            self.msg()
+        )
     }
 }
@@ -1,5 +1,6 @@
 use crate::{
     parse_types::BinaryOp,
+    statement::Stmt,
     token::{Literal, TokenType},
     value::ValueType,
 };
@@ -25,10 +26,33 @@ pub enum ExprType<'a> {
         args: Vec<Expr<'a>>,
         index: Option<usize>,
     },
+    // calls a value of a function type, 'make_adder(2)(3)'
+    CallValue {
+        callee: Box<Expr<'a>>,
+        args: Vec<Expr<'a>>,
+    },
+    // a named function used as a value
+    FuncRef {
+        name: &'a str,
+        index: usize,
+    },
+    Lambda {
+        parameters: Vec<(ValueType, &'a str)>,
+        return_ty: ValueType,
+        body: Vec<Stmt<'a>>,
+    },
     Cast {
         value: Box<Expr<'a>>,
         target: ValueType,
     },
+    // 'value?', unwraps a Result or an Option or returns its error
+    Try(Box<Expr<'a>>),
+    // a variant of an enum that holds values, 'Shape::Circle(2.)'
+    EnumVariant {
+        name: &'a str,
+        tag: u8,
+        args: Vec<Expr<'a>>,
+    },
     MethodCall {
         inst: Box<Expr<'a>>,
         property: &'a str,
@@ -41,6 +65,21 @@ pub enum ExprType<'a> {
         args: Vec<Expr<'a>>,
         use_self: bool,
     },
+    // a native method of a value that doesn't carry its methods, like 'name.trim()' on a string,
+    // 'methods' is the native type they're registered under
+    NatMethodCall {
+        methods: &'static str,
+        inst: Box<Expr<'a>>,
+        index: u8,
+        args: Vec<Expr<'a>>,
+    },
+    // a method of a value with a trait type, it's looked up in the vtable of the instance
+    TraitMethodCall {
+        trait_id: u8,
+        inst: Box<Expr<'a>>,
+        index: u8,
+        args: Vec<Expr<'a>>,
+    },
     Dot {
         inst: Box<Expr<'a>>,
         property: &'a str,
@@ -21,16 +21,34 @@ impl<'a> FuncCompilerStack<'a> {
         self.comps[self.current].scope_depth -= 1;
 
         while self.should_remove_local() {
-            self.emit_byte(OpCode::Pop as u8, 69);
-            self.comps[self.current].local_count -= 1;
+            self.pop_local(69);
         }
     }
 
+    // captured locals are moved to the heap before they leave the stack
+    pub fn pop_local(&mut self, line: u32) {
+        let local_count = self.current().local_count;
+        if self.current().locals[local_count - 1].is_captured {
+            self.emit_byte(OpCode::CloseUpvalue as u8, line);
+        } else {
+            self.emit_byte(OpCode::Pop as u8, line);
+        }
+        self.comps[self.current].local_count -= 1;
+    }
+
     pub fn end_compiler(&mut self, line: u32) -> ObjFunc {
         self.emit_return(line);
 
-        self.current = 0;
-        self.comps.pop().unwrap().get_func()
+        let mut comp = self.comps.pop().unwrap();
+        self.current = self.comps.len().saturating_sub(1);
+        comp.func.upvalue_count = comp.upvalues.len() as u8;
+        comp.get_func()
+    }
+
+    /// The upvalues of the function that is compiled right now, as pairs of whether it
+    /// captures a local of the enclosing function and the index of that local or upvalue.
+    pub fn get_upvalues(&self) -> &[(bool, u8)] {
+        &self.current().upvalues
     }
 
     pub fn emit_return(&mut self, line: u32) {
@@ -44,6 +62,20 @@ impl<'a> FuncCompilerStack<'a> {
         Ok(())
     }
 
+    pub fn emit_closure(
+        &mut self,
+        func: StackValue,
+        upvalues: &[(bool, u8)],
+        line: u32,
+    ) -> Result<(), EmitErr> {
+        let const_index = self.make_constant(func, line)?;
+        self.emit_bytes(OpCode::Closure as u8, const_index, line);
+        for (is_local, index) in upvalues {
+            self.emit_bytes(*is_local as u8, *index, line);
+        }
+        Ok(())
+    }
+
     fn make_constant(&mut self, value: StackValue, line: u32) -> Result<u8, EmitErr> {
         let const_index = self.add_constant(value);
         if const_index > u8::MAX.into() {
@@ -83,10 +115,6 @@ impl<'a> FuncCompilerStack<'a> {
         Ok(())
     }
 
-    pub fn decrement_local_count(&mut self) {
-        self.comps[self.current].local_count -= 1;
-    }
-
     fn add_constant(&mut self, value: StackValue) -> usize {
         self.comps[self.current].func.chunk.add_constant(value)
     }
@@ -159,6 +187,7 @@ impl<'a> FuncCompilerStack<'a> {
             return Err(EmitErr::new(line, msg));
         }
 
+        self.pop_loop_locals(line);
         let jump = self.emit_jump(OpCode::Loop, line);
         self.comps[self.current]
             .continue_stack
@@ -180,11 +209,20 @@ impl<'a> FuncCompilerStack<'a> {
         Ok(())
     }
 
+    pub fn begin_try(&mut self) {
+        self.comps[self.current].try_depth += 1;
+    }
+
+    pub fn end_try(&mut self) {
+        self.comps[self.current].try_depth -= 1;
+    }
+
     pub fn add_break(&mut self, line: u32) -> Result<(), EmitErr> {
         if self.current().break_stack.is_empty() {
             return Err(EmitErr::new(line, "'break' can only be used inside loops."));
         }
 
+        self.pop_loop_locals(line);
         let jump = self.emit_jump(OpCode::Jump, line);
         self.comps[self.current]
             .break_stack
@@ -196,10 +234,34 @@ impl<'a> FuncCompilerStack<'a> {
     }
 
     pub fn push_new_break_stack(&mut self) {
-        self.comps[self.current].break_stack.push(vec![]);
+        let local_count = self.current().local_count;
+        let current = &mut self.comps[self.current];
+        current.break_stack.push(vec![]);
+        current.loop_local_counts.push(local_count);
+        current.loop_try_depths.push(current.try_depth);
+    }
+
+    // locals declared inside the loop body are still on the stack when jumping out of it, and the
+    // handlers of the 'try' blocks inside it are still active
+    fn pop_loop_locals(&mut self, line: u32) {
+        let loop_try_depth = *self.current().loop_try_depths.last().unwrap();
+        for _ in loop_try_depth..self.current().try_depth {
+            self.emit_byte(OpCode::PopHandler as u8, line);
+        }
+
+        let loop_local_count = *self.current().loop_local_counts.last().unwrap();
+        for i in (loop_local_count..self.current().local_count).rev() {
+            if self.current().locals[i].is_captured {
+                self.emit_byte(OpCode::CloseUpvalue as u8, line);
+            } else {
+                self.emit_byte(OpCode::Pop as u8, line);
+            }
+        }
     }
 
     pub fn patch_breaks(&mut self) -> Result<(), EmitErr> {
+        self.comps[self.current].loop_local_counts.pop();
+        self.comps[self.current].loop_try_depths.pop();
         let breaks = self.comps[self.current].break_stack.pop().unwrap();
         for jump in breaks {
             self.patch_jump(jump)?;
@@ -208,20 +270,76 @@ impl<'a> FuncCompilerStack<'a> {
     }
 
     pub fn resolve_local(&mut self, name: &str) -> Option<u8> {
-        for i in (0..self.current().local_count).rev() {
-            if self.current().locals[i].name == name {
+        Self::resolve_local_in(&self.comps[self.current], name)
+    }
+
+    fn resolve_local_in(comp: &FuncCompiler, name: &str) -> Option<u8> {
+        for i in (0..comp.local_count).rev() {
+            if comp.locals[i].name == name {
                 return Some(i as u8);
             }
         }
         None
     }
 
+    // looks for the name in the enclosing functions, every function in between gets an upvalue
+    pub fn resolve_upvalue(&mut self, name: &str, line: u32) -> Result<Option<u8>, EmitErr> {
+        self.resolve_upvalue_in(self.current, name, line)
+    }
+
+    fn resolve_upvalue_in(
+        &mut self,
+        comp: usize,
+        name: &str,
+        line: u32,
+    ) -> Result<Option<u8>, EmitErr> {
+        if comp == 0 {
+            return Ok(None);
+        }
+
+        let enclosing = comp - 1;
+        if let Some(index) = Self::resolve_local_in(&self.comps[enclosing], name) {
+            self.comps[enclosing].locals[index as usize].is_captured = true;
+            return self.add_upvalue(comp, true, index, line).map(Some);
+        }
+
+        match self.resolve_upvalue_in(enclosing, name, line)? {
+            Some(index) => self.add_upvalue(comp, false, index, line).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn add_upvalue(
+        &mut self,
+        comp: usize,
+        is_local: bool,
+        index: u8,
+        line: u32,
+    ) -> Result<u8, EmitErr> {
+        let upvalues = &mut self.comps[comp].upvalues;
+        if let Some(i) = upvalues
+            .iter()
+            .position(|upvalue| *upvalue == (is_local, index))
+        {
+            return Ok(i as u8);
+        }
+
+        if upvalues.len() == MAX_LOCAL_AMT {
+            return Err(EmitErr::new(
+                line,
+                "Too many closure variables in function.",
+            ));
+        }
+        upvalues.push((is_local, index));
+        Ok(upvalues.len() as u8 - 1)
+    }
+
     fn should_remove_local(&self) -> bool {
         let depth = self.current().locals[self.current().local_count - 1].depth;
         self.current().local_count > 0 && depth > self.current().scope_depth
     }
 
-    fn current(&self) -> &FuncCompiler {
+    fn current(&self) -> &FuncCompiler<'a> {
         &self.comps[self.current]
     }
 }
@@ -230,10 +348,16 @@ impl<'a> FuncCompilerStack<'a> {
 struct Local<'a> {
     name: &'a str,
     depth: usize,
+    // whether a closure uses this local
+    is_captured: bool,
 }
 impl<'a> Local<'a> {
     fn new(name: &'a str, depth: usize) -> Self {
-        Self { name, depth }
+        Self {
+            name,
+            depth,
+            is_captured: false,
+        }
     }
 }
 
@@ -247,6 +371,12 @@ pub struct FuncCompiler<'a> {
     func: ObjFunc,
     break_stack: Vec<Vec<usize>>,
     continue_stack: Vec<Vec<usize>>,
+    // the amount of locals when each loop was entered
+    loop_local_counts: Vec<usize>,
+    // the 'try' blocks the current code is in, and how many there were when each loop was entered
+    try_depth: usize,
+    loop_try_depths: Vec<usize>,
+    upvalues: Vec<(bool, u8)>,
 }
 impl<'a> FuncCompiler<'a> {
     pub fn new(func_name: String) -> Self {
@@ -258,6 +388,10 @@ impl<'a> FuncCompiler<'a> {
             func: ObjFunc::new(func_name),
             break_stack: vec![],
             continue_stack: vec![],
+            loop_local_counts: vec![],
+            try_depth: 0,
+            loop_try_depths: vec![],
+            upvalues: vec![],
         }
     }
 
@@ -1,8 +1,6 @@
 use crate::{
-    error::PRINT_HEAP,
-    object::{Gc, GcData, GcHeader, GcMemSize, Object},
+    object::{Gc, GcData, GcHeader, GcMemSize, ObjFunc, ObjUpvalue, Object},
     value::StackValue,
-    vm::STACK_SIZE,
 };
 use std::ptr::NonNull;
 
@@ -14,6 +12,9 @@ pub struct Heap {
     permanent_head: Option<Object>,
     bytes_allocated: usize,
     gc_threshold: f64,
+    pub print_on_collect: bool,
+    // objects allocated by a native function that is still running
+    temp_roots: Vec<StackValue>,
 }
 impl Heap {
     pub fn new() -> Self {
@@ -22,6 +23,8 @@ impl Heap {
             permanent_head: None,
             bytes_allocated: 0,
             gc_threshold: INITIAL_GC_THRESHOLD as f64,
+            print_on_collect: false,
+            temp_roots: vec![],
         }
     }
     pub fn print(&self) {
@@ -80,6 +83,34 @@ impl Heap {
                     }
                 }
             }
+            Object::Enum(value) => {
+                self.mark_object(value.data.name, gray_list);
+                for el in &value.data.values {
+                    if let StackValue::Obj(obj) = el {
+                        self.mark_object(*obj, gray_list);
+                    }
+                }
+            }
+            Object::Closure(closure) => {
+                for upvalue in &closure.data.upvalues {
+                    self.mark_object(Object::Upvalue(*upvalue), gray_list);
+                }
+            }
+            Object::Map(map) => {
+                for (key, value) in &map.data.entries {
+                    for el in [key, value] {
+                        if let StackValue::Obj(obj) = el {
+                            self.mark_object(*obj, gray_list);
+                        }
+                    }
+                }
+            }
+            // open upvalues point to the stack, which is already marked
+            Object::Upvalue(upvalue) => {
+                if let Some(StackValue::Obj(obj)) = upvalue.data.closed {
+                    self.mark_object(obj, gray_list);
+                }
+            }
         }
     }
     fn sweep(&mut self) {
@@ -102,18 +133,29 @@ impl Heap {
         self.head = new_head;
     }
 
-    pub fn collect_garbage(&mut self, stack: &mut [StackValue; STACK_SIZE], stack_top: usize) {
+    /// Frees every object that can't be reached from `stack` or the `open_upvalues` of the VM.
+    pub fn collect_garbage(&mut self, stack: &[StackValue], open_upvalues: &[Gc<ObjUpvalue>]) {
         let mut gray_objects = vec![];
-        for value in stack.iter().take(stack_top) {
+        for value in stack {
             if let StackValue::Obj(obj) = value {
                 self.mark_object(*obj, &mut gray_objects);
             }
         }
+        for upvalue in open_upvalues {
+            self.mark_object(Object::Upvalue(*upvalue), &mut gray_objects);
+        }
+        let temp_roots = std::mem::take(&mut self.temp_roots);
+        for value in &temp_roots {
+            if let StackValue::Obj(obj) = value {
+                self.mark_object(*obj, &mut gray_objects);
+            }
+        }
+        self.temp_roots = temp_roots;
 
         self.trace_objects(&mut gray_objects);
         self.sweep();
 
-        if PRINT_HEAP {
+        if self.print_on_collect {
             self.print();
         }
     }
@@ -122,8 +164,8 @@ impl Heap {
         &mut self,
         data: T,
         map: F,
-        stack: &mut [StackValue; STACK_SIZE],
-        stack_top: usize,
+        stack: &[StackValue],
+        open_upvalues: &[Gc<ObjUpvalue>],
     ) -> (Object, Gc<T>)
     where
         F: Fn(Gc<T>) -> Object,
@@ -134,7 +176,7 @@ impl Heap {
         self.bytes_allocated += size;
 
         if self.bytes_allocated > self.gc_threshold as usize {
-            self.collect_garbage(stack, stack_top);
+            self.collect_garbage(stack, open_upvalues);
             self.bytes_allocated = 0;
             self.gc_threshold *= 1.8;
         }
@@ -183,6 +225,49 @@ impl Heap {
         (object, gc)
     }
 
+    /// Keeps `value` alive until `clear_temp_roots` is called.
+    pub fn add_temp_root(&mut self, value: StackValue) {
+        self.temp_roots.push(value);
+    }
+    pub fn clear_temp_roots(&mut self) {
+        self.temp_roots.clear();
+    }
+
+    /// Returns every function that was compiled into this heap.
+    pub fn funcs(&self) -> Vec<Gc<ObjFunc>> {
+        let mut funcs = vec![];
+        let mut current = self.permanent_head;
+        while let Some(object) = current {
+            if let Object::Func(func) = object {
+                funcs.push(func);
+            }
+            current = object.header().next;
+        }
+        funcs
+    }
+
+    /// Takes ownership of all objects in `other`, so they live as long as this heap.
+    pub fn append(&mut self, mut other: Heap) {
+        self.head = Self::concat_lists(other.head.take(), self.head.take());
+        self.permanent_head =
+            Self::concat_lists(other.permanent_head.take(), self.permanent_head.take());
+        self.bytes_allocated += other.bytes_allocated;
+    }
+
+    fn concat_lists(list: Option<Object>, rest: Option<Object>) -> Option<Object> {
+        let Some(head) = list else {
+            return rest;
+        };
+
+        let mut tail = head;
+        while let Some(next) = tail.header().next {
+            tail = next;
+        }
+        tail.header_mut().next = rest;
+
+        Some(head)
+    }
+
     unsafe fn dealloc(&mut self, object: Object) {
         match object {
             Object::Str(ptr) => {
@@ -205,6 +290,22 @@ impl Heap {
                 let raw = ptr.ptr.as_ptr();
                 drop(Box::from_raw(raw));
             }
+            Object::Enum(ptr) => {
+                let raw = ptr.ptr.as_ptr();
+                drop(Box::from_raw(raw));
+            }
+            Object::Closure(ptr) => {
+                let raw = ptr.ptr.as_ptr();
+                drop(Box::from_raw(raw));
+            }
+            Object::Upvalue(ptr) => {
+                let raw = ptr.ptr.as_ptr();
+                drop(Box::from_raw(raw));
+            }
+            Object::Map(ptr) => {
+                let raw = ptr.ptr.as_ptr();
+                drop(Box::from_raw(raw));
+            }
         }
     }
 
@@ -218,6 +319,10 @@ impl Heap {
                 Object::Native(ref ptr) => ptr.header.next,
                 Object::Arr(ref ptr) => ptr.header.next,
                 Object::Inst(ref ptr) => ptr.header.next,
+                Object::Enum(ref ptr) => ptr.header.next,
+                Object::Closure(ref ptr) => ptr.header.next,
+                Object::Upvalue(ref ptr) => ptr.header.next,
+                Object::Map(ref ptr) => ptr.header.next,
             };
 
             unsafe {
@@ -0,0 +1,288 @@
+use std::{
+    cell::RefCell,
+    io::{self, BufRead, Write},
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+use crate::{
+    analysis::Analyser,
+    analysis_types::{NatFuncData, NatStructData},
+    emitter::Emitter,
+    error::{CrustErr, DebugOptions},
+    heap::Heap,
+    module::Modules,
+    native::Natives,
+    object::{NativeFunc, ObjFunc},
+    parser::Parser,
+    scanner::Scanner,
+    statement::Stmt,
+    token::Token,
+    value::ValueType,
+    vm::{self, ProgramIo, VM},
+};
+
+/// A compiled Crust program, ready to be executed by an [`Interpreter`].
+pub struct Program {
+    func: ObjFunc,
+    heap: Heap,
+    // names of the imported files, used to report runtime errors
+    files: Vec<Option<String>>,
+}
+impl Program {
+    /// Returns the bytecode of every function in the program, starting with 'main'.
+    pub fn disassemble(&self) -> String {
+        disassemble(&self.func, "main", &self.heap)
+    }
+}
+
+/// Disassembles `func` followed by the functions and methods in `heap`, sorted by name.
+pub(crate) fn disassemble(func: &ObjFunc, name: &str, heap: &Heap) -> String {
+    let mut out = func.chunk.disassemble(name);
+
+    let mut funcs = heap.funcs();
+    funcs.sort_by(|lhs, rhs| lhs.data.get_name().cmp(rhs.data.get_name()));
+    for func in funcs {
+        // placeholder for 'main', which isn't stored in the heap
+        if func.data.chunk.code.is_empty() {
+            continue;
+        }
+        out.push('\n');
+        out.push_str(&func.data.chunk.disassemble(func.data.get_name()));
+    }
+    out
+}
+
+/// Compiles and runs Crust source code.
+///
+/// ```
+/// let mut interpreter = crust::Interpreter::new();
+/// let output = interpreter.capture_output();
+///
+/// interpreter.run("fn main() { println(1 + 2); }").unwrap();
+/// assert_eq!(output.take(), "3\n");
+/// ```
+pub struct Interpreter {
+    pub(crate) out: Box<dyn Write>,
+    input: Box<dyn BufRead>,
+    args: Vec<String>,
+    pub(crate) max_frames: usize,
+    pub(crate) natives: Natives<'static>,
+    pub(crate) debug: DebugOptions,
+    source_path: Option<PathBuf>,
+}
+impl Interpreter {
+    pub fn new() -> Self {
+        Self {
+            out: Box::new(io::stdout()),
+            input: Box::new(io::BufReader::new(io::stdin())),
+            args: vec![],
+            max_frames: vm::DEFAULT_MAX_FRAMES,
+            natives: Natives::new(),
+            debug: DebugOptions::default(),
+            source_path: None,
+        }
+    }
+
+    /// Makes `func` callable as `name` from every program compiled afterwards.
+    /// Registering multiple functions with the same name overloads them on their parameter types.
+    ///
+    /// ```
+    /// use crust::{NatCtx, StackValue, ValueType};
+    ///
+    /// fn twice(args: &[StackValue], _ctx: &mut NatCtx) -> Result<StackValue, String> {
+    ///     let StackValue::I64(n) = args[0] else { unreachable!() };
+    ///     n.checked_mul(2).map(StackValue::I64).ok_or("Overflow.".to_string())
+    /// }
+    ///
+    /// let mut interpreter = crust::Interpreter::new();
+    /// let output = interpreter.capture_output();
+    /// interpreter.register_func("twice", vec![ValueType::I64], ValueType::I64, twice);
+    ///
+    /// interpreter.run("fn main() { println(twice(21)); }").unwrap();
+    /// assert_eq!(output.take(), "42\n");
+    /// ```
+    pub fn register_func(
+        &mut self,
+        name: &'static str,
+        parameters: Vec<ValueType>,
+        return_ty: ValueType,
+        func: NativeFunc,
+    ) {
+        let data = NatFuncData {
+            parameters,
+            func,
+            return_ty,
+            use_self: false,
+        };
+        self.natives.funcs.entry(name).or_default().push(data);
+    }
+
+    /// Makes the struct `name` available to every program compiled afterwards.
+    /// Methods with `use_self` receive the instance as their first argument.
+    pub fn register_struct(&mut self, name: &'static str, data: NatStructData<'static>) {
+        self.natives.structs.insert(name, data);
+    }
+
+    /// Writes everything the program prints to `out` instead of stdout.
+    pub fn set_output(&mut self, out: impl Write + 'static) {
+        self.out = Box::new(out);
+    }
+
+    /// Keeps everything the program prints in memory, it can be read through the returned handle.
+    pub fn capture_output(&mut self) -> CapturedOutput {
+        let output = CapturedOutput::default();
+        self.set_output(output.clone());
+        output
+    }
+
+    /// Makes the program read its input from `input` instead of stdin.
+    ///
+    /// ```
+    /// let mut interpreter = crust::Interpreter::new();
+    /// let output = interpreter.capture_output();
+    /// interpreter.set_input(std::io::Cursor::new("crust\n"));
+    ///
+    /// interpreter.run("fn main() { println(read_line()); }").unwrap();
+    /// assert_eq!(output.take(), "crust\n");
+    /// ```
+    pub fn set_input(&mut self, input: impl BufRead + 'static) {
+        self.input = Box::new(input);
+    }
+
+    /// Sets the arguments that are passed to `fn main(str[] args)`.
+    pub fn set_args(&mut self, args: Vec<String>) {
+        self.args = args;
+    }
+
+    pub(crate) fn io(&mut self) -> ProgramIo<'_> {
+        ProgramIo {
+            out: &mut *self.out,
+            input: &mut *self.input,
+            args: &self.args,
+        }
+    }
+
+    /// Sets the maximum call depth, exceeding it results in a stack overflow error.
+    pub fn set_max_frames(&mut self, max_frames: usize) {
+        self.max_frames = max_frames;
+    }
+
+    pub fn set_debug_options(&mut self, debug: DebugOptions) {
+        self.debug = debug;
+    }
+
+    /// Sets the file the compiled source code is read from. Imports are resolved relative to
+    /// it, otherwise relative to the working directory.
+    pub fn set_source_path(&mut self, path: impl Into<PathBuf>) {
+        self.source_path = Some(path.into());
+    }
+
+    pub fn compile(&self, source: &str) -> Result<Program, CrustErr> {
+        let path = self.source_path.as_deref();
+        let dir = path.and_then(Path::parent).unwrap_or(Path::new(""));
+        let modules = Modules::load(source.to_string(), dir, path)?;
+
+        let files = modules.file_names();
+        self.compile_modules(&modules)
+            .map(|(func, heap)| {
+                let program = Program { func, heap, files };
+                if self.debug.disasm {
+                    println!("{}", program.disassemble());
+                }
+                program
+            })
+            .map_err(|err| err.locate(&modules.file_names()))
+    }
+
+    fn compile_modules(&self, modules: &Modules) -> Result<(ObjFunc, Heap), CrustErr> {
+        // imported modules are declared before the file that imports them
+        let mut statements = vec![];
+        for index in (0..modules.len()).rev() {
+            let tokens = modules.tokens(index)?;
+            statements.extend(self.parse_tokens(tokens)?);
+        }
+
+        let entities = Analyser::analyse_stmts(&mut statements, self.natives.clone())
+            .map_err(CrustErr::Sem)?;
+        Emitter::compile(statements, entities).map_err(CrustErr::Emit)
+    }
+
+    pub(crate) fn parse<'a>(&self, source: &'a str) -> Result<Vec<Stmt<'a>>, CrustErr> {
+        let tokens = Scanner::new(source).scan_tokens().map_err(CrustErr::Scan)?;
+        self.parse_tokens(tokens)
+    }
+
+    fn parse_tokens<'a>(&self, tokens: Vec<Token<'a>>) -> Result<Vec<Stmt<'a>>, CrustErr> {
+        if self.debug.tokens {
+            for token in &tokens {
+                println!("{:?} type: {:?}", token, token.ty as u8);
+            }
+            println!();
+        }
+
+        let statements = Parser::compile(tokens).map_err(CrustErr::Parse)?;
+        if self.debug.ast {
+            println!("{statements:#?}");
+        }
+        Ok(statements)
+    }
+
+    pub fn execute(&mut self, mut program: Program) -> Result<(), CrustErr> {
+        let (max_frames, debug) = (self.max_frames, self.debug);
+        let result = VM::interpret(
+            program.func,
+            &mut program.heap,
+            max_frames,
+            debug,
+            self.io(),
+        );
+
+        // make sure everything is printed before the caller reports an error or exits
+        let _ = self.out.flush();
+        match result {
+            Ok(None) => Ok(()),
+            Ok(Some(code)) => Err(CrustErr::Exit(code)),
+            Err(err) => Err(CrustErr::Runtime(err).locate(&program.files)),
+        }
+    }
+
+    pub fn run(&mut self, source: &str) -> Result<(), CrustErr> {
+        let program = self.compile(source)?;
+        self.execute(program)
+    }
+}
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Handle to the output of a program run by an [`Interpreter`] after calling
+/// [`Interpreter::capture_output`].
+#[derive(Debug, Clone, Default)]
+pub struct CapturedOutput {
+    buffer: Rc<RefCell<Vec<u8>>>,
+}
+impl CapturedOutput {
+    /// Returns everything that has been printed so far.
+    pub fn contents(&self) -> String {
+        String::from_utf8_lossy(&self.buffer.borrow()).into_owned()
+    }
+
+    /// Returns everything that has been printed so far and clears the buffer.
+    pub fn take(&self) -> String {
+        let bytes = std::mem::take(&mut *self.buffer.borrow_mut());
+        String::from_utf8_lossy(&bytes).into_owned()
+    }
+}
+impl Write for CapturedOutput {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buffer.borrow_mut().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,30 @@
+mod analysis;
+mod analysis_types;
+mod chunk;
+mod emitter;
+mod error;
+mod expression;
+mod func_compiler;
+mod heap;
+mod interpreter;
+mod module;
+mod native;
+mod object;
+mod op_code;
+mod parse_types;
+mod parser;
+mod repl;
+mod scanner;
+mod statement;
+mod token;
+mod value;
+mod vm;
+
+pub use analysis_types::{NatFuncData, NatStructData};
+pub use error::{CrustErr, DebugOptions, EmitErr, ParseErr, RuntimeErr, ScanErr, SemErr};
+pub use interpreter::{CapturedOutput, Interpreter, Program};
+pub use object::{NatCtx, NativeFunc, Object};
+pub use repl::Repl;
+pub use value::{GenericType, StackValue, ValueType};
+
+use op_code::OpCode;
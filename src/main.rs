@@ -1,84 +1,120 @@
-use analysis::Analyser;
-use emitter::Emitter;
-use error::{PRINT_PARSE_TREE, PRINT_TOKENS};
-use op_code::OpCode;
-use scanner::Scanner;
-use value::StackValue;
+use std::io::{self, Write};
 
 use colored::Colorize;
+use crust::{CrustErr, DebugOptions, Interpreter, Repl};
 
-mod analysis;
-mod analysis_types;
-mod chunk;
-mod emitter;
-mod error;
-mod expression;
-mod func_compiler;
-mod heap;
-mod native;
-mod object;
-mod op_code;
-mod parse_types;
-mod parser;
-mod scanner;
-mod statement;
-mod token;
-mod value;
-mod vm;
+// the output of Crust programs is printed in green
+struct GreenStdout(io::Stdout);
+impl Write for GreenStdout {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let text = String::from_utf8_lossy(buf);
+        write!(self.0, "{}", text.green())?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()
+    }
+}
+
+const USAGE: &str =
+    "Usage: crust [--tokens] [--ast] [--disasm] [--trace] [--heap] [file [args...] | repl]";
 
 fn main() {
     std::env::set_var("RUST_BACKTRACE", "1");
 
-    let args: Vec<String> = std::env::args().collect();
+    let mut debug = DebugOptions::default();
+    let mut path = None;
+    let mut program_args = vec![];
+    for arg in std::env::args().skip(1) {
+        // everything after the file is passed on to the program
+        if path.is_some() {
+            program_args.push(arg);
+            continue;
+        }
+        match arg.as_str() {
+            "--tokens" => debug.tokens = true,
+            "--ast" => debug.ast = true,
+            "--disasm" => debug.disasm = true,
+            "--trace" => debug.trace = true,
+            "--heap" => debug.heap = true,
+            _ if arg.starts_with("--") => {
+                eprintln!("Unexpected argument '{arg}'.\n{USAGE}");
+                std::process::exit(64);
+            }
+            _ => path = Some(arg),
+        }
+    }
 
-    let source = if args.len() <= 1 {
-        let msg = "Could not find file.crs. The file should be in the same directory as either the executable file or Cargo.toml.";
-        std::fs::read_to_string("file.crs").expect(msg)
-    } else {
-        let msg = format!("Could not find file '{}'.", args[1]);
-        std::fs::read_to_string(&args[1]).expect(&msg)
-    };
+    let mut interpreter = Interpreter::new();
+    interpreter.set_output(GreenStdout(io::stdout()));
+    interpreter.set_debug_options(debug);
+    interpreter.set_args(program_args);
 
-    let scanner = Scanner::new(&source);
-    let tokens = match scanner.scan_tokens() {
-        Ok(tokens) => tokens,
-        Err(_) => {
-            println!(
-                "{}",
-                "Scan error(s) detected, terminating program.".purple()
-            );
+    let path = match path {
+        Some(path) if path != "repl" => path,
+        _ => {
+            run_repl(interpreter);
             return;
         }
     };
 
-    if PRINT_TOKENS {
-        for token in &tokens {
-            println!("{:?} type: {:?}", token, token.ty as u8);
+    let msg = format!("Could not find file '{path}'.");
+    let source = std::fs::read_to_string(&path).expect(&msg);
+    interpreter.set_source_path(&path);
+
+    if let Err(err) = interpreter.run(&source) {
+        if let CrustErr::Exit(code) = err {
+            std::process::exit(code);
         }
-        println!();
+        err.print();
+        let exit_code = if err.is_runtime_err() { 70 } else { 65 };
+        std::process::exit(exit_code);
     }
+}
 
-    let mut statements = match parser::Parser::compile(tokens) {
-        Some(statements) => statements,
-        None => {
-            println!(
-                "{}",
-                "Parse error(s) detected, terminating program.".purple()
-            );
-            return;
+fn run_repl(interpreter: Interpreter) {
+    let mut repl = Repl::new(interpreter);
+    let mut input = String::new();
+
+    loop {
+        let prompt = if input.is_empty() { "> " } else { "... " };
+        print!("{}", prompt.blue());
+        io::stdout().flush().unwrap();
+
+        let mut line = String::new();
+        if io::stdin().read_line(&mut line).unwrap() == 0 {
+            println!();
+            break;
         }
-    };
-    if PRINT_PARSE_TREE {
-        dbg!(&statements);
-    }
+        input.push_str(&line);
 
-    let entities = match Analyser::analyse_stmts(&mut statements) {
-        Some(func_data) => func_data,
-        None => return,
-    };
+        // keep reading lines until every block is closed
+        if open_braces(&input) > 0 {
+            continue;
+        }
 
-    // dbg!(&statements);
-    if let Some((func, heap)) = Emitter::compile(statements, entities) {
-        vm::VM::interpret(func, heap);
+        if !input.trim().is_empty() {
+            match repl.eval(&input) {
+                Err(CrustErr::Exit(code)) => std::process::exit(code),
+                Err(err) => err.print(),
+                Ok(()) => (),
+            }
+        }
+        input.clear();
+    }
+}
+
+fn open_braces(input: &str) -> i32 {
+    let mut in_str = false;
+    let mut open = 0;
+    for char in input.chars() {
+        match char {
+            '"' => in_str = !in_str,
+            '{' if !in_str => open += 1,
+            '}' if !in_str => open -= 1,
+            _ => (),
+        }
     }
+    open
 }
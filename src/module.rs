@@ -0,0 +1,410 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Component, Path, PathBuf},
+};
+
+use crate::{
+    error::{CrustErr, ParseErr},
+    scanner::Scanner,
+    token::{Literal, Token, TokenType},
+};
+
+// every line also stores the index of the file it's in, so errors in imported files can be
+// reported with the right file name
+const LINE_BITS: u32 = 20;
+const LINE_MASK: u32 = (1 << LINE_BITS) - 1;
+
+pub fn encode_line(file: usize, line: u32) -> u32 {
+    ((file as u32) << LINE_BITS) | line
+}
+
+pub fn decode_line(line: u32) -> (usize, u32) {
+    ((line >> LINE_BITS) as usize, line & LINE_MASK)
+}
+
+struct Module {
+    // the file that is being run doesn't have a name
+    name: Option<String>,
+    file_name: String,
+    source: String,
+    imports: Vec<String>,
+    // maps the functions, types and globals declared in the module to their qualified name
+    // 'module::name'
+    funcs: HashMap<String, String>,
+    types: HashMap<String, String>,
+    globals: HashMap<String, String>,
+}
+
+/// A program and all the files it imports, directly or indirectly.
+pub struct Modules {
+    modules: Vec<Module>,
+    paths: HashMap<PathBuf, usize>,
+}
+impl Modules {
+    /// Loads `source` and the files it imports. Imports are resolved relative to `dir`, `path` is
+    /// the file `source` was read from if there is one.
+    pub fn load(source: String, dir: &Path, path: Option<&Path>) -> Result<Self, CrustErr> {
+        let mut modules = Self {
+            modules: vec![],
+            paths: HashMap::new(),
+        };
+
+        let file_name = path.map_or(String::new(), |path| path.display().to_string());
+        modules.add(None, file_name, source);
+        if let Some(path) = path.and_then(|path| fs::canonicalize(path).ok()) {
+            modules.paths.insert(path, 0);
+        }
+
+        match modules.load_imports(0, dir, &mut vec![0]) {
+            Ok(()) => Ok(modules),
+            Err(err) => Err(err.locate(&modules.file_names())),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.modules.len()
+    }
+
+    /// The name of every imported file by index, `None` for the file that is being run.
+    pub fn file_names(&self) -> Vec<Option<String>> {
+        let mut names: Vec<Option<String>> = self
+            .modules
+            .iter()
+            .map(|module| Some(module.file_name.clone()))
+            .collect();
+        names[0] = None;
+        names
+    }
+
+    /// Returns the tokens of a module without its imports. Declarations of the module and
+    /// references to declarations of imported modules are replaced by their qualified name.
+    pub fn tokens(&self, index: usize) -> Result<Vec<Token<'_>>, CrustErr> {
+        let module = &self.modules[index];
+        let tokens = self.scan(index)?;
+
+        let parse_err = |line: u32, msg: String| CrustErr::Parse(vec![ParseErr::new(line, &msg)]);
+
+        let mut resolved: Vec<Token> = Vec::with_capacity(tokens.len());
+        let mut depth = 0;
+        let mut in_enum = false;
+        // the depth of the fields of the struct that is being declared
+        let mut struct_depth = None;
+        let mut i = 0;
+        while i < tokens.len() {
+            let token = tokens[i];
+            let next = tokens.get(i + 1).map(|token| token.ty);
+
+            match token.ty {
+                TokenType::LeftBrace => {
+                    depth += 1;
+                    let keyword = resolved.len().checked_sub(2).map(|i| resolved[i].ty);
+                    in_enum = keyword == Some(TokenType::Enum);
+                    if keyword == Some(TokenType::Struct) {
+                        struct_depth = Some(depth);
+                    }
+                }
+                TokenType::RightBrace => {
+                    if struct_depth == Some(depth) {
+                        struct_depth = None;
+                    }
+                    depth -= 1;
+                    in_enum = false;
+                }
+                TokenType::Import if depth == 0 => {
+                    // 'import "path";' was already checked when the module was loaded
+                    i += 3;
+                    continue;
+                }
+                _ => (),
+            }
+
+            let previous = resolved.last().map(|token| token.ty);
+            let is_property = matches!(previous, Some(TokenType::Dot | TokenType::DoubleColon));
+            // enum variants keep their name
+            if token.ty != TokenType::Identifier || is_property || in_enum {
+                resolved.push(token);
+                i += 1;
+                continue;
+            }
+
+            if next == Some(TokenType::DoubleColon)
+                && module.imports.iter().any(|name| name == token.lexeme)
+            {
+                let imported = self.module(token.lexeme);
+                let name = match tokens.get(i + 2) {
+                    Some(name) if name.ty == TokenType::Identifier => name.lexeme,
+                    _ => {
+                        let msg = format!("Expected a name after '{}::'.", token.lexeme);
+                        return Err(parse_err(token.line, msg));
+                    }
+                };
+
+                // a function can also be passed as a value, and a global can hold a lambda
+                let qualified = match next_after(&tokens, i + 2) {
+                    Some(TokenType::LeftParen) => {
+                        imported.funcs.get(name).or(imported.globals.get(name))
+                    }
+                    _ => imported.globals.get(name).or(imported.funcs.get(name)),
+                };
+                let Some(qualified) = qualified.or(imported.types.get(name)) else {
+                    let msg = format!(
+                        "Module '{}' has no function, struct, enum or global named '{name}'.",
+                        token.lexeme
+                    );
+                    return Err(parse_err(token.line, msg));
+                };
+
+                resolved.push(Token {
+                    lexeme: qualified,
+                    ..token
+                });
+                i += 3;
+                continue;
+            }
+
+            // methods and fields are declared inside a struct, they keep their name
+            let is_method_decl = previous == Some(TokenType::Fn) && depth > 0;
+            let is_field_decl = struct_depth == Some(depth);
+            let qualified = match module.types.get(token.lexeme) {
+                Some(qualified) => Some(qualified),
+                None if next == Some(TokenType::LeftParen) && !is_method_decl => module
+                    .funcs
+                    .get(token.lexeme)
+                    .or(module.globals.get(token.lexeme)),
+                // locals with the name of a global or a function are renamed as well, so they
+                // still shadow it
+                None if next != Some(TokenType::LeftParen) && !is_field_decl => module
+                    .globals
+                    .get(token.lexeme)
+                    .or(module.funcs.get(token.lexeme)),
+                None => None,
+            };
+
+            match qualified {
+                Some(qualified) => resolved.push(Token {
+                    lexeme: qualified,
+                    ..token
+                }),
+                None => resolved.push(token),
+            }
+            i += 1;
+        }
+
+        Ok(resolved)
+    }
+
+    fn add(&mut self, name: Option<String>, file_name: String, source: String) -> usize {
+        self.modules.push(Module {
+            name,
+            file_name,
+            source,
+            imports: vec![],
+            funcs: HashMap::new(),
+            types: HashMap::new(),
+            globals: HashMap::new(),
+        });
+        self.modules.len() - 1
+    }
+
+    fn module(&self, name: &str) -> &Module {
+        let module = self
+            .modules
+            .iter()
+            .find(|module| module.name.as_deref() == Some(name));
+        module.unwrap()
+    }
+
+    fn scan(&self, index: usize) -> Result<Vec<Token<'_>>, CrustErr> {
+        let tokens = Scanner::new(&self.modules[index].source).scan_tokens();
+        match tokens {
+            Ok(mut tokens) => {
+                for token in &mut tokens {
+                    token.line = encode_line(index, token.line);
+                }
+                Ok(tokens)
+            }
+            Err(mut errs) => {
+                for err in &mut errs {
+                    err.line = encode_line(index, err.line);
+                }
+                Err(CrustErr::Scan(errs))
+            }
+        }
+    }
+
+    // `stack` holds the modules that are being loaded, used to detect import cycles
+    fn load_imports(
+        &mut self,
+        index: usize,
+        dir: &Path,
+        stack: &mut Vec<usize>,
+    ) -> Result<(), CrustErr> {
+        for (import, line) in self.read_header(index)? {
+            let parse_err = |msg: String| CrustErr::Parse(vec![ParseErr::new(line, &msg)]);
+
+            let path = normalize(&dir.join(&import));
+            let Ok(canonical) = fs::canonicalize(&path) else {
+                return Err(parse_err(format!("Could not find the file '{import}'.")));
+            };
+
+            let imported = match self.paths.get(&canonical) {
+                Some(imported) => {
+                    if let Some(start) = stack.iter().position(|index| index == imported) {
+                        let mut cycle: Vec<&str> = stack[start..]
+                            .iter()
+                            .map(|index| &self.modules[*index].file_name as &str)
+                            .collect();
+                        cycle.push(&self.modules[*imported].file_name);
+
+                        let msg = format!("Import cycle detected: {}.", cycle.join(" -> "));
+                        return Err(parse_err(msg));
+                    }
+                    *imported
+                }
+                None => {
+                    let source = fs::read_to_string(&canonical).map_err(|err| {
+                        parse_err(format!("Could not read the file '{import}': {err}."))
+                    })?;
+
+                    let name = path
+                        .file_stem()
+                        .map_or(String::new(), |stem| stem.to_string_lossy().to_string());
+                    let is_identifier = name.chars().next().is_some_and(char::is_alphabetic)
+                        && name.chars().all(|c| c.is_alphanumeric() || c == '_');
+                    if !is_identifier {
+                        let msg = format!(
+                            "'{name}' is not a valid module name, rename the file '{import}'."
+                        );
+                        return Err(parse_err(msg));
+                    }
+                    if self
+                        .modules
+                        .iter()
+                        .any(|module| module.name.as_ref() == Some(&name))
+                    {
+                        let msg = format!("Another file that is imported is also named '{name}'.");
+                        return Err(parse_err(msg));
+                    }
+
+                    let imported = self.add(Some(name), path.display().to_string(), source);
+                    self.paths.insert(canonical.clone(), imported);
+
+                    stack.push(imported);
+                    let dir = path.parent().unwrap_or(Path::new(""));
+                    self.load_imports(imported, dir, stack)?;
+                    stack.pop();
+
+                    imported
+                }
+            };
+
+            let name = self.modules[imported].name.clone().unwrap();
+            if !self.modules[index].imports.contains(&name) {
+                self.modules[index].imports.push(name);
+            }
+        }
+        Ok(())
+    }
+
+    // collects the declarations of a module and returns the files it imports
+    fn read_header(&mut self, index: usize) -> Result<Vec<(String, u32)>, CrustErr> {
+        let tokens = self.scan(index)?;
+
+        let mut imports = vec![];
+        let mut funcs = HashMap::new();
+        let mut types = HashMap::new();
+        let mut globals = HashMap::new();
+        let mut depth = 0;
+        for (i, token) in tokens.iter().enumerate() {
+            let name = match tokens.get(i + 1) {
+                Some(next) if next.ty == TokenType::Identifier => Some(next.lexeme.to_string()),
+                _ => None,
+            };
+
+            match token.ty {
+                TokenType::LeftBrace => depth += 1,
+                TokenType::RightBrace => depth -= 1,
+                TokenType::Import if depth == 0 => {
+                    let path = tokens.get(i + 1).map(|token| token.literal);
+                    let semicolon = tokens.get(i + 2).map(|token| token.ty);
+                    match (path, semicolon) {
+                        (Some(Literal::Str(path)), Some(TokenType::Semicolon)) => {
+                            imports.push((path.to_string(), token.line));
+                        }
+                        _ => {
+                            let msg = "Expected 'import \"path\";'.";
+                            let err = ParseErr::new(token.line, msg);
+                            return Err(CrustErr::Parse(vec![err]));
+                        }
+                    }
+                }
+                TokenType::Fn if depth == 0 => {
+                    if let Some(name) = name {
+                        funcs.insert(name, String::new());
+                    }
+                }
+                TokenType::Struct | TokenType::Enum | TokenType::Trait if depth == 0 => {
+                    if let Some(name) = name {
+                        types.insert(name, String::new());
+                    }
+                }
+                // 'int[] name =' or 'const str name ='
+                TokenType::Identifier if depth == 0 => {
+                    let is_decl = i > 0
+                        && (tokens[i - 1].as_value_type().is_some()
+                            || matches!(
+                                tokens[i - 1].ty,
+                                // 'int[] x', 'Vec<int> x', 'fn(int) x' and 'int? x'
+                                TokenType::RightBracket
+                                    | TokenType::Greater
+                                    | TokenType::RightParen
+                                    | TokenType::Question
+                            ));
+                    let next = next_after(&tokens, i);
+                    if is_decl && matches!(next, Some(TokenType::Equal | TokenType::Semicolon)) {
+                        globals.insert(token.lexeme.to_string(), String::new());
+                    }
+                }
+                _ => (),
+            }
+        }
+
+        let module = &mut self.modules[index];
+        if let Some(module_name) = &module.name {
+            let qualify = |(name, _): (String, String)| {
+                let qualified = format!("{module_name}::{name}");
+                (name, qualified)
+            };
+            module.funcs = funcs.into_iter().map(qualify).collect();
+            module.types = types.into_iter().map(qualify).collect();
+            module.globals = globals.into_iter().map(qualify).collect();
+        }
+
+        Ok(imports)
+    }
+}
+
+fn next_after(tokens: &[Token], index: usize) -> Option<TokenType> {
+    tokens.get(index + 1).map(|token| token.ty)
+}
+
+// removes '.' and resolves '..' where possible, so 'a/../b.crs' is reported as 'b.crs'
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => (),
+            Component::ParentDir
+                if matches!(
+                    normalized.components().next_back(),
+                    Some(Component::Normal(_))
+                ) =>
+            {
+                normalized.pop();
+            }
+            _ => normalized.push(component),
+        }
+    }
+    normalized
+}
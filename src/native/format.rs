@@ -0,0 +1,126 @@
+use crate::{
+    object::{NatCtx, Object},
+    value::StackValue,
+};
+
+/// The most values that can be passed to 'format' after the format string.
+pub const MAX_FORMAT_VALUES: usize = 8;
+
+// 'format("{} is {:.2}", name, value)', a placeholder is '{}' or '{:[<|>][0][width][.precision]}'
+// and '{{' and '}}' are literal braces
+pub fn format(args: &[StackValue], ctx: &mut NatCtx) -> Result<StackValue, String> {
+    let StackValue::Obj(Object::Str(template)) = args[0] else {
+        unreachable!()
+    };
+    let mut values = args[1..].iter();
+
+    let mut formatted = String::new();
+    let mut chars = template.data.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                formatted.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                formatted.push('}');
+            }
+            '{' => {
+                let mut spec = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => spec.push(c),
+                        None => return Err("Unclosed '{' in the format string.".to_string()),
+                    }
+                }
+
+                let Some(value) = values.next() else {
+                    let msg = format!(
+                        "The format string has more placeholders than the {} values given.",
+                        args.len() - 1
+                    );
+                    return Err(msg);
+                };
+                formatted.push_str(&format_value(*value, &spec)?);
+            }
+            '}' => return Err("Unmatched '}' in the format string.".to_string()),
+            c => formatted.push(c),
+        }
+    }
+
+    if values.next().is_some() {
+        let msg = format!(
+            "The format string has fewer placeholders than the {} values given.",
+            args.len() - 1
+        );
+        return Err(msg);
+    }
+
+    Ok(StackValue::Obj(ctx.alloc(formatted, Object::Str)))
+}
+
+fn format_value(value: StackValue, spec: &str) -> Result<String, String> {
+    let invalid = || format!("Invalid placeholder '{{{spec}}}' in the format string.");
+    if spec.is_empty() {
+        return Ok(value.to_string());
+    }
+    let Some(spec) = spec.strip_prefix(':') else {
+        return Err(invalid());
+    };
+
+    let (align, spec) = match spec.chars().next() {
+        Some(align @ ('<' | '>')) => (Some(align), &spec[1..]),
+        _ => (None, spec),
+    };
+    let (zero_pad, spec) = match spec.strip_prefix('0') {
+        Some(spec) => (true, spec),
+        None => (false, spec),
+    };
+    let (width, precision) = match spec.split_once('.') {
+        Some((width, precision)) => (width, Some(precision)),
+        None => (spec, None),
+    };
+    let width: usize = match width {
+        "" => 0,
+        width => width.parse().map_err(|_| invalid())?,
+    };
+    let precision: Option<usize> = match precision {
+        Some(precision) => Some(precision.parse().map_err(|_| invalid())?),
+        None => None,
+    };
+
+    let text = match (value, precision) {
+        (StackValue::F64(num), Some(precision)) => format!("{num:.precision$}"),
+        (StackValue::Obj(Object::Str(str)), Some(precision)) => {
+            str.data.chars().take(precision).collect()
+        }
+        (_, Some(_)) => {
+            let msg =
+                format!("A precision can only be used for doubles and strings, not '{value}'.");
+            return Err(msg);
+        }
+        (value, None) => value.to_string(),
+    };
+
+    // numbers are aligned to the right by default, and zeros are placed after the sign
+    let is_num = matches!(
+        value,
+        StackValue::F64(_) | StackValue::I64(_) | StackValue::U64(_)
+    );
+    if zero_pad && is_num {
+        let (sign, digits) = match text.strip_prefix('-') {
+            Some(digits) => ("-", digits),
+            None => ("", text.as_str()),
+        };
+        let width = width.saturating_sub(sign.len());
+        return Ok(format!("{sign}{digits:0>width$}"));
+    }
+    match align {
+        Some('<') => Ok(format!("{text:<width$}")),
+        Some(_) => Ok(format!("{text:>width$}")),
+        None if is_num => Ok(format!("{text:>width$}")),
+        None => Ok(format!("{text:<width$}")),
+    }
+}
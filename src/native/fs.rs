@@ -0,0 +1,76 @@
+use std::{fs, io::Write};
+
+use crate::{
+    object::{NatCtx, ObjArr, Object},
+    value::StackValue,
+};
+
+// paths are relative to the working directory, failures are runtime errors
+
+fn path_arg(args: &[StackValue]) -> String {
+    let StackValue::Obj(Object::Str(path)) = args[0] else {
+        unreachable!()
+    };
+    path.data.clone()
+}
+
+fn text_arg(args: &[StackValue]) -> String {
+    let StackValue::Obj(Object::Str(text)) = args[1] else {
+        unreachable!()
+    };
+    text.data.clone()
+}
+
+pub fn read_file(args: &[StackValue], ctx: &mut NatCtx) -> Result<StackValue, String> {
+    let path = path_arg(args);
+    let contents =
+        fs::read_to_string(&path).map_err(|err| format!("Could not read file '{path}': {err}."))?;
+    Ok(StackValue::Obj(ctx.alloc(contents, Object::Str)))
+}
+
+pub fn write_file(args: &[StackValue], _ctx: &mut NatCtx) -> Result<StackValue, String> {
+    let path = path_arg(args);
+    fs::write(&path, text_arg(args))
+        .map_err(|err| format!("Could not write file '{path}': {err}."))?;
+    Ok(StackValue::Null)
+}
+
+pub fn append_file(args: &[StackValue], _ctx: &mut NatCtx) -> Result<StackValue, String> {
+    let path = path_arg(args);
+    fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .and_then(|mut file| file.write_all(text_arg(args).as_bytes()))
+        .map_err(|err| format!("Could not append to file '{path}': {err}."))?;
+    Ok(StackValue::Null)
+}
+
+pub fn file_exists(args: &[StackValue], _ctx: &mut NatCtx) -> Result<StackValue, String> {
+    let path = path_arg(args);
+    Ok(StackValue::Bool(fs::metadata(path).is_ok()))
+}
+
+// the names of the entries, sorted
+pub fn list_dir(args: &[StackValue], ctx: &mut NatCtx) -> Result<StackValue, String> {
+    let path = path_arg(args);
+    let to_err = |err: std::io::Error| format!("Could not list directory '{path}': {err}.");
+    let mut names = fs::read_dir(&path)
+        .map_err(to_err)?
+        .map(|entry| entry.map(|entry| entry.file_name().to_string_lossy().into_owned()))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(to_err)?;
+    names.sort();
+
+    let names = names
+        .into_iter()
+        .map(|name| StackValue::Obj(ctx.alloc(name, Object::Str)))
+        .collect();
+    Ok(StackValue::Obj(ctx.alloc(ObjArr::new(names), Object::Arr)))
+}
+
+pub fn remove_file(args: &[StackValue], _ctx: &mut NatCtx) -> Result<StackValue, String> {
+    let path = path_arg(args);
+    fs::remove_file(&path).map_err(|err| format!("Could not remove file '{path}': {err}."))?;
+    Ok(StackValue::Null)
+}
@@ -1,6 +1,15 @@
 use std::collections::HashMap;
 
-use crate::{analysis_types::NatFuncData, heap::Heap, object::Object, value::StackValue};
+use super::{
+    format::{format, MAX_FORMAT_VALUES},
+    fs::{append_file, file_exists, list_dir, read_file, remove_file, write_file},
+    result::{err, err_ty, none, none_ty, ok, ok_ty, some, some_ty},
+};
+use crate::{
+    analysis_types::NatFuncData,
+    object::{NatCtx, ObjArr, Object},
+    value::StackValue,
+};
 
 pub fn register(nat_funcs: &mut HashMap<&str, Vec<NatFuncData>>) {
     use crate::value::ValueType;
@@ -24,6 +33,39 @@ pub fn register(nat_funcs: &mut HashMap<&str, Vec<NatFuncData>>) {
     add_func!("clock", clock, vec![], VT::F64);
     add_func!("print", print, vec![VT::Any], VT::Null);
     add_func!("println", println, vec![VT::Any], VT::Null);
+    add_func!("to_string", to_string, vec![VT::Any], VT::Str);
+    // return null at the end of the input
+    add_func!("read_line", read_line, vec![], VT::nullable(VT::Str));
+    add_func!("read_all", read_all, vec![], VT::nullable(VT::Str));
+    add_func!("exit", exit, vec![VT::I64], VT::Null);
+
+    add_func!("ok", ok, vec![VT::TypeParam("T".to_string())], ok_ty());
+    add_func!("err", err, vec![VT::TypeParam("E".to_string())], err_ty());
+    add_func!(
+        "some",
+        some,
+        vec![VT::TypeParam("T".to_string())],
+        some_ty()
+    );
+    add_func!("none", none, vec![], none_ty());
+
+    add_func!("read_file", read_file, vec![VT::Str], VT::Str);
+    add_func!("write_file", write_file, vec![VT::Str, VT::Str], VT::Null);
+    add_func!("append_file", append_file, vec![VT::Str, VT::Str], VT::Null);
+    add_func!("file_exists", file_exists, vec![VT::Str], VT::Bool);
+    add_func!(
+        "list_dir",
+        list_dir,
+        vec![VT::Str],
+        VT::Arr(Box::new(VT::Str))
+    );
+    add_func!("remove_file", remove_file, vec![VT::Str], VT::Null);
+    // an overload for every number of values
+    for count in 0..=MAX_FORMAT_VALUES {
+        let mut parameters = vec![VT::Str];
+        parameters.extend(std::iter::repeat_n(VT::Any, count));
+        add_func!("format", format, parameters, VT::Str);
+    }
 
     add_func!("sin", sin, vec![VT::F64], VT::F64);
     add_func!("cos", cos, vec![VT::F64], VT::F64);
@@ -44,155 +86,351 @@ pub fn register(nat_funcs: &mut HashMap<&str, Vec<NatFuncData>>) {
 
     add_func!("pow", pow, vec![VT::F64, VT::F64], VT::F64);
 
+    add_func!(
+        "wrapping_add",
+        wrapping_add_i64,
+        vec![VT::I64, VT::I64],
+        VT::I64
+    );
+    add_func!(
+        "wrapping_add",
+        wrapping_add_u64,
+        vec![VT::U64, VT::U64],
+        VT::U64
+    );
+    add_func!(
+        "wrapping_sub",
+        wrapping_sub_i64,
+        vec![VT::I64, VT::I64],
+        VT::I64
+    );
+    add_func!(
+        "wrapping_sub",
+        wrapping_sub_u64,
+        vec![VT::U64, VT::U64],
+        VT::U64
+    );
+    add_func!(
+        "wrapping_mul",
+        wrapping_mul_i64,
+        vec![VT::I64, VT::I64],
+        VT::I64
+    );
+    add_func!(
+        "wrapping_mul",
+        wrapping_mul_u64,
+        vec![VT::U64, VT::U64],
+        VT::U64
+    );
+
+    // these return null instead of a runtime error on overflow or division by zero
+    add_func!(
+        "checked_add",
+        checked_add_i64,
+        vec![VT::I64, VT::I64],
+        VT::nullable(VT::I64)
+    );
+    add_func!(
+        "checked_add",
+        checked_add_u64,
+        vec![VT::U64, VT::U64],
+        VT::nullable(VT::U64)
+    );
+    add_func!(
+        "checked_sub",
+        checked_sub_i64,
+        vec![VT::I64, VT::I64],
+        VT::nullable(VT::I64)
+    );
+    add_func!(
+        "checked_sub",
+        checked_sub_u64,
+        vec![VT::U64, VT::U64],
+        VT::nullable(VT::U64)
+    );
+    add_func!(
+        "checked_mul",
+        checked_mul_i64,
+        vec![VT::I64, VT::I64],
+        VT::nullable(VT::I64)
+    );
+    add_func!(
+        "checked_mul",
+        checked_mul_u64,
+        vec![VT::U64, VT::U64],
+        VT::nullable(VT::U64)
+    );
+    add_func!(
+        "checked_div",
+        checked_div_i64,
+        vec![VT::I64, VT::I64],
+        VT::nullable(VT::I64)
+    );
+    add_func!(
+        "checked_div",
+        checked_div_u64,
+        vec![VT::U64, VT::U64],
+        VT::nullable(VT::U64)
+    );
+
     add_func!("len", len, vec![VT::Arr(Box::new(VT::Any))], VT::U64);
+    add_func!("len", len_str, vec![VT::Str], VT::U64);
     add_func!("print_heap", print_heap, vec![], VT::Null);
 }
 
-fn clock(_args: &[StackValue], _heap: &mut Heap) -> StackValue {
+fn clock(_args: &[StackValue], _ctx: &mut NatCtx) -> Result<StackValue, String> {
     use std::time::{SystemTime, UNIX_EPOCH};
 
     let time = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
 
-    StackValue::F64(time.as_secs_f64())
+    Ok(StackValue::F64(time.as_secs_f64()))
+}
+
+fn print(args: &[StackValue], ctx: &mut NatCtx) -> Result<StackValue, String> {
+    write!(ctx.out, "{}", args[0]).map_err(|err| err.to_string())?;
+
+    Ok(StackValue::Null)
+}
+fn println(args: &[StackValue], ctx: &mut NatCtx) -> Result<StackValue, String> {
+    writeln!(ctx.out, "{}", args[0]).map_err(|err| err.to_string())?;
+
+    Ok(StackValue::Null)
 }
 
-fn print(args: &[StackValue], _heap: &mut Heap) -> StackValue {
-    use colored::Colorize;
+// used by f-strings
+fn to_string(args: &[StackValue], ctx: &mut NatCtx) -> Result<StackValue, String> {
+    match args[0] {
+        StackValue::Obj(Object::Str(_)) => Ok(args[0]),
+        value => Ok(StackValue::Obj(ctx.alloc(value.to_string(), Object::Str))),
+    }
+}
+
+fn read_line(_args: &[StackValue], ctx: &mut NatCtx) -> Result<StackValue, String> {
+    let mut line = String::new();
+    let read = ctx
+        .input
+        .read_line(&mut line)
+        .map_err(|err| err.to_string())?;
+    if read == 0 {
+        return Ok(StackValue::Null);
+    }
 
-    let string = format!("{}", args[0]).green();
-    print!("{string}");
+    if line.ends_with('\n') {
+        line.pop();
+        if line.ends_with('\r') {
+            line.pop();
+        }
+    }
+    Ok(StackValue::Obj(ctx.alloc(line, Object::Str)))
+}
 
-    StackValue::Null
+fn read_all(_args: &[StackValue], ctx: &mut NatCtx) -> Result<StackValue, String> {
+    let mut input = String::new();
+    let read = ctx
+        .input
+        .read_to_string(&mut input)
+        .map_err(|err| err.to_string())?;
+    if read == 0 {
+        return Ok(StackValue::Null);
+    }
+    Ok(StackValue::Obj(ctx.alloc(input, Object::Str)))
 }
-fn println(args: &[StackValue], _heap: &mut Heap) -> StackValue {
-    use colored::Colorize;
 
-    let string = format!("{}", args[0]).green();
-    println!("{string}");
+fn exit(args: &[StackValue], ctx: &mut NatCtx) -> Result<StackValue, String> {
+    let StackValue::I64(code) = args[0] else {
+        unreachable!()
+    };
+    ctx.exit_code = Some(code as i32);
+    Ok(StackValue::Null)
+}
 
-    StackValue::Null
+/// Returns the command-line arguments, passed to 'main' when it takes them.
+pub fn program_args(_args: &[StackValue], ctx: &mut NatCtx) -> Result<StackValue, String> {
+    let args = ctx.args.to_vec();
+    let args = args
+        .into_iter()
+        .map(|arg| StackValue::Obj(ctx.alloc(arg, Object::Str)))
+        .collect();
+    Ok(StackValue::Obj(ctx.alloc(ObjArr::new(args), Object::Arr)))
 }
 
-fn sin(args: &[StackValue], _heap: &mut Heap) -> StackValue {
+fn sin(args: &[StackValue], _ctx: &mut NatCtx) -> Result<StackValue, String> {
     let val = args[0];
     if let StackValue::F64(val) = val {
-        StackValue::F64(val.sin())
+        Ok(StackValue::F64(val.sin()))
     } else {
         unreachable!()
     }
 }
 
-fn cos(args: &[StackValue], _heap: &mut Heap) -> StackValue {
+fn cos(args: &[StackValue], _ctx: &mut NatCtx) -> Result<StackValue, String> {
     let val = args[0];
     if let StackValue::F64(val) = val {
-        StackValue::F64(val.cos())
+        Ok(StackValue::F64(val.cos()))
     } else {
         unreachable!()
     }
 }
 
-fn tan(args: &[StackValue], _heap: &mut Heap) -> StackValue {
+fn tan(args: &[StackValue], _ctx: &mut NatCtx) -> Result<StackValue, String> {
     let val = args[0];
     if let StackValue::F64(val) = val {
-        StackValue::F64(val.tan())
+        Ok(StackValue::F64(val.tan()))
     } else {
         unreachable!()
     }
 }
 
-fn min_f64(args: &[StackValue], _heap: &mut Heap) -> StackValue {
+fn min_f64(args: &[StackValue], _ctx: &mut NatCtx) -> Result<StackValue, String> {
     let val1 = args[0];
     let val2 = args[1];
     match (val1, val2) {
-        (StackValue::F64(val1), StackValue::F64(val2)) => StackValue::F64(val1.min(val2)),
+        (StackValue::F64(val1), StackValue::F64(val2)) => Ok(StackValue::F64(val1.min(val2))),
         _ => unreachable!(),
     }
 }
-fn min_u64(args: &[StackValue], _heap: &mut Heap) -> StackValue {
+fn min_u64(args: &[StackValue], _ctx: &mut NatCtx) -> Result<StackValue, String> {
     let val1 = args[0];
     let val2 = args[1];
     match (val1, val2) {
-        (StackValue::U64(val1), StackValue::U64(val2)) => StackValue::U64(val1.min(val2)),
+        (StackValue::U64(val1), StackValue::U64(val2)) => Ok(StackValue::U64(val1.min(val2))),
         _ => unreachable!(),
     }
 }
-fn min_i64(args: &[StackValue], _heap: &mut Heap) -> StackValue {
+fn min_i64(args: &[StackValue], _ctx: &mut NatCtx) -> Result<StackValue, String> {
     let val1 = args[0];
     let val2 = args[1];
     match (val1, val2) {
-        (StackValue::I64(val1), StackValue::I64(val2)) => StackValue::I64(val1.min(val2)),
+        (StackValue::I64(val1), StackValue::I64(val2)) => Ok(StackValue::I64(val1.min(val2))),
         _ => unreachable!(),
     }
 }
-fn max_f64(args: &[StackValue], _heap: &mut Heap) -> StackValue {
+fn max_f64(args: &[StackValue], _ctx: &mut NatCtx) -> Result<StackValue, String> {
     let val1 = args[0];
     let val2 = args[1];
     match (val1, val2) {
-        (StackValue::F64(val1), StackValue::F64(val2)) => StackValue::F64(val1.max(val2)),
+        (StackValue::F64(val1), StackValue::F64(val2)) => Ok(StackValue::F64(val1.max(val2))),
         _ => unreachable!(),
     }
 }
-fn max_u64(args: &[StackValue], _heap: &mut Heap) -> StackValue {
+fn max_u64(args: &[StackValue], _ctx: &mut NatCtx) -> Result<StackValue, String> {
     let val1 = args[0];
     let val2 = args[1];
     match (val1, val2) {
-        (StackValue::U64(val1), StackValue::U64(val2)) => StackValue::U64(val1.max(val2)),
+        (StackValue::U64(val1), StackValue::U64(val2)) => Ok(StackValue::U64(val1.max(val2))),
         _ => unreachable!(),
     }
 }
-fn max_i64(args: &[StackValue], _heap: &mut Heap) -> StackValue {
+fn max_i64(args: &[StackValue], _ctx: &mut NatCtx) -> Result<StackValue, String> {
     let val1 = args[0];
     let val2 = args[1];
     match (val1, val2) {
-        (StackValue::I64(val1), StackValue::I64(val2)) => StackValue::I64(val1.max(val2)),
+        (StackValue::I64(val1), StackValue::I64(val2)) => Ok(StackValue::I64(val1.max(val2))),
         _ => unreachable!(),
     }
 }
 
-fn abs_f64(args: &[StackValue], _heap: &mut Heap) -> StackValue {
+fn abs_f64(args: &[StackValue], _ctx: &mut NatCtx) -> Result<StackValue, String> {
     let val = args[0];
     if let StackValue::F64(val) = val {
-        StackValue::F64(val.abs())
+        Ok(StackValue::F64(val.abs()))
     } else {
         unreachable!()
     }
 }
-fn abs_i64(args: &[StackValue], _heap: &mut Heap) -> StackValue {
+fn abs_i64(args: &[StackValue], _ctx: &mut NatCtx) -> Result<StackValue, String> {
     let val = args[0];
     if let StackValue::I64(val) = val {
-        StackValue::I64(val.abs())
+        Ok(StackValue::I64(val.abs()))
     } else {
         unreachable!()
     }
 }
 
-fn sqrt(args: &[StackValue], _heap: &mut Heap) -> StackValue {
+fn sqrt(args: &[StackValue], _ctx: &mut NatCtx) -> Result<StackValue, String> {
     let val = args[0];
     if let StackValue::F64(val) = val {
-        StackValue::F64(val.sqrt())
+        Ok(StackValue::F64(val.sqrt()))
     } else {
         unreachable!()
     }
 }
 
-fn pow(args: &[StackValue], _heap: &mut Heap) -> StackValue {
+fn pow(args: &[StackValue], _ctx: &mut NatCtx) -> Result<StackValue, String> {
     let val1 = args[0];
     let val2 = args[1];
     match (val1, val2) {
-        (StackValue::F64(val1), StackValue::F64(val2)) => StackValue::F64(val1.powf(val2)),
+        (StackValue::F64(val1), StackValue::F64(val2)) => Ok(StackValue::F64(val1.powf(val2))),
         _ => unreachable!(),
     }
 }
 
-fn len(args: &[StackValue], _heap: &mut Heap) -> StackValue {
+macro_rules! wrapping_op {
+    ($($fun_name: ident, $method: ident, $variant: ident;)*) => {
+        $(
+            fn $fun_name(args: &[StackValue], _ctx: &mut NatCtx) -> Result<StackValue, String> {
+                match (args[0], args[1]) {
+                    (StackValue::$variant(val1), StackValue::$variant(val2)) => {
+                        Ok(StackValue::$variant(val1.$method(val2)))
+                    }
+                    _ => unreachable!(),
+                }
+            }
+        )*
+    };
+}
+
+macro_rules! checked_op {
+    ($($fun_name: ident, $method: ident, $variant: ident;)*) => {
+        $(
+            fn $fun_name(args: &[StackValue], _ctx: &mut NatCtx) -> Result<StackValue, String> {
+                match (args[0], args[1]) {
+                    (StackValue::$variant(val1), StackValue::$variant(val2)) => {
+                        Ok(val1.$method(val2).map_or(StackValue::Null, StackValue::$variant))
+                    }
+                    _ => unreachable!(),
+                }
+            }
+        )*
+    };
+}
+
+wrapping_op!(
+    wrapping_add_i64, wrapping_add, I64;
+    wrapping_add_u64, wrapping_add, U64;
+    wrapping_sub_i64, wrapping_sub, I64;
+    wrapping_sub_u64, wrapping_sub, U64;
+    wrapping_mul_i64, wrapping_mul, I64;
+    wrapping_mul_u64, wrapping_mul, U64;
+);
+
+checked_op!(
+    checked_add_i64, checked_add, I64;
+    checked_add_u64, checked_add, U64;
+    checked_sub_i64, checked_sub, I64;
+    checked_sub_u64, checked_sub, U64;
+    checked_mul_i64, checked_mul, I64;
+    checked_mul_u64, checked_mul, U64;
+    checked_div_i64, checked_div, I64;
+    checked_div_u64, checked_div, U64;
+);
+
+fn len(args: &[StackValue], _ctx: &mut NatCtx) -> Result<StackValue, String> {
     let arr = args[0];
     match arr {
-        StackValue::Obj(Object::Arr(arr)) => StackValue::U64(arr.data.elements.len() as u64),
+        StackValue::Obj(Object::Arr(arr)) => Ok(StackValue::U64(arr.data.elements.len() as u64)),
         _ => unreachable!(),
     }
 }
 
-fn print_heap(_args: &[StackValue], heap: &mut Heap) -> StackValue {
-    heap.print();
-    StackValue::Null
+fn len_str(args: &[StackValue], _ctx: &mut NatCtx) -> Result<StackValue, String> {
+    let StackValue::Obj(Object::Str(str)) = args[0] else {
+        unreachable!()
+    };
+    Ok(StackValue::U64(str.data.chars().count() as u64))
+}
+
+fn print_heap(_args: &[StackValue], ctx: &mut NatCtx) -> Result<StackValue, String> {
+    ctx.heap.print();
+    Ok(StackValue::Null)
 }
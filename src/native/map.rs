@@ -0,0 +1,162 @@
+use std::collections::HashMap;
+
+use crate::{
+    analysis_types::{NatFuncData, NatStructData},
+    object::{Gc, NatCtx, ObjArr, ObjMap, Object},
+    value::{StackValue, ValueType},
+};
+
+pub fn register<'a>(structs: &mut HashMap<&'a str, NatStructData<'a>>) {
+    let name = "Map";
+    let key_ty = ValueType::TypeParam("K".to_string());
+    let value_ty = ValueType::TypeParam("V".to_string());
+
+    let insert = NatFuncData {
+        parameters: vec![key_ty.clone(), value_ty.clone()],
+        func: insert,
+        return_ty: ValueType::Null,
+        use_self: true,
+    };
+    let get = NatFuncData {
+        parameters: vec![key_ty.clone()],
+        func: get,
+        return_ty: value_ty.clone(),
+        use_self: true,
+    };
+    let remove = NatFuncData {
+        parameters: vec![key_ty.clone()],
+        func: remove,
+        return_ty: value_ty.clone(),
+        use_self: true,
+    };
+    let contains = NatFuncData {
+        parameters: vec![key_ty.clone()],
+        func: contains,
+        return_ty: ValueType::Bool,
+        use_self: true,
+    };
+    let len = NatFuncData {
+        parameters: vec![],
+        func: len,
+        return_ty: ValueType::U64,
+        use_self: true,
+    };
+    let keys = NatFuncData {
+        parameters: vec![],
+        func: keys,
+        return_ty: ValueType::Arr(Box::new(key_ty)),
+        use_self: true,
+    };
+    let values = NatFuncData {
+        parameters: vec![],
+        func: values,
+        return_ty: ValueType::Arr(Box::new(value_ty)),
+        use_self: true,
+    };
+    let print = NatFuncData {
+        parameters: vec![],
+        func: print,
+        return_ty: ValueType::Null,
+        use_self: true,
+    };
+
+    // the entries aren't a field, they're created by the first method that needs them
+    let data = NatStructData {
+        fields: vec![],
+        methods: vec![
+            ("insert", insert),
+            ("get", get),
+            ("remove", remove),
+            ("contains", contains),
+            ("len", len),
+            ("keys", keys),
+            ("values", values),
+            ("print", print),
+        ],
+        type_params: vec!["K", "V"],
+    };
+    structs.insert(name, data);
+}
+
+// returns the entries of the map in 'self', if it has any
+fn entries(this: StackValue) -> Option<Gc<ObjMap>> {
+    let StackValue::Obj(Object::Inst(inst)) = this else {
+        unreachable!()
+    };
+
+    match inst.data.fields.first() {
+        Some(StackValue::Obj(Object::Map(map))) => Some(*map),
+        Some(_) => unreachable!(),
+        None => None,
+    }
+}
+
+fn insert(args: &[StackValue], ctx: &mut NatCtx) -> Result<StackValue, String> {
+    let mut map = match entries(args[0]) {
+        Some(map) => map,
+        None => {
+            let StackValue::Obj(Object::Inst(mut inst)) = args[0] else {
+                unreachable!()
+            };
+            // the instance is on the stack, so it keeps the entries alive
+            let Object::Map(map) = ctx.alloc(ObjMap::new(), Object::Map) else {
+                unreachable!()
+            };
+            inst.data.fields.push(StackValue::Obj(Object::Map(map)));
+            map
+        }
+    };
+
+    map.data.insert(args[1], args[2]);
+    Ok(StackValue::Null)
+}
+
+fn get(args: &[StackValue], _ctx: &mut NatCtx) -> Result<StackValue, String> {
+    match entries(args[0]).and_then(|map| map.data.get(args[1])) {
+        Some(value) => Ok(value),
+        None => Err(format!("The key '{}' isn't in the map.", args[1])),
+    }
+}
+
+fn remove(args: &[StackValue], _ctx: &mut NatCtx) -> Result<StackValue, String> {
+    match entries(args[0]).and_then(|mut map| map.data.remove(args[1])) {
+        Some(value) => Ok(value),
+        None => Err(format!("The key '{}' isn't in the map.", args[1])),
+    }
+}
+
+fn contains(args: &[StackValue], _ctx: &mut NatCtx) -> Result<StackValue, String> {
+    let found = entries(args[0]).is_some_and(|map| map.data.contains(args[1]));
+    Ok(StackValue::Bool(found))
+}
+
+fn len(args: &[StackValue], _ctx: &mut NatCtx) -> Result<StackValue, String> {
+    let len = entries(args[0]).map_or(0, |map| map.data.entries.len());
+    Ok(StackValue::U64(len as u64))
+}
+
+fn keys(args: &[StackValue], ctx: &mut NatCtx) -> Result<StackValue, String> {
+    let keys = match entries(args[0]) {
+        Some(map) => map.data.entries.iter().map(|(key, _)| *key).collect(),
+        None => vec![],
+    };
+    Ok(StackValue::Obj(ctx.alloc(ObjArr::new(keys), Object::Arr)))
+}
+
+fn values(args: &[StackValue], ctx: &mut NatCtx) -> Result<StackValue, String> {
+    let values = match entries(args[0]) {
+        Some(map) => map.data.entries.iter().map(|(_, value)| *value).collect(),
+        None => vec![],
+    };
+    Ok(StackValue::Obj(ctx.alloc(ObjArr::new(values), Object::Arr)))
+}
+
+fn print(args: &[StackValue], ctx: &mut NatCtx) -> Result<StackValue, String> {
+    let entries = match entries(args[0]) {
+        Some(map) => StackValue::Obj(Object::Map(map)).to_string(),
+        None => "{}".to_string(),
+    };
+    writeln!(ctx.out, "{entries}").map_err(|err| err.to_string())?;
+
+    Ok(StackValue::Null)
+}
@@ -2,19 +2,38 @@ use crate::analysis_types::{NatFuncData, NatStructData};
 
 use std::collections::HashMap;
 
+pub use funcs::program_args;
+pub use result::{OPTION, RESULT};
+pub use string::STR_METHODS;
+
+mod format;
+mod fs;
 mod funcs;
+mod map;
+mod result;
+mod string;
 mod structs;
 mod vec;
 
-pub fn register<'a>() -> (
-    HashMap<&'a str, Vec<NatFuncData>>,
-    HashMap<&'a str, NatStructData<'a>>,
-) {
-    let mut funcs = HashMap::new();
-    let mut structs = HashMap::new();
+/// The native types whose values don't carry their methods, the methods are looked up by the
+/// type instead.
+pub const STATIC_METHOD_TYPES: [&str; 3] = [STR_METHODS, RESULT, OPTION];
+
+/// The native functions and structs available to a program, the builtin ones and the ones
+/// registered by the host application.
+#[derive(Debug, Clone)]
+pub struct Natives<'a> {
+    pub funcs: HashMap<&'a str, Vec<NatFuncData>>,
+    pub structs: HashMap<&'a str, NatStructData<'a>>,
+}
+impl<'a> Natives<'a> {
+    pub fn new() -> Self {
+        let mut funcs = HashMap::new();
+        let mut structs = HashMap::new();
 
-    funcs::register(&mut funcs);
-    structs::register(&mut structs);
+        funcs::register(&mut funcs);
+        structs::register(&mut structs);
 
-    (funcs, structs)
+        Self { funcs, structs }
+    }
 }
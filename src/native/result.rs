@@ -0,0 +1,171 @@
+use std::collections::HashMap;
+
+use crate::{
+    analysis_types::{NatFuncData, NatStructData},
+    object::{NatCtx, ObjEnum, Object},
+    value::{StackValue, ValueType},
+};
+
+/// The name of the type returned by `ok` and `err`.
+pub const RESULT: &str = "Result";
+/// The name of the type returned by `some` and `none`.
+pub const OPTION: &str = "Option";
+
+// both are enums on the heap, the variant with tag 0 ('ok' or 'some') holds the value
+const VALUE_TAG: u8 = 0;
+const EMPTY_TAG: u8 = 1;
+
+pub fn register<'a>(structs: &mut HashMap<&'a str, NatStructData<'a>>) {
+    macro_rules! method {
+        ($name: expr, $func: ident, $parameters: expr, $return_ty: expr) => {
+            (
+                $name,
+                NatFuncData {
+                    parameters: $parameters,
+                    func: $func,
+                    return_ty: $return_ty,
+                    use_self: true,
+                },
+            )
+        };
+    }
+
+    use ValueType as VT;
+    let value_ty = VT::TypeParam("T".to_string());
+    let error_ty = VT::TypeParam("E".to_string());
+
+    let methods = vec![
+        method!("is_ok", holds_value, vec![], VT::Bool),
+        method!("is_err", is_empty, vec![], VT::Bool),
+        method!("unwrap", unwrap_result, vec![], value_ty.clone()),
+        method!("unwrap_err", unwrap_err, vec![], error_ty),
+        method!(
+            "unwrap_or",
+            unwrap_or,
+            vec![value_ty.clone()],
+            value_ty.clone()
+        ),
+    ];
+    let data = NatStructData {
+        fields: vec![],
+        methods,
+        type_params: vec!["T", "E"],
+    };
+    structs.insert(RESULT, data);
+
+    let methods = vec![
+        method!("is_some", holds_value, vec![], VT::Bool),
+        method!("is_none", is_empty, vec![], VT::Bool),
+        method!("unwrap", unwrap_option, vec![], value_ty.clone()),
+        method!("unwrap_or", unwrap_or, vec![value_ty.clone()], value_ty),
+    ];
+    let data = NatStructData {
+        fields: vec![],
+        methods,
+        type_params: vec!["T"],
+    };
+    structs.insert(OPTION, data);
+}
+
+/// The type of `ok(value)`, the error type is left open.
+pub fn ok_ty() -> ValueType {
+    let args = vec![ValueType::TypeParam("T".to_string()), ValueType::Any];
+    ValueType::generic(RESULT.to_string(), args)
+}
+
+/// The type of `err(error)`, the value type is left open.
+pub fn err_ty() -> ValueType {
+    let args = vec![ValueType::Any, ValueType::TypeParam("E".to_string())];
+    ValueType::generic(RESULT.to_string(), args)
+}
+
+/// The type of `some(value)`.
+pub fn some_ty() -> ValueType {
+    let args = vec![ValueType::TypeParam("T".to_string())];
+    ValueType::generic(OPTION.to_string(), args)
+}
+
+/// The type of `none()`, the value type is left open.
+pub fn none_ty() -> ValueType {
+    ValueType::generic(OPTION.to_string(), vec![ValueType::Any])
+}
+
+fn alloc_variant(
+    tag: u8,
+    name: &str,
+    values: Vec<StackValue>,
+    ctx: &mut NatCtx,
+) -> Result<StackValue, String> {
+    let name = ctx.alloc(name.to_string(), Object::Str);
+    let value = ObjEnum::new(tag, name, values);
+    Ok(StackValue::Obj(ctx.alloc(value, Object::Enum)))
+}
+
+pub fn ok(args: &[StackValue], ctx: &mut NatCtx) -> Result<StackValue, String> {
+    alloc_variant(VALUE_TAG, "ok", vec![args[0]], ctx)
+}
+
+pub fn err(args: &[StackValue], ctx: &mut NatCtx) -> Result<StackValue, String> {
+    alloc_variant(EMPTY_TAG, "err", vec![args[0]], ctx)
+}
+
+pub fn some(args: &[StackValue], ctx: &mut NatCtx) -> Result<StackValue, String> {
+    alloc_variant(VALUE_TAG, "some", vec![args[0]], ctx)
+}
+
+pub fn none(_args: &[StackValue], ctx: &mut NatCtx) -> Result<StackValue, String> {
+    alloc_variant(EMPTY_TAG, "none", vec![], ctx)
+}
+
+fn as_variant(value: &StackValue) -> &ObjEnum {
+    let StackValue::Obj(Object::Enum(variant)) = value else {
+        unreachable!()
+    };
+    &variant.data
+}
+
+fn holds_value(args: &[StackValue], _ctx: &mut NatCtx) -> Result<StackValue, String> {
+    Ok(StackValue::Bool(as_variant(&args[0]).tag == VALUE_TAG))
+}
+
+fn is_empty(args: &[StackValue], _ctx: &mut NatCtx) -> Result<StackValue, String> {
+    Ok(StackValue::Bool(as_variant(&args[0]).tag == EMPTY_TAG))
+}
+
+fn unwrap_result(args: &[StackValue], _ctx: &mut NatCtx) -> Result<StackValue, String> {
+    let variant = as_variant(&args[0]);
+    match variant.tag {
+        VALUE_TAG => Ok(variant.values[0]),
+        _ => Err(format!(
+            "Called 'unwrap' on an error: {}.",
+            variant.values[0].display()
+        )),
+    }
+}
+
+fn unwrap_err(args: &[StackValue], _ctx: &mut NatCtx) -> Result<StackValue, String> {
+    let variant = as_variant(&args[0]);
+    match variant.tag {
+        EMPTY_TAG => Ok(variant.values[0]),
+        _ => Err(format!(
+            "Called 'unwrap_err' on a value: {}.",
+            variant.values[0].display()
+        )),
+    }
+}
+
+fn unwrap_option(args: &[StackValue], _ctx: &mut NatCtx) -> Result<StackValue, String> {
+    let variant = as_variant(&args[0]);
+    match variant.tag {
+        VALUE_TAG => Ok(variant.values[0]),
+        _ => Err("Called 'unwrap' on none.".to_string()),
+    }
+}
+
+fn unwrap_or(args: &[StackValue], _ctx: &mut NatCtx) -> Result<StackValue, String> {
+    let variant = as_variant(&args[0]);
+    match variant.tag {
+        VALUE_TAG => Ok(variant.values[0]),
+        _ => Ok(args[1]),
+    }
+}
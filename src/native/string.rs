@@ -0,0 +1,171 @@
+use std::collections::HashMap;
+
+use crate::{
+    analysis_types::{NatFuncData, NatStructData},
+    object::{NatCtx, ObjArr, Object},
+    value::{StackValue, ValueType},
+};
+
+/// The name the methods of strings are registered under.
+pub const STR_METHODS: &str = "str";
+
+pub fn register<'a>(structs: &mut HashMap<&'a str, NatStructData<'a>>) {
+    macro_rules! method {
+        ($func: ident, $parameters: expr, $return_ty: expr) => {
+            (
+                stringify!($func),
+                NatFuncData {
+                    parameters: $parameters,
+                    func: $func,
+                    return_ty: $return_ty,
+                    use_self: true,
+                },
+            )
+        };
+    }
+
+    use ValueType as VT;
+    let str_arr = VT::Arr(Box::new(VT::Str));
+    let methods = vec![
+        method!(len, vec![], VT::U64),
+        method!(substring, vec![VT::U64, VT::U64], VT::Str),
+        method!(find, vec![VT::Str], VT::I64),
+        method!(contains, vec![VT::Str], VT::Bool),
+        method!(split, vec![VT::Str], str_arr.clone()),
+        method!(trim, vec![], VT::Str),
+        method!(to_upper, vec![], VT::Str),
+        method!(to_lower, vec![], VT::Str),
+        method!(replace, vec![VT::Str, VT::Str], VT::Str),
+        method!(starts_with, vec![VT::Str], VT::Bool),
+        method!(ends_with, vec![VT::Str], VT::Bool),
+        method!(chars, vec![], str_arr),
+        // these return null when the string isn't a number
+        method!(parse_int, vec![], VT::nullable(VT::I64)),
+        method!(parse_double, vec![], VT::nullable(VT::F64)),
+    ];
+
+    let data = NatStructData {
+        fields: vec![],
+        methods,
+        type_params: vec![],
+    };
+    structs.insert(STR_METHODS, data);
+}
+
+fn as_str(value: &StackValue) -> &str {
+    let StackValue::Obj(Object::Str(str)) = value else {
+        unreachable!()
+    };
+    &str.data
+}
+
+fn alloc_str(string: String, ctx: &mut NatCtx) -> StackValue {
+    StackValue::Obj(ctx.alloc(string, Object::Str))
+}
+
+fn alloc_strs<'s>(strings: impl Iterator<Item = &'s str>, ctx: &mut NatCtx) -> StackValue {
+    let strings = strings.map(|s| alloc_str(s.to_string(), ctx)).collect();
+    StackValue::Obj(ctx.alloc(ObjArr::new(strings), Object::Arr))
+}
+
+fn len(args: &[StackValue], _ctx: &mut NatCtx) -> Result<StackValue, String> {
+    Ok(StackValue::U64(as_str(&args[0]).chars().count() as u64))
+}
+
+// positions are counted in characters, not bytes
+fn substring(args: &[StackValue], ctx: &mut NatCtx) -> Result<StackValue, String> {
+    let str = as_str(&args[0]);
+    let len = str.chars().count();
+    let (StackValue::U64(start), StackValue::U64(end)) = (args[1], args[2]) else {
+        unreachable!()
+    };
+    if start > end || end as usize > len {
+        let msg =
+            format!("The range {start} to {end} is out of bounds for a string of length {len}.");
+        return Err(msg);
+    }
+
+    let substring = str
+        .chars()
+        .skip(start as usize)
+        .take((end - start) as usize);
+    Ok(alloc_str(substring.collect(), ctx))
+}
+
+// the position of the first occurrence, or -1
+fn find(args: &[StackValue], _ctx: &mut NatCtx) -> Result<StackValue, String> {
+    let str = as_str(&args[0]);
+    let position = match str.find(as_str(&args[1])) {
+        Some(byte_index) => str[..byte_index].chars().count() as i64,
+        None => -1,
+    };
+    Ok(StackValue::I64(position))
+}
+
+fn contains(args: &[StackValue], _ctx: &mut NatCtx) -> Result<StackValue, String> {
+    let found = as_str(&args[0]).contains(as_str(&args[1]));
+    Ok(StackValue::Bool(found))
+}
+
+fn split(args: &[StackValue], ctx: &mut NatCtx) -> Result<StackValue, String> {
+    let (str, separator) = (as_str(&args[0]), as_str(&args[1]));
+    if separator.is_empty() {
+        return Err("Can't split a string by an empty separator.".to_string());
+    }
+    Ok(alloc_strs(str.split(separator), ctx))
+}
+
+fn trim(args: &[StackValue], ctx: &mut NatCtx) -> Result<StackValue, String> {
+    let trimmed = as_str(&args[0]).trim().to_string();
+    Ok(alloc_str(trimmed, ctx))
+}
+
+fn to_upper(args: &[StackValue], ctx: &mut NatCtx) -> Result<StackValue, String> {
+    let upper = as_str(&args[0]).to_uppercase();
+    Ok(alloc_str(upper, ctx))
+}
+
+fn to_lower(args: &[StackValue], ctx: &mut NatCtx) -> Result<StackValue, String> {
+    let lower = as_str(&args[0]).to_lowercase();
+    Ok(alloc_str(lower, ctx))
+}
+
+fn replace(args: &[StackValue], ctx: &mut NatCtx) -> Result<StackValue, String> {
+    let (from, to) = (as_str(&args[1]), as_str(&args[2]));
+    if from.is_empty() {
+        return Err("Can't replace an empty string.".to_string());
+    }
+    let replaced = as_str(&args[0]).replace(from, to);
+    Ok(alloc_str(replaced, ctx))
+}
+
+fn starts_with(args: &[StackValue], _ctx: &mut NatCtx) -> Result<StackValue, String> {
+    let found = as_str(&args[0]).starts_with(as_str(&args[1]));
+    Ok(StackValue::Bool(found))
+}
+
+fn ends_with(args: &[StackValue], _ctx: &mut NatCtx) -> Result<StackValue, String> {
+    let found = as_str(&args[0]).ends_with(as_str(&args[1]));
+    Ok(StackValue::Bool(found))
+}
+
+fn chars(args: &[StackValue], ctx: &mut NatCtx) -> Result<StackValue, String> {
+    let str = as_str(&args[0]);
+    let chars = str.char_indices().map(|(i, c)| &str[i..i + c.len_utf8()]);
+    Ok(alloc_strs(chars, ctx))
+}
+
+// surrounding whitespace is ignored
+fn parse_int(args: &[StackValue], _ctx: &mut NatCtx) -> Result<StackValue, String> {
+    match as_str(&args[0]).trim().parse() {
+        Ok(num) => Ok(StackValue::I64(num)),
+        Err(_) => Ok(StackValue::Null),
+    }
+}
+
+fn parse_double(args: &[StackValue], _ctx: &mut NatCtx) -> Result<StackValue, String> {
+    match as_str(&args[0]).trim().parse() {
+        Ok(num) => Ok(StackValue::F64(num)),
+        Err(_) => Ok(StackValue::Null),
+    }
+}
@@ -2,8 +2,7 @@ use std::collections::HashMap;
 
 use crate::{
     analysis_types::{NatFuncData, NatStructData},
-    heap::Heap,
-    object::Object,
+    object::{NatCtx, Object},
     value::{StackValue, ValueType},
 };
 
@@ -21,19 +20,23 @@ pub fn register<'a>(structs: &mut HashMap<&'a str, NatStructData<'a>>) {
     let data = NatStructData {
         fields,
         methods: vec![("product", product)],
+        type_params: vec![],
     };
     structs.insert(name, data);
 
     super::vec::register(structs);
+    super::map::register(structs);
+    super::string::register(structs);
+    super::result::register(structs);
 }
 
-fn vec2_product(args: &[StackValue], _heap: &mut Heap) -> StackValue {
+fn vec2_product(args: &[StackValue], _ctx: &mut NatCtx) -> Result<StackValue, String> {
     let StackValue::Obj(Object::Inst(inst)) = args[0] else {
         unreachable!()
     };
     let (x, y) = (inst.data.fields[0], inst.data.fields[1]);
     match (x, y) {
-        (StackValue::F64(val1), StackValue::F64(val2)) => StackValue::F64(val1 * val2),
+        (StackValue::F64(val1), StackValue::F64(val2)) => Ok(StackValue::F64(val1 * val2)),
         _ => unreachable!(),
     }
 }
@@ -2,24 +2,25 @@ use std::collections::HashMap;
 
 use crate::{
     analysis_types::{NatFuncData, NatStructData},
-    heap::Heap,
-    object::Object,
+    object::{NatCtx, Object},
     value::{StackValue, ValueType},
 };
 
 pub fn register<'a>(structs: &mut HashMap<&'a str, NatStructData<'a>>) {
     let name = "Vec";
-    let field_ty = ValueType::Arr(Box::new(ValueType::Any));
+    // the type of the elements
+    let el_ty = ValueType::TypeParam("T".to_string());
+    let field_ty = ValueType::Arr(Box::new(el_ty.clone()));
     let fields = vec![(field_ty, "elements")];
 
     let get = NatFuncData {
         parameters: vec![ValueType::U64],
         func: get,
-        return_ty: ValueType::Any,
+        return_ty: el_ty.clone(),
         use_self: true,
     };
     let push = NatFuncData {
-        parameters: vec![ValueType::Any],
+        parameters: vec![el_ty.clone()],
         func: push,
         return_ty: ValueType::Null,
         use_self: true,
@@ -27,7 +28,7 @@ pub fn register<'a>(structs: &mut HashMap<&'a str, NatStructData<'a>>) {
     let pop = NatFuncData {
         parameters: vec![],
         func: pop,
-        return_ty: ValueType::Any,
+        return_ty: el_ty,
         use_self: true,
     };
     let print = NatFuncData {
@@ -52,11 +53,12 @@ pub fn register<'a>(structs: &mut HashMap<&'a str, NatStructData<'a>>) {
             ("len", len),
             ("pop", pop),
         ],
+        type_params: vec!["T"],
     };
     structs.insert(name, data);
 }
 
-fn get(args: &[StackValue], _heap: &mut Heap) -> StackValue {
+fn get(args: &[StackValue], _ctx: &mut NatCtx) -> Result<StackValue, String> {
     let StackValue::Obj(Object::Inst(inst)) = args[0] else {
         unreachable!()
     };
@@ -66,17 +68,11 @@ fn get(args: &[StackValue], _heap: &mut Heap) -> StackValue {
         unreachable!()
     };
 
-    let index = match args[1] {
-        StackValue::F64(n) => n as usize,
-        StackValue::U64(n) => n as usize,
-        StackValue::I64(n) => n as usize,
-        _ => unreachable!(),
-    };
-
-    arr.data.elements[index as usize]
+    let index = args[1].to_index(arr.data.elements.len())?;
+    Ok(arr.data.elements[index])
 }
 
-fn len(args: &[StackValue], _heap: &mut Heap) -> StackValue {
+fn len(args: &[StackValue], _ctx: &mut NatCtx) -> Result<StackValue, String> {
     let StackValue::Obj(Object::Inst(inst)) = args[0] else {
         unreachable!()
     };
@@ -86,10 +82,10 @@ fn len(args: &[StackValue], _heap: &mut Heap) -> StackValue {
         unreachable!()
     };
 
-    StackValue::U64(arr.data.elements.len() as u64)
+    Ok(StackValue::U64(arr.data.elements.len() as u64))
 }
 
-fn push(args: &[StackValue], _heap: &mut Heap) -> StackValue {
+fn push(args: &[StackValue], _ctx: &mut NatCtx) -> Result<StackValue, String> {
     let StackValue::Obj(Object::Inst(inst)) = args[0] else {
         unreachable!()
     };
@@ -100,10 +96,10 @@ fn push(args: &[StackValue], _heap: &mut Heap) -> StackValue {
     };
 
     arr.data.elements.push(args[1]);
-    StackValue::Null
+    Ok(StackValue::Null)
 }
 
-fn pop(args: &[StackValue], _heap: &mut Heap) -> StackValue {
+fn pop(args: &[StackValue], _ctx: &mut NatCtx) -> Result<StackValue, String> {
     let StackValue::Obj(Object::Inst(inst)) = args[0] else {
         unreachable!()
     };
@@ -113,16 +109,13 @@ fn pop(args: &[StackValue], _heap: &mut Heap) -> StackValue {
         unreachable!()
     };
 
-    if let Some(el) = arr.data.elements.pop() {
-        el
-    } else {
-        panic!("You tried to pop an element from an empty vec.");
+    match arr.data.elements.pop() {
+        Some(el) => Ok(el),
+        None => Err("You tried to pop an element from an empty vec.".to_string()),
     }
 }
 
-fn print(args: &[StackValue], _heap: &mut Heap) -> StackValue {
-    use colored::Colorize;
-
+fn print(args: &[StackValue], ctx: &mut NatCtx) -> Result<StackValue, String> {
     let StackValue::Obj(Object::Inst(inst)) = args[0] else {
         unreachable!()
     };
@@ -132,19 +125,8 @@ fn print(args: &[StackValue], _heap: &mut Heap) -> StackValue {
         unreachable!()
     };
 
-    let els = &arr.data.elements;
-
-    print!("[");
-    if !els.is_empty() {
-        let string = format!("{}", els[0]).green();
-        print!("{string}");
-
-        for el in arr.data.elements.iter().skip(1) {
-            let string = format!(", {el}").green();
-            print!("{string}");
-        }
-    }
-    println!("]");
+    let els: Vec<String> = arr.data.elements.iter().map(|el| el.to_string()).collect();
+    writeln!(ctx.out, "[{}]", els.join(", ")).map_err(|err| err.to_string())?;
 
-    StackValue::Null
+    Ok(StackValue::Null)
 }
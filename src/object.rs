@@ -1,3 +1,6 @@
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::io::{BufRead, Write};
 use std::ops;
 use std::ptr::NonNull;
 
@@ -65,6 +68,10 @@ pub enum Object {
     Native(Gc<ObjNative>),
     Arr(Gc<ObjArr>),
     Inst(Gc<ObjInstance>),
+    Enum(Gc<ObjEnum>),
+    Closure(Gc<ObjClosure>),
+    Upvalue(Gc<ObjUpvalue>),
+    Map(Gc<ObjMap>),
 }
 impl Object {
     pub fn header(&self) -> &GcHeader {
@@ -74,6 +81,10 @@ impl Object {
             Object::Native(obj) => obj.header(),
             Object::Arr(obj) => obj.header(),
             Object::Inst(obj) => obj.header(),
+            Object::Enum(obj) => obj.header(),
+            Object::Closure(obj) => obj.header(),
+            Object::Upvalue(obj) => obj.header(),
+            Object::Map(obj) => obj.header(),
         }
     }
     pub fn header_mut(&mut self) -> &mut GcHeader {
@@ -83,6 +94,10 @@ impl Object {
             Object::Native(obj) => obj.header_mut(),
             Object::Arr(obj) => obj.header_mut(),
             Object::Inst(obj) => obj.header_mut(),
+            Object::Enum(obj) => obj.header_mut(),
+            Object::Closure(obj) => obj.header_mut(),
+            Object::Upvalue(obj) => obj.header_mut(),
+            Object::Map(obj) => obj.header_mut(),
         }
     }
     pub fn is_marked(&self) -> bool {
@@ -118,10 +133,21 @@ impl GcMemSize for ObjArr {
 pub struct ObjInstance {
     pub fields: Vec<StackValue>,
     pub methods: Vec<StackValue>,
+    // the methods of the implemented traits indexed by trait id, shared by all instances of
+    // the struct
+    pub vtables: Option<Gc<ObjArr>>,
 }
 impl ObjInstance {
-    pub fn new(fields: Vec<StackValue>, methods: Vec<StackValue>) -> Self {
-        Self { fields, methods }
+    pub fn new(
+        fields: Vec<StackValue>,
+        methods: Vec<StackValue>,
+        vtables: Option<Gc<ObjArr>>,
+    ) -> Self {
+        Self {
+            fields,
+            methods,
+            vtables,
+        }
     }
 }
 impl GcMemSize for ObjInstance {
@@ -131,16 +157,169 @@ impl GcMemSize for ObjInstance {
     }
 }
 
+/// A variant of an enum that holds values.
+#[derive(Debug, Clone)]
+pub struct ObjEnum {
+    pub tag: u8,
+    // the name of the variant, a string
+    pub name: Object,
+    pub values: Vec<StackValue>,
+}
+impl ObjEnum {
+    pub fn new(tag: u8, name: Object, values: Vec<StackValue>) -> Self {
+        Self { tag, name, values }
+    }
+}
+impl GcMemSize for ObjEnum {
+    fn size_of(&self) -> usize {
+        std::mem::size_of::<StackValue>() * self.values.capacity()
+    }
+}
+
+/// A function together with the variables it captured from the functions around it.
+#[derive(Debug, Clone)]
+pub struct ObjClosure {
+    pub func: Gc<ObjFunc>,
+    pub upvalues: Vec<Gc<ObjUpvalue>>,
+}
+impl ObjClosure {
+    pub fn new(func: Gc<ObjFunc>, upvalues: Vec<Gc<ObjUpvalue>>) -> Self {
+        Self { func, upvalues }
+    }
+}
+impl GcMemSize for ObjClosure {
+    fn size_of(&self) -> usize {
+        std::mem::size_of::<Gc<ObjUpvalue>>() * self.upvalues.capacity()
+    }
+}
+
+/// A captured variable. While the variable is still on the stack it's open and `location` is
+/// its index in the stack, once it goes out of scope the value is moved into `closed`.
+#[derive(Debug, Clone)]
+pub struct ObjUpvalue {
+    pub location: usize,
+    pub closed: Option<StackValue>,
+}
+impl ObjUpvalue {
+    pub fn new(location: usize) -> Self {
+        Self {
+            location,
+            closed: None,
+        }
+    }
+}
+impl GcMemSize for ObjUpvalue {
+    fn size_of(&self) -> usize {
+        0
+    }
+}
+
+/// A key of an 'ObjMap', it's hashed and compared like 'StackValue::equals', so strings are
+/// compared by their content. Arrays and instances are only equal to themselves.
+#[derive(Debug, Clone, Copy)]
+pub struct MapKey(pub StackValue);
+impl Hash for MapKey {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        match self.0 {
+            StackValue::Null => 0u8.hash(state),
+            StackValue::Bool(b) => b.hash(state),
+            // 0.0 and -0.0 are equal
+            StackValue::F64(0.0) => 0u64.hash(state),
+            StackValue::F64(num) => num.to_bits().hash(state),
+            // uints and ints with the same value are equal
+            StackValue::U64(num) => (num as i64).hash(state),
+            StackValue::I64(num) => num.hash(state),
+            StackValue::Obj(Object::Str(str)) => str.data.hash(state),
+            StackValue::Obj(Object::Enum(value)) => {
+                value.data.tag.hash(state);
+                for el in &value.data.values {
+                    MapKey(*el).hash(state);
+                }
+            }
+            StackValue::Obj(obj) => std::ptr::hash(obj.header(), state),
+        }
+    }
+}
+impl PartialEq for MapKey {
+    fn eq(&self, other: &Self) -> bool {
+        match (self.0, other.0) {
+            // unlike with '==' a NaN key equals itself, so its entry can be found again
+            (StackValue::F64(lhs), StackValue::F64(rhs)) => {
+                lhs == rhs || lhs.to_bits() == rhs.to_bits()
+            }
+            (StackValue::Obj(Object::Enum(lhs)), StackValue::Obj(Object::Enum(rhs))) => {
+                lhs.data.tag == rhs.data.tag
+                    && lhs.data.values.len() == rhs.data.values.len()
+                    && (lhs.data.values.iter())
+                        .zip(&rhs.data.values)
+                        .all(|(lhs, rhs)| MapKey(*lhs) == MapKey(*rhs))
+            }
+            (lhs, rhs) => lhs.equals(rhs),
+        }
+    }
+}
+impl Eq for MapKey {}
+
+/// The entries of a map, in the order they were inserted.
+#[derive(Debug, Clone, Default)]
+pub struct ObjMap {
+    pub entries: Vec<(StackValue, StackValue)>,
+    // the index of every key in 'entries'
+    indices: HashMap<MapKey, usize>,
+}
+impl ObjMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, key: StackValue) -> Option<StackValue> {
+        let index = self.indices.get(&MapKey(key))?;
+        Some(self.entries[*index].1)
+    }
+
+    pub fn contains(&self, key: StackValue) -> bool {
+        self.indices.contains_key(&MapKey(key))
+    }
+
+    pub fn insert(&mut self, key: StackValue, value: StackValue) {
+        match self.indices.get(&MapKey(key)) {
+            Some(index) => self.entries[*index].1 = value,
+            None => {
+                self.indices.insert(MapKey(key), self.entries.len());
+                self.entries.push((key, value));
+            }
+        }
+    }
+
+    /// Removes the entry of `key` and returns its value, the last entry takes its place.
+    pub fn remove(&mut self, key: StackValue) -> Option<StackValue> {
+        let index = self.indices.remove(&MapKey(key))?;
+        let (_, value) = self.entries.swap_remove(index);
+        if let Some((moved, _)) = self.entries.get(index) {
+            self.indices.insert(MapKey(*moved), index);
+        }
+        Some(value)
+    }
+}
+impl GcMemSize for ObjMap {
+    fn size_of(&self) -> usize {
+        std::mem::size_of::<(StackValue, StackValue)>() * self.entries.capacity()
+            + std::mem::size_of::<(MapKey, usize)>() * self.indices.capacity()
+    }
+}
+
 #[derive(Debug)]
 pub struct ObjFunc {
     pub chunk: Chunk,
     name: String,
+    pub upvalue_count: u8,
 }
 impl ObjFunc {
     pub fn new(name: String) -> Self {
         Self {
             chunk: Chunk::new(),
             name,
+            upvalue_count: 0,
         }
     }
     pub fn get_name(&self) -> &String {
@@ -153,7 +332,33 @@ impl GcMemSize for ObjFunc {
     }
 }
 
-pub type NativeFunc = fn(&[StackValue], &mut Heap) -> StackValue;
+/// Everything a native function can access besides its arguments.
+pub struct NatCtx<'a> {
+    pub heap: &'a mut Heap,
+    pub out: &'a mut dyn Write,
+    pub input: &'a mut dyn BufRead,
+    // the command-line arguments of the program
+    pub args: &'a [String],
+    // stops the program after the native function returns
+    pub exit_code: Option<i32>,
+    // the roots of the garbage collector
+    pub stack: &'a [StackValue],
+    pub open_upvalues: &'a [Gc<ObjUpvalue>],
+}
+impl NatCtx<'_> {
+    /// Allocates a garbage collected object, it stays alive at least until the native function
+    /// returns.
+    pub fn alloc<T: GcMemSize, F>(&mut self, data: T, map: F) -> Object
+    where
+        F: Fn(Gc<T>) -> Object,
+    {
+        let (obj, _) = self.heap.alloc(data, map, self.stack, self.open_upvalues);
+        self.heap.add_temp_root(StackValue::Obj(obj));
+        obj
+    }
+}
+
+pub type NativeFunc = fn(&[StackValue], &mut NatCtx) -> Result<StackValue, String>;
 
 #[derive(Debug, Clone)]
 pub struct ObjNative {
@@ -57,6 +57,33 @@ pub enum OpCode {
     GreaterEqual,
     Less,
     LessEqual,
+
+    // enums that hold values
+    AllocEnum,
+    GetEnumTag,
+    GetEnumValue,
+
+    // globals live in the first local slots of 'main'
+    GetGlobal,
+    SetGlobal,
+
+    Closure,
+    GetUpvalue,
+    SetUpvalue,
+    CloseUpvalue,
+
+    CastToStr,
+
+    // unwraps a Result or an Option and jumps over the return of its error
+    TryUnwrap,
+
+    // the handlers of 'try' blocks
+    PushHandler,
+    PopHandler,
+    Throw,
+
+    // looks a method of a trait up in the vtables of an instance
+    PushTraitMethod,
 }
 impl std::convert::From<u8> for OpCode {
     fn from(value: u8) -> Self {
@@ -100,6 +127,21 @@ impl std::convert::From<u8> for OpCode {
             36 => OpCode::GreaterEqual,
             37 => OpCode::Less,
             38 => OpCode::LessEqual,
+            39 => OpCode::AllocEnum,
+            40 => OpCode::GetEnumTag,
+            41 => OpCode::GetEnumValue,
+            42 => OpCode::GetGlobal,
+            43 => OpCode::SetGlobal,
+            44 => OpCode::Closure,
+            45 => OpCode::GetUpvalue,
+            46 => OpCode::SetUpvalue,
+            47 => OpCode::CloseUpvalue,
+            48 => OpCode::CastToStr,
+            49 => OpCode::TryUnwrap,
+            50 => OpCode::PushHandler,
+            51 => OpCode::PopHandler,
+            52 => OpCode::Throw,
+            53 => OpCode::PushTraitMethod,
             _ => panic!("Not a valid opcode."),
         }
     }
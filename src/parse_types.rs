@@ -40,8 +40,10 @@ pub enum FnType {
     Unary,
     Binary,
     Cast,
+    Try,
     Number,
     String,
+    FString,
     Literal,
     Var,
     Call,
@@ -49,6 +51,7 @@ pub enum FnType {
     Dot,
     DoubleColon,
     This,
+    Lambda,
 }
 
 #[derive(Clone, Copy)]
@@ -1,28 +1,32 @@
 use crate::{
-    error::{print_error, ParseErr},
+    error::ParseErr,
     expression::{Expr, ExprType},
     parse_types::{BinaryOp, FnType, Precedence},
-    statement::{Stmt, StmtType},
+    statement::{MatchArm, Pattern, Stmt, StmtType, TraitMethod},
     token::{Literal, Token, TokenType},
     value::ValueType,
 };
 
-use colored::Colorize;
+// the types and names of the parameters of a function
+type Parameters<'a> = Vec<(ValueType, &'a str)>;
 
 const EXPECTED_SEMICOLON_MSG: &str = "Expected ';' at end of statement.";
 
 pub struct Parser<'token> {
     tokens: Vec<Token<'token>>,
     current_token: usize,
+    // the type parameters of the generic declarations that are being parsed
+    type_params: Vec<&'token str>,
 }
 impl<'a> Parser<'a> {
-    pub fn compile(tokens: Vec<Token<'a>>) -> Option<Vec<Stmt<'a>>> {
+    pub fn compile(tokens: Vec<Token<'a>>) -> Result<Vec<Stmt<'a>>, Vec<ParseErr>> {
         let mut parser = Parser {
             tokens,
             current_token: 0,
+            type_params: vec![],
         };
 
-        let mut had_error = false;
+        let mut errors = vec![];
         let mut statements = Vec::new();
         while !parser.check(TokenType::Eof) {
             match parser.declaration() {
@@ -30,22 +34,17 @@ impl<'a> Parser<'a> {
                     statements.push(result);
                 }
                 Err(err) => {
-                    print_error(err.line, &err.msg);
-                    had_error = true;
+                    errors.push(err);
+                    parser.type_params.clear();
                     parser.synchronize();
                 }
             }
         }
-        parser.current_token += 1;
 
-        if had_error {
-            return None;
-        }
-
-        if parser.current_token != parser.tokens.len() {
-            println!("{}", "Not all tokens were parsed.".red());
+        if !errors.is_empty() {
+            return Err(errors);
         }
-        Some(statements)
+        Ok(statements)
     }
 
     fn parse_precedence(&mut self, precedence: Precedence) -> Result<Expr<'a>, ParseErr> {
@@ -80,26 +79,59 @@ impl<'a> Parser<'a> {
 
     fn declaration(&mut self) -> Result<Stmt<'a>, ParseErr> {
         if let Some(var_type) = self.peek().as_value_type() {
+            let mut var_type = self.as_type_param(var_type);
+            let start = self.current_token;
             self.advance();
-            if self.peek().ty != TokenType::Identifier && self.peek().ty != TokenType::LeftBracket {
-                self.regress();
-                return self.statement();
+            // 'Vec<int> name', as opposed to a comparison like 'a < b'
+            if self.check(TokenType::Less) {
+                match self.try_type_args(var_type.clone()) {
+                    Some(ty) => var_type = ty,
+                    None => {
+                        self.current_token = start;
+                        return self.statement();
+                    }
+                }
             }
 
-            self.advance();
-            if self.peek().ty == TokenType::Num {
-                self.regress();
-                self.regress();
+            // 'int? name' and 'int[] name' are declarations, 'x?' and 'x[0]' are expressions
+            let after_type = self.current_token;
+            loop {
+                if self.check(TokenType::LeftBracket)
+                    && self.peek_next().ty == TokenType::RightBracket
+                {
+                    self.advance();
+                    self.advance();
+                } else if !self.matches(TokenType::Question) {
+                    break;
+                }
+            }
+            let is_decl = self.check(TokenType::Identifier);
+            self.current_token = if is_decl { after_type } else { start };
+            if !is_decl {
                 return self.statement();
             }
-            self.regress();
             self.var_decl(var_type)
+        } else if self.matches(TokenType::Const) {
+            let ty = self.parse_type("Expected type after 'const'.")?;
+            self.const_decl(ty)
+        } else if self.check(TokenType::Fn) && self.peek_next().ty == TokenType::LeftParen {
+            // 'fn(int): int name' declares a variable that holds a function
+            let ty = self.parse_type("Expected function type.")?;
+            self.var_decl(ty)
         } else if self.matches(TokenType::Fn) {
             self.func_decl()
         } else if self.matches(TokenType::Struct) {
             self.struct_decl()
         } else if self.matches(TokenType::Enum) {
             self.enum_decl()
+        } else if self.matches(TokenType::Trait) {
+            self.trait_decl()
+        } else if self.matches(TokenType::Impl) {
+            self.impl_decl()
+        } else if self.matches(TokenType::Import) {
+            // imports at the top of a file are resolved before parsing
+            let msg = "'import' can only be used at the top level of a file.";
+            Err(ParseErr::new(self.previous().line, msg))
         } else {
             self.statement()
         }
@@ -120,7 +152,19 @@ impl<'a> Parser<'a> {
             self.consume(TokenType::Identifier, "Expected variant name.")?;
             let variant_name = self.previous().lexeme;
 
-            variants.push(variant_name);
+            // the types of the values a variant holds, like 'Rect(double, double)'
+            let mut values = vec![];
+            if self.matches(TokenType::LeftParen) {
+                while !self.check(TokenType::RightParen) {
+                    values.push(self.parse_type("Expected type of variant value.")?);
+                    if !self.matches(TokenType::Comma) {
+                        break;
+                    }
+                }
+                self.consume(TokenType::RightParen, "Expected ')' after variant values.")?;
+            }
+
+            variants.push((variant_name, values));
             self.consume(TokenType::Comma, "Expected ',' after enum variant.")?;
         }
         self.consume(TokenType::RightBrace, "Expected '}' after struct body.")?;
@@ -137,21 +181,20 @@ impl<'a> Parser<'a> {
         let name = self.previous().lexeme;
         let line = self.previous().line;
 
+        let type_params = self.type_param_list()?;
+        let outer_type_params = self.type_params.len();
+        self.type_params.extend(&type_params);
+
         self.consume(TokenType::LeftBrace, "Expected '{' after struct name.")?;
 
         let mut fields = Vec::new();
-        while !self.check(TokenType::RightBrace) && !self.check(TokenType::Fn) {
-            let mut field_ty = match self.advance().as_value_type() {
-                Some(ty) => ty,
-                None => {
-                    let msg = "Expected type for field declaration in struct body.";
-                    return Err(ParseErr::new(line, msg));
-                }
-            };
-            while self.matches(TokenType::LeftBracket) {
-                self.consume(TokenType::RightBracket, "Expected ']' after left bracket.")?;
-                field_ty = ValueType::Arr(Box::new(field_ty));
-            }
+        // 'fn(int) callback;' is a field, 'fn name()' starts the methods
+        let is_method = |parser: &Self| {
+            parser.check(TokenType::Fn) && parser.peek_next().ty == TokenType::Identifier
+        };
+        while !self.check(TokenType::RightBrace) && !is_method(self) {
+            let field_ty =
+                self.parse_type("Expected type for field declaration in struct body.")?;
 
             self.consume(TokenType::Identifier, "Expected variable name after type.")?;
             let field_name = self.previous().lexeme;
@@ -166,11 +209,13 @@ impl<'a> Parser<'a> {
         }
 
         self.consume(TokenType::RightBrace, "Expected '}' after struct body.")?;
+        self.type_params.truncate(outer_type_params);
 
         let ty = StmtType::Struct {
             name,
             fields,
             methods,
+            type_params,
         };
         Ok(Stmt::new(ty, line))
     }
@@ -183,6 +228,28 @@ impl<'a> Parser<'a> {
         let name = self.previous().lexeme;
         let line = self.previous().line;
 
+        let type_params = self.type_param_list()?;
+        let outer_type_params = self.type_params.len();
+        self.type_params.extend(&type_params);
+
+        let (parameters, use_self) = self.func_parameters()?;
+        let return_ty = self.return_type()?;
+        let body = self.func_body()?;
+        self.type_params.truncate(outer_type_params);
+
+        let fn_ty = StmtType::Func {
+            name,
+            parameters,
+            body,
+            return_ty,
+            use_self,
+            type_params,
+        };
+        let func = Stmt::new(fn_ty, line);
+        Ok(func)
+    }
+    // '(self, int x)', methods take 'self' as their first parameter
+    fn func_parameters(&mut self) -> Result<(Parameters<'a>, bool), ParseErr> {
         self.consume(TokenType::LeftParen, "Expected '(' after function name.")?;
 
         let mut parameters = Vec::new();
@@ -202,20 +269,104 @@ impl<'a> Parser<'a> {
         }
 
         self.consume(TokenType::RightParen, "Expected ')' after function name.")?;
+        Ok((parameters, use_self))
+    }
+
+    // 'trait Shape { fn area(self): double; }'
+    fn trait_decl(&mut self) -> Result<Stmt<'a>, ParseErr> {
+        self.consume(
+            TokenType::Identifier,
+            "Expected trait name after 'trait' keyword.",
+        )?;
+        let name = self.previous().lexeme;
+        let line = self.previous().line;
 
-        let mut return_ty = ValueType::Null;
+        self.consume(TokenType::LeftBrace, "Expected '{' after trait name.")?;
+        let mut methods = vec![];
+        while self.matches(TokenType::Fn) {
+            self.consume(TokenType::Identifier, "Expected method name after 'fn'.")?;
+            let method_name = self.previous().lexeme;
+            let method_line = self.previous().line;
+
+            let (parameters, use_self) = self.func_parameters()?;
+            let return_ty = self.return_type()?;
+            self.consume(TokenType::Semicolon, "Expected ';' after trait method.")?;
+
+            methods.push(TraitMethod {
+                name: method_name,
+                parameters,
+                return_ty,
+                use_self,
+                line: method_line,
+            });
+        }
+        self.consume(TokenType::RightBrace, "Expected '}' after trait body.")?;
+
+        let ty = StmtType::Trait { name, methods };
+        Ok(Stmt::new(ty, line))
+    }
+
+    // 'impl Shape for Circle { fn area(self): double { ... } }'
+    fn impl_decl(&mut self) -> Result<Stmt<'a>, ParseErr> {
+        self.consume(TokenType::Identifier, "Expected trait name after 'impl'.")?;
+        let trait_name = self.previous().lexeme;
+        let line = self.previous().line;
+
+        self.consume(TokenType::For, "Expected 'for' after trait name.")?;
+        self.consume(TokenType::Identifier, "Expected struct name after 'for'.")?;
+        let struct_name = self.previous().lexeme;
+
+        self.consume(TokenType::LeftBrace, "Expected '{' after struct name.")?;
+        let mut methods = vec![];
+        while self.matches(TokenType::Fn) {
+            methods.push(self.func_decl()?);
+        }
+        self.consume(TokenType::RightBrace, "Expected '}' after impl body.")?;
+
+        let ty = StmtType::Impl {
+            trait_name,
+            struct_name,
+            methods,
+        };
+        Ok(Stmt::new(ty, line))
+    }
+
+    // the '<A, B>' after the name of a generic function or struct
+    fn type_param_list(&mut self) -> Result<Vec<&'a str>, ParseErr> {
+        let mut type_params = vec![];
+        if !self.matches(TokenType::Less) {
+            return Ok(type_params);
+        }
+
+        loop {
+            self.consume(TokenType::Identifier, "Expected name of type parameter.")?;
+            type_params.push(self.previous().lexeme);
+            if !self.matches(TokenType::Comma) {
+                break;
+            }
+        }
+        self.consume(TokenType::Greater, "Expected '>' after type parameters.")?;
+        Ok(type_params)
+    }
+
+    fn as_type_param(&self, ty: ValueType) -> ValueType {
+        match ty {
+            ValueType::UnknownType(name) if self.type_params.contains(&name.as_str()) => {
+                ValueType::TypeParam(name)
+            }
+            ty => ty,
+        }
+    }
+
+    fn return_type(&mut self) -> Result<ValueType, ParseErr> {
         if self.matches(TokenType::Colon) {
-            return_ty = match self.advance().as_value_type() {
-                Some(return_ty) => return_ty,
-                _ => {
-                    return Err(ParseErr::new(
-                        self.previous().line,
-                        "Expected return type after finding ':'.",
-                    ));
-                }
-            };
+            self.parse_type("Expected return type after finding ':'.")
+        } else {
+            Ok(ValueType::Null)
         }
+    }
 
+    fn func_body(&mut self) -> Result<Vec<Stmt<'a>>, ParseErr> {
         self.consume(
             TokenType::LeftBrace,
             "Expected '{' at begin of function body.",
@@ -232,33 +383,36 @@ impl<'a> Parser<'a> {
                 "Expected '}' at end of function body.",
             )?;
         }
+        Ok(body)
+    }
 
-        let fn_ty = StmtType::Func {
-            name,
+    // 'fn(int x): int { return x * 2; }'
+    fn lambda(&mut self) -> Result<Expr<'a>, ParseErr> {
+        let line = self.previous().line;
+        self.consume(TokenType::LeftParen, "Expected '(' after 'fn'.")?;
+
+        let mut parameters = Vec::new();
+        if !self.check(TokenType::RightParen) {
+            parameters.push(self.parse_parameter()?);
+            while self.matches(TokenType::Comma) {
+                parameters.push(self.parse_parameter()?);
+            }
+        }
+        self.consume(TokenType::RightParen, "Expected ')' after parameters.")?;
+
+        let return_ty = self.return_type()?;
+        let body = self.func_body()?;
+
+        let ty = ExprType::Lambda {
             parameters,
-            body,
             return_ty,
-            use_self,
+            body,
         };
-        let func = Stmt::new(fn_ty, line);
-        Ok(func)
+        Ok(Expr::new(ty, line))
     }
+
     fn parse_parameter(&mut self) -> Result<(ValueType, &'a str), ParseErr> {
-        let var_ty = match self.advance().as_value_type() {
-            Some(mut var_type) => {
-                while self.matches(TokenType::LeftBracket) {
-                    self.consume(TokenType::RightBracket, "Expected ']' after left bracket.")?;
-                    var_type = ValueType::Arr(Box::new(var_type));
-                }
-                var_type
-            }
-            _ => {
-                return Err(ParseErr::new(
-                    self.previous().line,
-                    "Expected type for parameter.",
-                ));
-            }
-        };
+        let var_ty = self.parse_type("Expected type for parameter.")?;
 
         self.consume(TokenType::Identifier, "Expected parameter name.")?;
         let name = self.previous().lexeme;
@@ -266,11 +420,82 @@ impl<'a> Parser<'a> {
         Ok((var_ty, name))
     }
 
-    fn var_decl(&mut self, mut ty: ValueType) -> Result<Stmt<'a>, ParseErr> {
-        while self.matches(TokenType::LeftBracket) {
-            self.consume(TokenType::RightBracket, "Expected ']' after left bracket.")?;
-            ty = ValueType::Arr(Box::new(ty));
+    fn parse_type(&mut self, msg: &str) -> Result<ValueType, ParseErr> {
+        let ty = if self.matches(TokenType::Fn) {
+            self.func_type()?
+        } else {
+            match self.advance().as_value_type() {
+                Some(ValueType::UnknownType(name)) if self.matches(TokenType::Less) => {
+                    self.type_args(name)?
+                }
+                Some(ty) => self.as_type_param(ty),
+                None => return Err(ParseErr::new(self.previous().line, msg)),
+            }
+        };
+        self.type_suffix(ty)
+    }
+
+    // the '[]' and '?' after a type, 'int?[]' is an array of nullable ints
+    fn type_suffix(&mut self, mut ty: ValueType) -> Result<ValueType, ParseErr> {
+        loop {
+            if self.matches(TokenType::LeftBracket) {
+                self.consume(TokenType::RightBracket, "Expected ']' after left bracket.")?;
+                ty = ValueType::Arr(Box::new(ty));
+            } else if self.matches(TokenType::Question) {
+                ty = ValueType::nullable(ty);
+            } else {
+                return Ok(ty);
+            }
         }
+    }
+
+    // the type arguments after the '<' of 'Vec<int>'
+    fn type_args(&mut self, name: String) -> Result<ValueType, ParseErr> {
+        let mut args = vec![self.parse_type("Expected type argument.")?];
+        while self.matches(TokenType::Comma) {
+            args.push(self.parse_type("Expected type argument.")?);
+        }
+        self.consume(TokenType::Greater, "Expected '>' after type arguments.")?;
+        Ok(ValueType::generic(name, args))
+    }
+
+    // parses the type arguments of 'ty' if there are any, otherwise nothing is consumed
+    fn try_type_args(&mut self, ty: ValueType) -> Option<ValueType> {
+        let ValueType::UnknownType(name) = ty else {
+            return None;
+        };
+        let start = self.current_token;
+        self.advance();
+        match self.type_args(name) {
+            Ok(ty) => Some(ty),
+            Err(_) => {
+                self.current_token = start;
+                None
+            }
+        }
+    }
+
+    // 'fn(int, str): bool', the return type can be left out
+    fn func_type(&mut self) -> Result<ValueType, ParseErr> {
+        self.consume(TokenType::LeftParen, "Expected '(' after 'fn'.")?;
+        let mut parameters = vec![];
+        if !self.check(TokenType::RightParen) {
+            parameters.push(self.parse_type("Expected parameter type.")?);
+            while self.matches(TokenType::Comma) {
+                parameters.push(self.parse_type("Expected parameter type.")?);
+            }
+        }
+        self.consume(TokenType::RightParen, "Expected ')' after parameter types.")?;
+
+        let return_ty = Box::new(self.return_type()?);
+        Ok(ValueType::Func {
+            parameters,
+            return_ty,
+        })
+    }
+
+    fn var_decl(&mut self, ty: ValueType) -> Result<Stmt<'a>, ParseErr> {
+        let ty = self.type_suffix(ty)?;
 
         self.consume(TokenType::Identifier, "Expected variable name after type.")?;
         let name = self.previous().lexeme;
@@ -279,17 +504,40 @@ impl<'a> Parser<'a> {
         let value = if self.matches(TokenType::Equal) {
             let value = self.expression()?;
             self.consume(TokenType::Semicolon, EXPECTED_SEMICOLON_MSG)?;
-            value
+            Some(value)
         } else {
             self.consume(TokenType::Semicolon, EXPECTED_SEMICOLON_MSG)?;
-            Expr::new(ExprType::Lit(Literal::Null), line)
+            None
         };
 
-        let kind = StmtType::Var { name, value, ty };
+        let kind = StmtType::Var {
+            name,
+            value,
+            ty,
+            is_const: false,
+        };
         let var = Stmt::new(kind, line);
         Ok(var)
     }
 
+    fn const_decl(&mut self, ty: ValueType) -> Result<Stmt<'a>, ParseErr> {
+        self.consume(TokenType::Identifier, "Expected constant name after type.")?;
+        let name = self.previous().lexeme;
+        let line = self.previous().line;
+
+        self.consume(TokenType::Equal, "Expected '=' after constant name.")?;
+        let value = self.expression()?;
+        self.consume(TokenType::Semicolon, EXPECTED_SEMICOLON_MSG)?;
+
+        let kind = StmtType::Var {
+            name,
+            value: Some(value),
+            ty,
+            is_const: true,
+        };
+        Ok(Stmt::new(kind, line))
+    }
+
     fn statement(&mut self) -> Result<Stmt<'a>, ParseErr> {
         if self.matches(TokenType::Print) {
             self.print_statement()
@@ -301,12 +549,18 @@ impl<'a> Parser<'a> {
             self.while_stmt()
         } else if self.matches(TokenType::For) {
             self.for_stmt()
+        } else if self.matches(TokenType::Match) {
+            self.match_stmt()
         } else if self.matches(TokenType::Break) {
             self.break_stmt()
         } else if self.matches(TokenType::Continue) {
             self.continue_stmt()
         } else if self.matches(TokenType::Return) {
             self.return_stmt()
+        } else if self.matches(TokenType::Try) {
+            self.try_stmt()
+        } else if self.matches(TokenType::Throw) {
+            self.throw_stmt()
         } else {
             self.expr_stmt()
         }
@@ -413,7 +667,12 @@ impl<'a> Parser<'a> {
         // declare var
         let value = self.expression()?;
         let ty = ValueType::I64;
-        let kind = StmtType::Var { name, value, ty };
+        let kind = StmtType::Var {
+            name,
+            value: Some(value),
+            ty,
+            is_const: false,
+        };
         let var = Box::new(Stmt::new(kind, line));
 
         // condition
@@ -446,6 +705,90 @@ impl<'a> Parser<'a> {
         Ok(stmt)
     }
 
+    fn match_stmt(&mut self) -> Result<Stmt<'a>, ParseErr> {
+        let line = self.previous().line;
+        let value = self.expression()?;
+        self.consume(TokenType::LeftBrace, "Expected '{' after match value.")?;
+
+        let mut arms = vec![];
+        while !self.check(TokenType::RightBrace) && !self.check(TokenType::Eof) {
+            let line = self.peek().line;
+
+            let mut patterns = vec![self.pattern()?];
+            while self.matches(TokenType::Pipe) {
+                patterns.push(self.pattern()?);
+            }
+            self.consume(TokenType::FatArrow, "Expected '=>' after match pattern.")?;
+
+            let body = self.match_arm_body()?;
+            arms.push(MatchArm {
+                patterns,
+                body,
+                line,
+            });
+        }
+        self.consume(TokenType::RightBrace, "Expected '}' at end of match.")?;
+
+        let ty = StmtType::Match { value, arms };
+        Ok(Stmt::new(ty, line))
+    }
+
+    // either a block or a single statement that ends with ',' instead of ';'
+    fn match_arm_body(&mut self) -> Result<Stmt<'a>, ParseErr> {
+        if self.matches(TokenType::LeftBrace) {
+            let block = self.block()?;
+            self.matches(TokenType::Comma);
+            return Ok(block);
+        }
+
+        let line = self.peek().line;
+        let ends_arm = |parser: &Self| {
+            parser.check(TokenType::Comma)
+                || parser.check(TokenType::Semicolon)
+                || parser.check(TokenType::RightBrace)
+        };
+
+        let ty = if self.matches(TokenType::Return) {
+            let value = if ends_arm(self) {
+                Expr::new(ExprType::Lit(Literal::Null), line)
+            } else {
+                self.expression()?
+            };
+            StmtType::Return(value)
+        } else if self.matches(TokenType::Break) {
+            StmtType::Break
+        } else if self.matches(TokenType::Continue) {
+            StmtType::Continue
+        } else if self.matches(TokenType::Print) {
+            StmtType::Println(self.expression()?)
+        } else {
+            StmtType::Expr(self.expression()?)
+        };
+
+        if !ends_arm(self) {
+            return Err(ParseErr::new(line, "Expected ',' after match arm."));
+        }
+        if !self.matches(TokenType::Comma) {
+            self.matches(TokenType::Semicolon);
+        }
+        Ok(Stmt::new(ty, line))
+    }
+
+    fn pattern(&mut self) -> Result<Pattern<'a>, ParseErr> {
+        if self.check(TokenType::Identifier) && self.peek().lexeme == "_" {
+            self.advance();
+            return Ok(Pattern::Wildcard);
+        }
+
+        // the analyser checks that patterns are constant
+        let start = self.parse_precedence(Precedence::Unary)?;
+        if self.matches(TokenType::To) {
+            let end = self.parse_precedence(Precedence::Unary)?;
+            return Ok(Pattern::Range { start, end });
+        }
+        Ok(Pattern::Value(start))
+    }
+
     fn while_stmt(&mut self) -> Result<Stmt<'a>, ParseErr> {
         let condition = self.expression()?;
         let body = Box::new(self.statement()?);
@@ -455,6 +798,46 @@ impl<'a> Parser<'a> {
         Ok(stmt)
     }
 
+    // 'try { ... } catch (e) { ... }'
+    fn try_stmt(&mut self) -> Result<Stmt<'a>, ParseErr> {
+        let line = self.previous().line;
+
+        self.consume(TokenType::LeftBrace, "Expected '{' after 'try'.")?;
+        let body = Box::new(self.block()?);
+
+        self.consume(
+            TokenType::Catch,
+            "Expected 'catch' after the body of 'try'.",
+        )?;
+        self.consume(TokenType::LeftParen, "Expected '(' after 'catch'.")?;
+        self.consume(
+            TokenType::Identifier,
+            "Expected a variable name in 'catch'.",
+        )?;
+        let error = self.previous().lexeme;
+        self.consume(
+            TokenType::RightParen,
+            "Expected ')' after the variable of 'catch'.",
+        )?;
+
+        self.consume(TokenType::LeftBrace, "Expected '{' after 'catch (...)'.")?;
+        let catch_body = Box::new(self.block()?);
+
+        let ty = StmtType::Try {
+            body,
+            error,
+            catch_body,
+        };
+        Ok(Stmt::new(ty, line))
+    }
+
+    fn throw_stmt(&mut self) -> Result<Stmt<'a>, ParseErr> {
+        let line = self.previous().line;
+        let value = self.expression()?;
+        self.consume(TokenType::Semicolon, EXPECTED_SEMICOLON_MSG)?;
+        Ok(Stmt::new(StmtType::Throw(value), line))
+    }
+
     fn if_stmt(&mut self) -> Result<Stmt<'a>, ParseErr> {
         let line = self.previous().line;
 
@@ -503,12 +886,16 @@ impl<'a> Parser<'a> {
             match self.peek().ty {
                 TokenType::Struct
                 | TokenType::Fn
+                | TokenType::Const
                 | TokenType::F64
                 | TokenType::Bool
                 | TokenType::Str
                 | TokenType::For
                 | TokenType::If
                 | TokenType::While
+                | TokenType::Match
+                | TokenType::Try
+                | TokenType::Throw
                 | TokenType::Print
                 | TokenType::Return => {
                     // dbg!(self.peek().kind);
@@ -618,6 +1005,44 @@ impl<'a> Parser<'a> {
         Ok(Expr::new(kind, self.previous().line))
     }
 
+    // 'f"sum: {a + b}!"' becomes '"sum: " + (a + b) as str + "!"'
+    fn fstring(&mut self) -> Result<Expr<'a>, ParseErr> {
+        let line = self.previous().line;
+        let mut expr = self.string()?;
+        while self.previous().ty == TokenType::FStrPart {
+            let value = self.expression()?;
+            let value = ExprType::Cast {
+                value: Box::new(value),
+                target: ValueType::Str,
+            };
+            expr = Expr::new(
+                ExprType::Binary {
+                    left: Box::new(expr),
+                    op: BinaryOp::Add,
+                    right: Box::new(Expr::new(value, line)),
+                },
+                line,
+            );
+
+            if !self.matches(TokenType::FStrPart) && !self.matches(TokenType::FStrEnd) {
+                let msg = "Expected '}' after expression in f-string.";
+                return Err(ParseErr::new(self.previous().line, msg));
+            }
+            let part = self.string()?;
+            if !matches!(part.expr, ExprType::Lit(Literal::Str(""))) {
+                expr = Expr::new(
+                    ExprType::Binary {
+                        left: Box::new(expr),
+                        op: BinaryOp::Add,
+                        right: Box::new(part),
+                    },
+                    line,
+                );
+            }
+        }
+        Ok(expr)
+    }
+
     fn grouping(&mut self) -> Result<Expr<'a>, ParseErr> {
         let expr = self.expression()?;
         self.consume(TokenType::RightParen, "Expected ')' after expression.")?;
@@ -631,9 +1056,11 @@ impl<'a> Parser<'a> {
             FnType::Unary => self.unary(),
             FnType::Number => self.number(),
             FnType::String => self.string(),
+            FnType::FString => self.fstring(),
             FnType::Literal => self.literal(),
             FnType::Var => self.var(can_assign),
             FnType::This => self.this(),
+            FnType::Lambda => self.lambda(),
             _ => unreachable!(),
         }
     }
@@ -708,13 +1135,17 @@ impl<'a> Parser<'a> {
 
             Ok(Expr::new(ty, line))
         } else {
-            Err(ParseErr {
-                line,
-                msg: "Expected type after 'as' keyword.".to_string(),
-            })
+            Err(ParseErr::new(line, "Expected type after 'as' keyword."))
         }
     }
 
+    // 'value?' returns the error of a Result or an Option from the function
+    fn try_unwrap(&mut self, value: Expr<'a>) -> Expr<'a> {
+        let line = self.previous().line;
+        let value = Box::new(value);
+        Expr::new(ExprType::Try(value), line)
+    }
+
     fn call(&mut self, name: Expr<'a>) -> Result<Expr<'a>, ParseErr> {
         let mut args = Vec::new();
         while !self.check(TokenType::RightParen) {
@@ -747,7 +1178,10 @@ impl<'a> Parser<'a> {
                 args,
                 is_static: true,
             },
-            _ => unreachable!(),
+            callee => ExprType::CallValue {
+                callee: Box::new(Expr::new(callee, name.line)),
+                args,
+            },
         };
 
         let expr = Expr::new(ty, self.previous().line);
@@ -767,6 +1201,7 @@ impl<'a> Parser<'a> {
             FnType::Dot => self.dot(left, can_assign),
             FnType::DoubleColon => self.double_colon(left),
             FnType::Cast => self.cast(left),
+            FnType::Try => Ok(self.try_unwrap(left)),
             _ => unreachable!(),
         }
     }
@@ -776,10 +1211,7 @@ impl<'a> Parser<'a> {
             self.advance();
             Ok(())
         } else {
-            Err(ParseErr {
-                line: self.previous().line,
-                msg: msg.to_string(),
-            })
+            Err(ParseErr::new(self.previous().line, msg))
         }
     }
 
@@ -803,14 +1235,17 @@ impl<'a> Parser<'a> {
         self.previous()
     }
 
-    fn regress(&mut self) {
-        self.current_token -= 1;
-    }
-
     fn peek(&self) -> Token<'a> {
         self.tokens[self.current_token]
     }
 
+    fn peek_next(&self) -> Token<'a> {
+        match self.tokens.get(self.current_token + 1) {
+            Some(token) => *token,
+            None => self.peek(),
+        }
+    }
+
     fn previous(&self) -> Token<'a> {
         self.tokens[self.current_token - 1]
     }
@@ -0,0 +1,133 @@
+use std::io::Write;
+
+use crate::{
+    analysis::Analyser,
+    analysis_types::{EnityData, Symbol},
+    emitter::Emitter,
+    error::CrustErr,
+    heap::Heap,
+    interpreter::{self, Interpreter},
+    statement::{Stmt, StmtType},
+    value::StackValue,
+    vm::VM,
+};
+
+/// An interactive session that runs Crust code piece by piece. Functions, structs, enums and
+/// top-level variables stay alive across inputs, and the value of expression statements is printed.
+///
+/// ```
+/// let mut interpreter = crust::Interpreter::new();
+/// let output = interpreter.capture_output();
+/// let mut repl = crust::Repl::new(interpreter);
+///
+/// repl.eval("fn square(int n): int { return n * n; }").unwrap();
+/// repl.eval("int x = square(3);").unwrap();
+/// repl.eval("x + 1").unwrap();
+/// assert_eq!(output.take(), "10\n");
+/// ```
+pub struct Repl {
+    interpreter: Interpreter,
+    // the analysed declarations of earlier inputs, they're compiled again for every input
+    entities: EnityData<'static>,
+    vars: Vec<Symbol<'static>>,
+    locals: Vec<StackValue>,
+    heap: Heap,
+    // the inputs that declared something, `entities` and `vars` borrow from them so they have to
+    // be dropped first
+    sources: Vec<Box<str>>,
+}
+impl Repl {
+    pub fn new(interpreter: Interpreter) -> Self {
+        let mut entities = EnityData::new();
+        entities.nat_funcs = interpreter.natives.funcs.clone();
+        entities.nat_structs = interpreter.natives.structs.clone();
+        Self {
+            interpreter,
+            entities,
+            vars: vec![],
+            locals: vec![],
+            heap: Heap::new(),
+            sources: vec![],
+        }
+    }
+
+    /// Runs `input`. If it fails to compile nothing changes, if a runtime error occurs the
+    /// variables it declared are dropped but changes to earlier variables are kept.
+    pub fn eval(&mut self, input: &str) -> Result<(), CrustErr> {
+        let mut input = input.trim().to_string();
+        // allows typing '1 + 2' instead of '1 + 2;'
+        if !input.ends_with(';') && !input.ends_with('}') {
+            input.push(';');
+        }
+        let input = input.into_boxed_str();
+        // SAFETY: the contents of a boxed string don't move when the box does, and the box is
+        // either kept in `sources` until everything that borrows from it is dropped, or dropped
+        // at the end of this call when nothing that outlives the call borrows from it
+        let source: &'static str = unsafe { &*(&*input as *const str) };
+
+        let statements = self.interpreter.parse(source)?;
+
+        let (mut decls, mut stmts): (Vec<Stmt>, Vec<Stmt>) =
+            statements.into_iter().partition(|stmt| {
+                matches!(
+                    stmt.stmt,
+                    StmtType::Func { .. }
+                        | StmtType::Struct { .. }
+                        | StmtType::Enum { .. }
+                        | StmtType::Trait { .. }
+                        | StmtType::Impl { .. }
+                )
+            });
+
+        // the analyser takes the methods out of an 'impl'
+        let has_decls = !decls.is_empty();
+        let entities =
+            Analyser::analyse_repl(self.entities.clone(), &mut decls, &mut stmts, &self.vars)
+                .map_err(CrustErr::Sem)?;
+
+        let new_vars: Vec<Symbol> = stmts
+            .iter()
+            .filter_map(|stmt| match &stmt.stmt {
+                StmtType::Var {
+                    name,
+                    ty,
+                    is_const: true,
+                    ..
+                } => Some(Symbol::constant(name, ty.clone())),
+                StmtType::Var { name, ty, .. } => Some(Symbol::new(name, ty.clone())),
+                _ => None,
+            })
+            .collect();
+
+        let names: Vec<&str> = self.vars.iter().map(|var| var.name).collect();
+        let (func, heap, global_count) =
+            Emitter::compile_repl(stmts, &names, entities.clone()).map_err(CrustErr::Emit)?;
+        if self.interpreter.debug.disasm {
+            println!("{}", interpreter::disassemble(&func, "repl", &heap));
+        }
+        self.heap.append(heap);
+        self.entities = entities;
+        if has_decls || !new_vars.is_empty() {
+            self.sources.push(input);
+        }
+
+        let interpreter = &mut self.interpreter;
+        let (max_frames, debug) = (interpreter.max_frames, interpreter.debug);
+        let result = VM::interpret_with_locals(
+            func,
+            &mut self.heap,
+            max_frames,
+            debug,
+            interpreter.io(),
+            &mut self.locals,
+            global_count,
+        );
+        let _ = interpreter.out.flush();
+        if let Some(code) = result.map_err(CrustErr::Runtime)? {
+            return Err(CrustErr::Exit(code));
+        }
+
+        self.vars.extend(new_vars);
+        Ok(())
+    }
+}
@@ -1,4 +1,4 @@
-use crate::error::print_error;
+use crate::error::ScanErr;
 use std::collections::HashMap;
 
 use crate::token::{Literal, Token, TokenType};
@@ -12,7 +12,9 @@ pub struct Scanner<'source> {
     start: usize,
     current: usize,
     line: u32,
-    had_error: bool,
+    errors: Vec<ScanErr>,
+    // the number of open braces in every f-string expression that is being scanned
+    interpolations: Vec<usize>,
 }
 
 impl<'source> Scanner<'source> {
@@ -30,6 +32,8 @@ impl<'source> Scanner<'source> {
             "true",True "false",False "null",Null "self",This "parent",Super
             "struct",Struct "fn",Fn "return",Return "pr",Print "double",F64 "uint",U64
             "int",I64 "bool",Bool "str",Str "in",In "to",To "break",Break "continue",Continue
+            "import",Import "match",Match "const",Const "try",Try "catch",Catch "throw",Throw
+            "trait",Trait "impl",Impl
         );
 
         let source_len = source_file.len();
@@ -41,11 +45,12 @@ impl<'source> Scanner<'source> {
             start: 0,
             current: 0,
             line: 1,
-            had_error: false,
+            errors: vec![],
+            interpolations: vec![],
         }
     }
 
-    pub fn scan_tokens(mut self) -> Result<Vec<Token<'source>>, ()> {
+    pub fn scan_tokens(mut self) -> Result<Vec<Token<'source>>, Vec<ScanErr>> {
         while !self.at_end_input() {
             self.start = self.current;
             self.scan_token();
@@ -54,8 +59,8 @@ impl<'source> Scanner<'source> {
         self.tokens
             .push(Token::new(TokenType::Eof, "", Literal::None, self.line));
 
-        if self.had_error {
-            Err(())
+        if !self.errors.is_empty() {
+            Err(self.errors)
         } else {
             Ok(self.tokens)
         }
@@ -84,16 +89,36 @@ impl<'source> Scanner<'source> {
         match c {
             '(' => self.add_token(TokenType::LeftParen),
             ')' => self.add_token(TokenType::RightParen),
-            '{' => self.add_token(TokenType::LeftBrace),
-            '}' => self.add_token(TokenType::RightBrace),
+            '{' => {
+                if let Some(depth) = self.interpolations.last_mut() {
+                    *depth += 1;
+                }
+                self.add_token(TokenType::LeftBrace);
+            }
+            // the end of an expression in an f-string
+            '}' if self.interpolations.last() == Some(&0) => {
+                self.interpolations.pop();
+                self.fstring_part();
+            }
+            '}' => {
+                if let Some(depth) = self.interpolations.last_mut() {
+                    *depth -= 1;
+                }
+                self.add_token(TokenType::RightBrace);
+            }
             '[' => self.add_token(TokenType::LeftBracket),
             ']' => self.add_token(TokenType::RightBracket),
             ',' => self.add_token(TokenType::Comma),
             '.' => self.add_token(TokenType::Dot),
             ';' => self.add_token(TokenType::Semicolon),
+            '?' => self.add_token(TokenType::Question),
             // '^' => self.add_token(TokenType::Caret),
             ':' => ternary!(':', DoubleColon, Colon),
             '!' => ternary!('=', BangEqual, Bang),
+            '=' if self.matches('>') => {
+                self.current += 1;
+                self.add_token(TokenType::FatArrow);
+            }
             '=' => ternary!('=', EqualEqual, Equal),
             '<' => ternary!('=', LessEqual, Less),
             '>' => ternary!('=', GreaterEqual, Greater),
@@ -105,8 +130,7 @@ impl<'source> Scanner<'source> {
             '&' => {
                 if !self.matches('&') {
                     let msg = "Expected another '&' after '&'.";
-                    print_error(self.line, msg);
-                    self.had_error = true;
+                    self.error(msg);
                 } else {
                     self.add_token(TokenType::And);
                     self.current += 1;
@@ -114,9 +138,7 @@ impl<'source> Scanner<'source> {
             }
             '|' => {
                 if !self.matches('|') {
-                    let msg = "Expected another '|' after '|'.";
-                    print_error(self.line, msg);
-                    self.had_error = true;
+                    self.add_token(TokenType::Pipe);
                 } else {
                     self.add_token(TokenType::Or);
                     self.current += 1;
@@ -145,8 +167,7 @@ impl<'source> Scanner<'source> {
                     self.current += 1;
                 }
                 if self.at_end_input() {
-                    print_error(self.line, "Unterminated sequence of characters.");
-                    self.had_error = true;
+                    self.error("Unterminated sequence of characters.");
                     return;
                 }
 
@@ -156,6 +177,11 @@ impl<'source> Scanner<'source> {
                 self.current += 1;
             }
 
+            'f' if self.matches('"') => {
+                self.current += 1;
+                self.fstring_part();
+            }
+
             ' ' | '\r' | '\t' => (),
             '\n' => self.line += 1,
 
@@ -178,13 +204,38 @@ impl<'source> Scanner<'source> {
                     self.add_token(kind);
                 } else {
                     let msg = format!("'{c}' is an unvalid character.");
-                    print_error(self.line, &msg);
-                    self.had_error = true;
+                    self.error(&msg);
                 }
             }
         }
     }
 
+    // scans the text of an f-string up to the next expression or the end of the string,
+    // 'f"sum: {a + b}!"' is scanned as 'FStrPart("sum: ") a + b FStrEnd("!")'
+    fn fstring_part(&mut self) {
+        let part_start = self.current;
+        while self.peek() != '"' && self.peek() != '{' && !self.at_end_input() {
+            if self.peek() == '\n' {
+                self.line += 1;
+            }
+            self.current += 1;
+        }
+        if self.at_end_input() {
+            self.error("Unterminated f-string.");
+            return;
+        }
+
+        let str = &self.source[part_start..self.current];
+        let kind = if self.peek() == '{' {
+            self.interpolations.push(0);
+            TokenType::FStrPart
+        } else {
+            TokenType::FStrEnd
+        };
+        self.current += 1;
+        self.add_lit_token(kind, Literal::Str(str));
+    }
+
     fn check_for_end_comment(&mut self) {
         while !self.at_end_input() {
             if self.peek() == '\n' {
@@ -203,8 +254,11 @@ impl<'source> Scanner<'source> {
         }
 
         let msg = "Unterminated comment, never found '*/'.";
-        print_error(self.line, msg);
-        self.had_error = true;
+        self.error(msg);
+    }
+
+    fn error(&mut self, msg: &str) {
+        self.errors.push(ScanErr::new(self.line, msg));
     }
 
     fn peek(&self) -> char {
@@ -16,8 +16,10 @@ pub enum StmtType<'a> {
     Expr(Expr<'a>),
     Var {
         name: &'a str,
-        value: Expr<'a>,
+        // 'None' for a declaration without a value, like 'int x;'
+        value: Option<Expr<'a>>,
         ty: ValueType,
+        is_const: bool,
     },
     Println(Expr<'a>),
     Return(Expr<'a>),
@@ -38,20 +40,80 @@ pub enum StmtType<'a> {
         condition: Expr<'a>,
         body: Box<Stmt<'a>>,
     },
+    Match {
+        value: Expr<'a>,
+        arms: Vec<MatchArm<'a>>,
+    },
+    // 'error' holds the thrown value in the catch body
+    Try {
+        body: Box<Stmt<'a>>,
+        error: &'a str,
+        catch_body: Box<Stmt<'a>>,
+    },
+    Throw(Expr<'a>),
     Func {
         name: &'a str,
         parameters: Vec<(ValueType, &'a str)>,
         body: Vec<Stmt<'a>>,
         return_ty: ValueType,
         use_self: bool,
+        type_params: Vec<&'a str>,
     },
     Struct {
         name: &'a str,
         fields: Vec<(ValueType, &'a str)>,
         methods: Vec<Stmt<'a>>,
+        type_params: Vec<&'a str>,
     },
     Enum {
         name: &'a str,
-        variants: Vec<&'a str>,
+        // every variant with the types of the values it holds
+        variants: Vec<(&'a str, Vec<ValueType>)>,
+    },
+    Trait {
+        name: &'a str,
+        methods: Vec<TraitMethod<'a>>,
+    },
+    // 'impl Shape for Circle { ... }', the methods are added to the struct
+    Impl {
+        trait_name: &'a str,
+        struct_name: &'a str,
+        methods: Vec<Stmt<'a>>,
+    },
+}
+
+/// The signature of a method that every implementation of a trait has.
+#[derive(Debug, Clone)]
+pub struct TraitMethod<'a> {
+    pub name: &'a str,
+    pub parameters: Vec<(ValueType, &'a str)>,
+    pub return_ty: ValueType,
+    pub use_self: bool,
+    pub line: u32,
+}
+
+#[derive(Debug, Clone)]
+pub struct MatchArm<'a> {
+    pub patterns: Vec<Pattern<'a>>,
+    pub body: Stmt<'a>,
+    pub line: u32,
+}
+
+#[derive(Debug, Clone)]
+pub enum Pattern<'a> {
+    // '_'
+    Wildcard,
+    // a literal or an enum variant
+    Value(Expr<'a>),
+    // 'start to end', excluding end like in for loops
+    Range {
+        start: Expr<'a>,
+        end: Expr<'a>,
+    },
+    // a variant of an enum that holds values, with the names its values are bound to.
+    // The analyser turns 'Shape::Circle(r)' into this, '_' doesn't bind a value
+    Variant {
+        tag: u8,
+        bindings: Vec<Option<&'a str>>,
     },
 }
@@ -72,6 +72,8 @@ pub enum TokenType {
     Comma,
     Dot,
     Semicolon,
+    Pipe,
+    Question,
 
     Minus,
     Plus,
@@ -85,6 +87,7 @@ pub enum TokenType {
     BangEqual,
     Equal,
     EqualEqual,
+    FatArrow,
     Greater,
     GreaterEqual,
     Less,
@@ -98,6 +101,9 @@ pub enum TokenType {
     // literals
     Identifier,
     StringLit,
+    // the text of an f-string before an expression, and the text after the last one
+    FStrPart,
+    FStrEnd,
     Num,
 
     // keywords
@@ -109,18 +115,26 @@ pub enum TokenType {
     False,
     For,
     Break,
+    Catch,
+    Const,
     Continue,
     In,
     To,
     Fn,
     If,
+    Impl,
+    Import,
+    Match,
     Null,
     Or,
     Print,
     Return,
     Super,
     This,
+    Throw,
+    Trait,
     True,
+    Try,
     While,
 
     // var decl
@@ -155,13 +169,16 @@ impl TokenType {
             | TT::Less
             | TT::LessEqual => ParseRule::new(F::Empty, F::Binary, P::Comparison),
             TT::Identifier => ParseRule::new(F::Var, F::Empty, P::None),
-            TT::StringLit => ParseRule::new(F::String, F::Empty, P::None),
+            TT::StringLit | TT::FStrEnd => ParseRule::new(F::String, F::Empty, P::None),
+            TT::FStrPart => ParseRule::new(F::FString, F::Empty, P::None),
             TT::Num => ParseRule::new(F::Number, F::Empty, P::None),
             TT::As => ParseRule::new(F::Number, F::Cast, P::Call),
+            TT::Question => ParseRule::new(F::Empty, F::Try, P::Call),
             TT::And => ParseRule::new(F::Empty, F::Binary, P::And),
             TT::Or => ParseRule::new(F::Empty, F::Binary, P::Or),
             TT::False | TT::True | TT::Null => ParseRule::new(F::Literal, F::Empty, P::None),
             TT::This => ParseRule::new(F::This, F::Empty, P::None),
+            TT::Fn => ParseRule::new(F::Lambda, F::Empty, P::None),
             _ => ParseRule::new(F::Empty, F::Empty, P::None),
         }
     }
@@ -1,9 +1,10 @@
 use std::{
+    collections::HashMap,
     fmt::{self, Display},
-    ops::{Neg, Not},
+    ops::Not,
 };
 
-use crate::object::Object;
+use crate::object::{ObjEnum, Object};
 
 #[derive(Debug, Clone, PartialEq, PartialOrd)]
 pub enum ValueType {
@@ -15,23 +16,110 @@ pub enum ValueType {
     U64,
     Str,
     Arr(Box<ValueType>),
+    // 'int?', a value of the type or null
+    Nullable(Box<ValueType>),
     Struct(String),
+    // a struct with type arguments, like 'Vec<int>'
+    Generic(Box<GenericType>),
+    // a type parameter of a generic declaration, like the 'T' in 'Vec<T>'
+    TypeParam(String),
     Enum(String),
+    // an enum with variants that hold values, these are stored on the heap
+    DataEnum(String),
+    // an instance of any struct that implements the trait
+    Trait(String),
     UnknownType(String),
+    Func {
+        parameters: Vec<ValueType>,
+        return_ty: Box<ValueType>,
+    },
 }
 impl ValueType {
+    pub fn generic(name: String, args: Vec<ValueType>) -> Self {
+        ValueType::Generic(Box::new(GenericType { name, args }))
+    }
+
+    pub fn nullable(ty: ValueType) -> Self {
+        match ty {
+            ValueType::Nullable(_) | ValueType::Null | ValueType::Any => ty,
+            ty => ValueType::Nullable(Box::new(ty)),
+        }
+    }
+
+    // the type without null, 'int' for 'int?'
+    pub fn non_null(&self) -> &ValueType {
+        match self {
+            ValueType::Nullable(inner) => inner,
+            ty => ty,
+        }
+    }
+
     pub fn is_num(&self) -> bool {
         matches!(
             self,
             ValueType::F64 | ValueType::I64 | ValueType::U64 | ValueType::Enum(_)
         )
     }
+
+    // the name of the struct for both generic and non-generic structs
+    pub fn struct_name(&self) -> Option<&str> {
+        match self {
+            ValueType::Struct(name) => Some(name),
+            ValueType::Generic(generic) => Some(&generic.name),
+            _ => None,
+        }
+    }
+
+    pub fn has_type_params(&self) -> bool {
+        match self {
+            ValueType::TypeParam(_) => true,
+            ValueType::Arr(inner) | ValueType::Nullable(inner) => inner.has_type_params(),
+            ValueType::Generic(generic) => generic.args.iter().any(ValueType::has_type_params),
+            ValueType::Func {
+                parameters,
+                return_ty,
+            } => parameters.iter().any(ValueType::has_type_params) || return_ty.has_type_params(),
+            _ => false,
+        }
+    }
+
+    /// Replaces the type parameters in this type with the types they are bound to.
+    pub fn substitute(&self, bindings: &HashMap<String, ValueType>) -> ValueType {
+        match self {
+            ValueType::TypeParam(name) => match bindings.get(name) {
+                Some(ty) => ty.clone(),
+                None => self.clone(),
+            },
+            ValueType::Arr(inner) => ValueType::Arr(Box::new(inner.substitute(bindings))),
+            ValueType::Nullable(inner) => ValueType::nullable(inner.substitute(bindings)),
+            ValueType::Generic(generic) => {
+                let args = generic
+                    .args
+                    .iter()
+                    .map(|ty| ty.substitute(bindings))
+                    .collect();
+                ValueType::generic(generic.name.clone(), args)
+            }
+            ValueType::Func {
+                parameters,
+                return_ty,
+            } => ValueType::Func {
+                parameters: parameters
+                    .iter()
+                    .map(|ty| ty.substitute(bindings))
+                    .collect(),
+                return_ty: Box::new(return_ty.substitute(bindings)),
+            },
+            _ => self.clone(),
+        }
+    }
 }
 impl fmt::Display for ValueType {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         // dbg!(self);
         match self {
             ValueType::Arr(ty) => write!(f, "[{ty}]"),
+            ValueType::Nullable(ty) => write!(f, "{ty}?"),
             ValueType::Any => write!(f, "Any"),
             ValueType::Null => write!(f, "Null"),
             ValueType::Bool => write!(f, "Bool"),
@@ -40,12 +128,36 @@ impl fmt::Display for ValueType {
             ValueType::U64 => write!(f, "Uint"),
             ValueType::Str => write!(f, "String"),
             ValueType::Struct(s) => write!(f, "struct {s}"),
-            ValueType::Enum(e) => write!(f, "enum {e}"),
+            ValueType::Generic(generic) => {
+                let args: Vec<String> = generic.args.iter().map(|ty| ty.to_string()).collect();
+                write!(f, "struct {}<{}>", generic.name, args.join(", "))
+            }
+            ValueType::TypeParam(name) => write!(f, "{name}"),
+            ValueType::Enum(e) | ValueType::DataEnum(e) => write!(f, "enum {e}"),
+            ValueType::Trait(t) => write!(f, "trait {t}"),
             ValueType::UnknownType(t) => write!(f, "type {t}"),
+            ValueType::Func {
+                parameters,
+                return_ty,
+            } => {
+                let parameters: Vec<String> = parameters.iter().map(|ty| ty.to_string()).collect();
+                write!(f, "fn({})", parameters.join(", "))?;
+                match **return_ty {
+                    ValueType::Null => Ok(()),
+                    _ => write!(f, ": {return_ty}"),
+                }
+            }
         }
     }
 }
 
+// boxed in 'ValueType' so it doesn't grow every type that holds one
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
+pub struct GenericType {
+    pub name: String,
+    pub args: Vec<ValueType>,
+}
+
 #[derive(Debug, Clone, Copy)]
 pub enum StackValue {
     Null,
@@ -56,22 +168,32 @@ pub enum StackValue {
     Obj(Object),
 }
 
+// integer operations are checked, overflow and division by zero result in a runtime error
 macro_rules! add_num_operation {
-    ($fun_name: ident, $op: tt) => {
+    ($fun_name: ident, $op: tt, $checked: ident) => {
         #[inline(always)]
-        pub fn $fun_name(self, rhs: StackValue) -> StackValue {
-            match (self, rhs) {
-                (StackValue::F64(lhs), StackValue::F64(rhs)) => StackValue::F64(lhs $op rhs),
-                (StackValue::I64(lhs), StackValue::I64(rhs)) => StackValue::I64(lhs $op rhs),
-                (StackValue::U64(lhs), StackValue::U64(rhs)) => StackValue::U64(lhs $op rhs),
-                (StackValue::U64(lhs), StackValue::I64(rhs)) => StackValue::I64(lhs as i64 $op rhs),
-                (StackValue::I64(lhs), StackValue::U64(rhs)) => StackValue::I64(lhs $op rhs as i64),
+        pub fn $fun_name(self, rhs: StackValue) -> Result<StackValue, String> {
+            let result = match (self, rhs) {
+                (StackValue::F64(lhs), StackValue::F64(rhs)) => return Ok(StackValue::F64(lhs $op rhs)),
+                (StackValue::I64(lhs), StackValue::I64(rhs)) => lhs.$checked(rhs).map(StackValue::I64),
+                (StackValue::U64(lhs), StackValue::U64(rhs)) => lhs.$checked(rhs).map(StackValue::U64),
+                (StackValue::U64(lhs), StackValue::I64(rhs)) => i64::try_from(lhs).ok().and_then(|lhs| lhs.$checked(rhs)).map(StackValue::I64),
+                (StackValue::I64(lhs), StackValue::U64(rhs)) => i64::try_from(rhs).ok().and_then(|rhs| lhs.$checked(rhs)).map(StackValue::I64),
                 _ => unreachable!("operation is only available for numbers"),
-            }
+            };
+            result.ok_or_else(|| arithmetic_err(self, stringify!($op), rhs))
         }
     };
 }
 
+fn arithmetic_err(lhs: StackValue, op: &str, rhs: StackValue) -> String {
+    if op == "/" && rhs.equals(StackValue::I64(0)) {
+        format!("Attempted to divide '{lhs}' by zero.")
+    } else {
+        format!("Integer overflow while evaluating '{lhs} {op} {rhs}'.")
+    }
+}
+
 macro_rules! add_num_comparison {
     ($fun_name: ident, $op: tt) => {
         #[inline(always)]
@@ -89,10 +211,10 @@ macro_rules! add_num_comparison {
 }
 
 impl StackValue {
-    // add_num_operation!(add_nums, +);
-    add_num_operation!(sub_nums, -);
-    add_num_operation!(mul_nums, *);
-    add_num_operation!(div_nums, /);
+    add_num_operation!(add_nums, +, checked_add);
+    add_num_operation!(sub_nums, -, checked_sub);
+    add_num_operation!(mul_nums, *, checked_mul);
+    add_num_operation!(div_nums, /, checked_div);
 
     add_num_comparison!(is_greater_than, >);
     add_num_comparison!(is_greater_equal_than, >=);
@@ -114,10 +236,46 @@ impl StackValue {
             (StackValue::Obj(Object::Str(str1)), StackValue::Obj(Object::Str(str2))) => {
                 str1.data == str2.data
             }
+            (StackValue::Obj(Object::Enum(lhs)), StackValue::Obj(Object::Enum(rhs))) => {
+                let mut values = lhs.data.values.iter().zip(&rhs.data.values);
+                lhs.data.tag == rhs.data.tag && values.all(|(lhs, rhs)| lhs.equals(*rhs))
+            }
+            // arrays, instances and maps are only equal to themselves, like functions
+            (
+                StackValue::Obj(lhs @ (Object::Arr(_) | Object::Inst(_) | Object::Map(_))),
+                StackValue::Obj(rhs @ (Object::Arr(_) | Object::Inst(_) | Object::Map(_))),
+            ) => std::ptr::eq(lhs.header(), rhs.header()),
+            // functions are only equal to themselves
+            (StackValue::Obj(Object::Func(lhs)), StackValue::Obj(Object::Func(rhs))) => {
+                lhs.ptr == rhs.ptr
+            }
+            (StackValue::Obj(Object::Native(lhs)), StackValue::Obj(Object::Native(rhs))) => {
+                lhs.ptr == rhs.ptr
+            }
+            (StackValue::Obj(Object::Closure(lhs)), StackValue::Obj(Object::Closure(rhs))) => {
+                lhs.ptr == rhs.ptr
+            }
+            (
+                StackValue::Obj(Object::Func(_) | Object::Native(_) | Object::Closure(_)),
+                StackValue::Obj(Object::Func(_) | Object::Native(_) | Object::Closure(_)),
+            ) => false,
             _ => unreachable!(),
         }
     }
     #[inline(always)]
+    pub fn to_index(self, len: usize) -> Result<usize, String> {
+        let index = match self {
+            StackValue::U64(index) => index as i128,
+            StackValue::I64(index) => index as i128,
+            _ => unreachable!("only integers can be used as index"),
+        };
+
+        if index < 0 || index >= len as i128 {
+            return Err(format!("Index {index} out of bounds for length {len}."));
+        }
+        Ok(index as usize)
+    }
+    #[inline(always)]
     pub fn and(self, rhs: StackValue) -> bool {
         match (self, rhs) {
             (StackValue::Bool(lhs), StackValue::Bool(rhs)) => lhs && rhs,
@@ -133,15 +291,16 @@ impl StackValue {
     }
 }
 
-impl Neg for StackValue {
-    type Output = Self;
-
+impl StackValue {
     #[inline(always)]
-    fn neg(self) -> Self::Output {
+    pub fn negate(self) -> Result<StackValue, String> {
         match self {
-            StackValue::F64(value) => StackValue::F64(-value),
-            StackValue::I64(value) => StackValue::I64(-value),
-            StackValue::U64(_) => panic!("attempted to use minus on unsigned int."),
+            StackValue::F64(value) => Ok(StackValue::F64(-value)),
+            StackValue::I64(value) => match value.checked_neg() {
+                Some(value) => Ok(StackValue::I64(value)),
+                None => Err(format!("Integer overflow while evaluating '-{value}'.")),
+            },
+            StackValue::U64(_) => Err("Attempted to use minus on an unsigned int.".to_string()),
             _ => {
                 unreachable!("Attempted to use operation that is not defined for this type.")
             }
@@ -171,10 +330,22 @@ impl Display for StackValue {
             StackValue::I64(num) => write!(f, "{num}"),
             StackValue::Obj(o) => match o {
                 Object::Str(s) => write!(f, "{}", s.data),
-                Object::Func(_) => unreachable!(),
-                Object::Native(_) => unreachable!(),
+                Object::Func(func) => write!(f, "fn {}", func.data.get_name()),
+                Object::Native(func) => write!(f, "nat {}", func.data.get_name()),
+                Object::Closure(closure) => write!(f, "fn {}", closure.data.func.data.get_name()),
+                Object::Upvalue(_) => unreachable!(),
                 Object::Arr(a) => write!(f, "{:?}", a.data.elements),
                 Object::Inst(i) => write!(f, "inst {:?}", i.data.fields),
+                Object::Map(m) => {
+                    let entries: Vec<String> = m
+                        .data
+                        .entries
+                        .iter()
+                        .map(|(k, v)| format!("{k}: {v}"))
+                        .collect();
+                    write!(f, "{{{}}}", entries.join(", "))
+                }
+                Object::Enum(e) => write!(f, "{}", Self::display_enum(&e.data)),
             },
         }
     }
@@ -191,6 +362,8 @@ impl StackValue {
                 Object::Str(s) => format!("{:?}", s.data),
                 Object::Func(f) => format!("fn {}", f.data.get_name()),
                 Object::Native(f) => format!("nat {}", f.data.get_name()),
+                Object::Closure(c) => format!("fn {}", c.data.func.data.get_name()),
+                Object::Upvalue(_) => unreachable!(),
                 Object::Arr(a) => format!("arr {:?}", a.data.elements),
                 Object::Inst(i) => {
                     let mut s = String::from("inst ");
@@ -199,7 +372,30 @@ impl StackValue {
                     }
                     s
                 }
+                Object::Enum(e) => Self::display_enum(&e.data),
+                Object::Map(m) => {
+                    let entries: Vec<String> = m
+                        .data
+                        .entries
+                        .iter()
+                        .map(|(k, v)| format!("{}: {}", k.display(), v.display()))
+                        .collect();
+                    format!("{{{}}}", entries.join(", "))
+                }
             },
         }
     }
+
+    // 'Rect(2, 3)', or just 'Empty' for variants without values
+    fn display_enum(value: &ObjEnum) -> String {
+        let Object::Str(name) = value.name else {
+            unreachable!()
+        };
+        if value.values.is_empty() {
+            return name.data.clone();
+        }
+
+        let values: Vec<String> = value.values.iter().map(StackValue::display).collect();
+        format!("{}({})", name.data, values.join(", "))
+    }
 }
@@ -1,29 +1,36 @@
-use colored::Colorize;
+use std::io::{BufRead, Write};
 
 use crate::{
-    error::DEBUG_TRACE_EXECUTION,
+    error::{DebugOptions, RuntimeErr},
     heap::Heap,
-    object::{Gc, ObjArr, ObjFunc, ObjInstance, Object},
+    object::{Gc, NatCtx, ObjArr, ObjClosure, ObjEnum, ObjFunc, ObjInstance, ObjUpvalue, Object},
     op_code::OpCode,
     value::StackValue,
 };
 
-pub enum InterpretResult {
-    Ok,
-    // RuntimeError,
-}
-
-pub const STACK_SIZE: usize = 256;
-const FRAMES_SIZE: usize = 64;
+const INITIAL_STACK_SIZE: usize = 256;
+const INITIAL_FRAMES_SIZE: usize = 64;
+pub const DEFAULT_MAX_FRAMES: usize = 16_384;
 
 #[derive(Debug, Clone, Copy)]
 #[repr(C)]
 struct CallFrame {
     func: Gc<ObjFunc>,
+    // the captured variables of the function if it's a closure
+    closure: Option<Gc<ObjClosure>>,
     ip: *const u8,
     slots: usize,
 }
 
+// the catch body of a 'try' block that is being executed
+#[derive(Debug, Clone, Copy)]
+struct Handler {
+    // the index of the frame the 'try' block is in
+    frame: usize,
+    catch_ip: *const u8,
+    stack_top: usize,
+}
+
 #[inline(always)]
 unsafe fn read_byte(ip: &mut *const u8) -> u8 {
     unsafe {
@@ -43,47 +50,157 @@ unsafe fn read_short(ip: &mut *const u8) -> u16 {
     ((high as u16) << 8) | (low as u16)
 }
 
-pub struct VM {
-    frames: [CallFrame; FRAMES_SIZE],
-    frame_count: usize,
-    stack: [StackValue; STACK_SIZE],
+/// Where a program reads its input from and writes its output to.
+pub struct ProgramIo<'a> {
+    pub out: &'a mut dyn Write,
+    pub input: &'a mut dyn BufRead,
+    // the command-line arguments passed to 'main'
+    pub args: &'a [String],
+}
+
+pub struct VM<'a> {
+    frames: Vec<CallFrame>,
+    max_frames: usize,
+    // grows on demand, the amount of frames is what limits the depth of recursion
+    stack: Vec<StackValue>,
     stack_top: usize,
-    heap: Heap,
+    heap: &'a mut Heap,
+    // upvalues that still point to the stack, sorted by their location
+    open_upvalues: Vec<Gc<ObjUpvalue>>,
+    // innermost last
+    handlers: Vec<Handler>,
+    out: &'a mut dyn Write,
+    input: &'a mut dyn BufRead,
+    args: &'a [String],
+    // set when the program calls 'exit'
+    exit_code: Option<i32>,
+    trace: bool,
 }
-impl VM {
-    pub fn interpret(func: ObjFunc, mut heap: Heap) -> InterpretResult {
+impl<'a> VM<'a> {
+    /// Runs `func`, the result is the exit code if the program called 'exit'.
+    pub fn interpret(
+        func: ObjFunc,
+        heap: &'a mut Heap,
+        max_frames: usize,
+        debug: DebugOptions,
+        io: ProgramIo<'a>,
+    ) -> Result<Option<i32>, RuntimeErr> {
+        let mut vm = Self::new(func, heap, max_frames, debug, io);
+        unsafe { vm.run() }?;
+        Ok(vm.exit_code)
+    }
+
+    /// Runs `func` with `locals` in its first local slots. Afterwards `locals` holds the values of
+    /// the first `local_count` locals, or of the ones it already held if a runtime error occurred.
+    pub fn interpret_with_locals(
+        func: ObjFunc,
+        heap: &'a mut Heap,
+        max_frames: usize,
+        debug: DebugOptions,
+        io: ProgramIo<'a>,
+        locals: &mut Vec<StackValue>,
+        local_count: usize,
+    ) -> Result<Option<i32>, RuntimeErr> {
+        let mut vm = Self::new(func, heap, max_frames, debug, io);
+        for local in locals.iter() {
+            vm.stack_push(*local);
+        }
+
+        let result = unsafe { vm.run() };
+
+        // returning from the outermost frame doesn't overwrite its locals, so they're still on the stack
+        let local_count = if result.is_ok() {
+            local_count
+        } else {
+            locals.len()
+        };
+        locals.clear();
+        locals.extend_from_slice(&vm.stack[1..=local_count]);
+
+        result.map(|_| vm.exit_code)
+    }
+
+    fn new(
+        func: ObjFunc,
+        heap: &'a mut Heap,
+        max_frames: usize,
+        debug: DebugOptions,
+        io: ProgramIo<'a>,
+    ) -> Self {
+        heap.print_on_collect = debug.heap;
         let (func_object, gc_obj) = heap.alloc_permanent(func, Object::Func);
 
         let frame = CallFrame {
             ip: gc_obj.data.chunk.get_ptr(),
             slots: 0,
             func: gc_obj,
+            closure: None,
         };
-        let frames = [frame; FRAMES_SIZE];
+        let mut frames = Vec::with_capacity(INITIAL_FRAMES_SIZE);
+        frames.push(frame);
 
         let mut vm = Self {
             heap,
             frames,
-            frame_count: 1,
-            stack: [StackValue::Null; STACK_SIZE],
+            max_frames,
+            stack: vec![StackValue::Null; INITIAL_STACK_SIZE],
             stack_top: 0,
+            open_upvalues: vec![],
+            handlers: vec![],
+            out: io.out,
+            input: io.input,
+            args: io.args,
+            exit_code: None,
+            trace: debug.trace,
         };
 
         vm.stack_push(StackValue::Obj(func_object));
-
-        unsafe { vm.run() }
+        vm
     }
 
-    unsafe fn run(&mut self) -> InterpretResult {
-        let mut frame = self.frames.as_mut_ptr().add(self.frame_count - 1);
+    unsafe fn run(&mut self) -> Result<(), RuntimeErr> {
+        let mut frame = self.current_frame();
         let mut ip = (*frame).ip;
 
         loop {
-            if DEBUG_TRACE_EXECUTION {
+            if self.trace {
                 (*frame).ip = ip;
                 self.debug_trace(frame)
             }
 
+            // an error inside a 'try' block is caught as its message
+            macro_rules! runtime_err {
+                ($msg: expr) => {{
+                    (*frame).ip = ip;
+                    let msg: String = $msg;
+                    if self.handlers.is_empty() {
+                        return Err(self.runtime_error(msg));
+                    }
+
+                    let (msg, _) = self.heap.alloc(
+                        msg,
+                        Object::Str,
+                        &self.stack[..self.stack_top],
+                        &self.open_upvalues,
+                    );
+                    self.unwind(StackValue::Obj(msg));
+                    frame = self.current_frame();
+                    ip = (*frame).ip;
+                    continue;
+                }};
+            }
+
+            macro_rules! arithmetic_op {
+                ($operation: ident) => {{
+                    let rhs = self.stack_pop();
+                    let lhs = self.stack_pop();
+                    match lhs.$operation(rhs) {
+                        Ok(new_value) => self.stack_push(new_value),
+                        Err(msg) => runtime_err!(msg),
+                    }
+                }};
+            }
+
             macro_rules! binary_op {
                 ($operation: ident) => {{
                     let rhs = self.stack_pop();
@@ -115,6 +232,66 @@ impl VM {
                     let slot = read_byte(&mut ip) as usize;
                     self.stack[(*frame).slots + slot] = self.stack_peek();
                 }
+                OpCode::GetUpvalue => {
+                    let index = read_byte(&mut ip) as usize;
+                    let upvalue = (*frame).closure.unwrap().data.upvalues[index];
+                    let value = match upvalue.data.closed {
+                        Some(value) => value,
+                        None => self.stack[upvalue.data.location],
+                    };
+                    self.stack_push(value);
+                }
+                OpCode::SetUpvalue => {
+                    let index = read_byte(&mut ip) as usize;
+                    let mut upvalue = (*frame).closure.unwrap().data.upvalues[index];
+                    let value = self.stack_peek();
+                    match upvalue.data.closed {
+                        Some(_) => upvalue.data.closed = Some(value),
+                        None => self.stack[upvalue.data.location] = value,
+                    }
+                }
+                OpCode::CloseUpvalue => {
+                    self.close_upvalues(self.stack_top - 1);
+                    self.pop_no_return();
+                }
+                OpCode::Closure => {
+                    let index = read_byte(&mut ip) as usize;
+                    let frame_func = (*frame).func;
+                    let StackValue::Obj(Object::Func(func)) =
+                        frame_func.data.chunk.constants[index]
+                    else {
+                        unreachable!()
+                    };
+
+                    let mut upvalues = Vec::with_capacity(func.data.upvalue_count as usize);
+                    for _ in 0..func.data.upvalue_count {
+                        let is_local = read_byte(&mut ip) == 1;
+                        let index = read_byte(&mut ip) as usize;
+                        let upvalue = if is_local {
+                            self.capture_upvalue((*frame).slots + index)
+                        } else {
+                            (*frame).closure.unwrap().data.upvalues[index]
+                        };
+                        upvalues.push(upvalue);
+                    }
+
+                    let closure = ObjClosure::new(func, upvalues);
+                    let (obj, _) = self.heap.alloc(
+                        closure,
+                        Object::Closure,
+                        &self.stack[..self.stack_top],
+                        &self.open_upvalues,
+                    );
+                    self.stack_push(StackValue::Obj(obj));
+                }
+                OpCode::GetGlobal => {
+                    let slot = read_byte(&mut ip) as usize;
+                    self.stack_push(self.stack[slot]);
+                }
+                OpCode::SetGlobal => {
+                    let slot = read_byte(&mut ip) as usize;
+                    self.stack[slot] = self.stack_peek();
+                }
 
                 OpCode::AllocArr => {
                     let len = self.stack_pop();
@@ -131,44 +308,65 @@ impl VM {
                     }
 
                     let obj = ObjArr::new(values);
-                    let (object, _) =
-                        self.heap
-                            .alloc(obj, Object::Arr, &mut self.stack, self.stack_top);
+                    let (object, _) = self.heap.alloc(
+                        obj,
+                        Object::Arr,
+                        &self.stack[..self.stack_top],
+                        &self.open_upvalues,
+                    );
                     let arr = StackValue::Obj(object);
                     self.stack_push(arr);
                 }
                 OpCode::IndexArr => {
-                    let index = match self.stack_pop() {
-                        StackValue::U64(index) => index as usize,
-                        StackValue::I64(index) => index as usize,
+                    let index = self.stack_pop();
+                    match self.stack_pop() {
+                        StackValue::Obj(Object::Arr(arr)) => {
+                            match index.to_index(arr.data.elements.len()) {
+                                Ok(index) => self.stack_push(arr.data.elements[index]),
+                                Err(msg) => runtime_err!(msg),
+                            }
+                        }
+                        // a string is indexed by its characters
+                        StackValue::Obj(Object::Str(str)) => {
+                            let index = match index.to_index(str.data.chars().count()) {
+                                Ok(index) => index,
+                                Err(msg) => runtime_err!(msg),
+                            };
+                            let char = str.data.chars().nth(index).unwrap().to_string();
+                            let (obj, _) = self.heap.alloc(
+                                char,
+                                Object::Str,
+                                &self.stack[..self.stack_top],
+                                &self.open_upvalues,
+                            );
+                            self.stack_push(StackValue::Obj(obj));
+                        }
                         _ => unreachable!(),
-                    };
-
-                    let arr = self.stack_pop();
-                    if let StackValue::Obj(Object::Arr(arr)) = arr {
-                        let value = arr.data.elements[index];
-                        self.stack_push(value);
                     }
                 }
                 OpCode::AssignIndex => {
                     let new_value = self.stack_pop();
-                    let index = match self.stack_pop() {
-                        StackValue::U64(index) => index as usize,
-                        StackValue::I64(index) => index as usize,
-                        _ => unreachable!(),
+                    let index = self.stack_pop();
+                    let StackValue::Obj(Object::Arr(mut arr)) = self.stack_peek() else {
+                        unreachable!()
                     };
 
-                    let arr = self.stack_peek();
-                    if let StackValue::Obj(Object::Arr(mut arr)) = arr {
-                        arr.data.elements[index] = new_value;
+                    match index.to_index(arr.data.elements.len()) {
+                        Ok(index) => arr.data.elements[index] = new_value,
+                        Err(msg) => runtime_err!(msg),
                     }
                 }
 
                 OpCode::FuncCall => {
                     let arg_count = read_byte(&mut ip) as usize;
                     (*frame).ip = ip;
-                    self.call(arg_count);
-                    frame = self.frames.as_mut_ptr().add(self.frame_count - 1);
+                    if let Err(msg) = self.call(arg_count) {
+                        runtime_err!(msg);
+                    }
+                    if self.exit_code.is_some() {
+                        return Ok(());
+                    }
+                    frame = self.current_frame();
                     ip = (*frame).ip;
                 }
                 OpCode::PushMethod => {
@@ -184,6 +382,24 @@ impl VM {
                     self.stack_push(method);
                     self.stack_push(inst_stack);
                 }
+                OpCode::PushTraitMethod => {
+                    let trait_id = read_byte(&mut ip) as usize;
+                    let index = read_byte(&mut ip) as usize;
+                    let inst_stack = self.stack_pop();
+                    let StackValue::Obj(Object::Inst(inst)) = inst_stack else {
+                        unreachable!()
+                    };
+                    let Some(vtables) = inst.data.vtables else {
+                        unreachable!()
+                    };
+                    let StackValue::Obj(Object::Arr(vtable)) = vtables.data.elements[trait_id]
+                    else {
+                        unreachable!()
+                    };
+
+                    self.stack_push(vtable.data.elements[index]);
+                    self.stack_push(inst_stack);
+                }
 
                 OpCode::AllocInstance => {
                     let methods_len = read_byte(&mut ip) as usize;
@@ -201,13 +417,57 @@ impl VM {
                     }
                     // dbg!(&methods);
 
-                    let inst = ObjInstance::new(fields, methods);
-                    let (obj, _) =
-                        self.heap
-                            .alloc(inst, Object::Inst, &mut self.stack, self.stack_top);
+                    // the vtables are permanent, so they don't need to be marked
+                    let vtables = match self.stack_pop() {
+                        StackValue::Obj(Object::Arr(vtables)) => Some(vtables),
+                        _ => None,
+                    };
+
+                    let inst = ObjInstance::new(fields, methods, vtables);
+                    let (obj, _) = self.heap.alloc(
+                        inst,
+                        Object::Inst,
+                        &self.stack[..self.stack_top],
+                        &self.open_upvalues,
+                    );
                     let obj = StackValue::Obj(obj);
                     self.stack_push(obj);
                 }
+                OpCode::AllocEnum => {
+                    let tag = read_byte(&mut ip);
+                    let values_len = read_byte(&mut ip) as usize;
+
+                    // the values stay on the stack until the enum is allocated, so they can't
+                    // be collected in the meantime
+                    let start = self.stack_top - values_len;
+                    let values = self.stack[start..self.stack_top].to_vec();
+                    let StackValue::Obj(name) = self.stack[start - 1] else {
+                        unreachable!()
+                    };
+
+                    let value = ObjEnum::new(tag, name, values);
+                    let (obj, _) = self.heap.alloc(
+                        value,
+                        Object::Enum,
+                        &self.stack[..self.stack_top],
+                        &self.open_upvalues,
+                    );
+                    self.stack_top = start - 1;
+                    self.stack_push(StackValue::Obj(obj));
+                }
+                OpCode::GetEnumTag => {
+                    let StackValue::Obj(Object::Enum(value)) = self.stack_pop() else {
+                        unreachable!()
+                    };
+                    self.stack_push(StackValue::U64(value.data.tag as u64));
+                }
+                OpCode::GetEnumValue => {
+                    let index = read_byte(&mut ip) as usize;
+                    let StackValue::Obj(Object::Enum(value)) = self.stack_pop() else {
+                        unreachable!()
+                    };
+                    self.stack_push(value.data.values[index]);
+                }
                 OpCode::GetPubField => {
                     let index = read_byte(&mut ip) as usize;
                     let inst = self.stack_pop();
@@ -250,15 +510,26 @@ impl VM {
                 OpCode::Return => {
                     let result = self.stack_pop();
 
-                    self.frame_count -= 1;
-                    if self.frame_count == 0 {
+                    let slots = (*frame).slots;
+                    self.close_upvalues(slots);
+                    self.frames.pop();
+                    // the function can return from inside a 'try' block
+                    let frame_count = self.frames.len();
+                    while self
+                        .handlers
+                        .last()
+                        .is_some_and(|handler| handler.frame >= frame_count)
+                    {
+                        self.handlers.pop();
+                    }
+                    if self.frames.is_empty() {
                         self.pop_no_return();
-                        return InterpretResult::Ok;
+                        return Ok(());
                     }
 
-                    self.stack_top = (*frame).slots;
+                    self.stack_top = slots;
                     self.stack_push(result);
-                    frame = self.frames.as_mut_ptr().add(self.frame_count - 1);
+                    frame = self.current_frame();
                     ip = (*frame).ip;
                 }
 
@@ -272,6 +543,39 @@ impl VM {
                         ip = ip.add(offset);
                     }
                 }
+                // a Result or Option that holds a value is replaced by it, otherwise it's left
+                // for the return that follows
+                OpCode::TryUnwrap => {
+                    let offset = read_short(&mut ip) as usize;
+                    let StackValue::Obj(Object::Enum(value)) = self.stack_peek() else {
+                        unreachable!()
+                    };
+                    if value.data.tag == 0 {
+                        self.stack_pop();
+                        self.stack_push(value.data.values[0]);
+                        ip = ip.add(offset);
+                    }
+                }
+                OpCode::PushHandler => {
+                    let offset = read_short(&mut ip) as usize;
+                    self.handlers.push(Handler {
+                        frame: self.frames.len() - 1,
+                        catch_ip: ip.add(offset),
+                        stack_top: self.stack_top,
+                    });
+                }
+                OpCode::PopHandler => {
+                    self.handlers.pop();
+                }
+                OpCode::Throw => {
+                    let value = self.stack_pop();
+                    if self.handlers.is_empty() {
+                        runtime_err!(value.to_string());
+                    }
+                    self.unwind(value);
+                    frame = self.current_frame();
+                    ip = (*frame).ip;
+                }
                 OpCode::Loop => {
                     let offset = read_short(&mut ip) as usize;
                     ip = ip.sub(offset);
@@ -304,15 +608,29 @@ impl VM {
                     };
                     self.stack_push(new_value);
                 }
+                OpCode::CastToStr => {
+                    // the value stays on the stack while the string is allocated
+                    let value = self.stack_peek();
+                    if !matches!(value, StackValue::Obj(Object::Str(_))) {
+                        let (obj, _) = self.heap.alloc(
+                            value.to_string(),
+                            Object::Str,
+                            &self.stack[..self.stack_top],
+                            &self.open_upvalues,
+                        );
+                        self.stack_pop();
+                        self.stack_push(StackValue::Obj(obj));
+                    }
+                }
 
                 OpCode::True => self.stack_push(StackValue::Bool(true)),
                 OpCode::False => self.stack_push(StackValue::Bool(false)),
                 OpCode::Null => self.stack_push(StackValue::Null),
 
-                OpCode::Negate => {
-                    let new_value = -self.stack_pop();
-                    self.stack_push(new_value);
-                }
+                OpCode::Negate => match self.stack_pop().negate() {
+                    Ok(new_value) => self.stack_push(new_value),
+                    Err(msg) => runtime_err!(msg),
+                },
                 OpCode::Not => {
                     let new_value = !self.stack_pop();
                     self.stack_push(new_value);
@@ -323,20 +641,20 @@ impl VM {
                     let lhs = self.stack_pop();
 
                     let new_value = match (lhs, rhs) {
-                        (StackValue::F64(lhs), StackValue::F64(rhs)) => StackValue::F64(lhs + rhs),
-                        (StackValue::I64(lhs), StackValue::I64(rhs)) => StackValue::I64(lhs + rhs),
-                        (StackValue::U64(lhs), StackValue::U64(rhs)) => StackValue::U64(lhs + rhs),
                         (StackValue::Obj(lhs), StackValue::Obj(rhs)) => {
                             self.concatenate_strings(lhs, rhs)
                         }
-                        _ => unreachable!(),
+                        _ => match lhs.add_nums(rhs) {
+                            Ok(new_value) => new_value,
+                            Err(msg) => runtime_err!(msg),
+                        },
                     };
 
                     self.stack_push(new_value);
                 }
-                OpCode::Sub => binary_op!(sub_nums),
-                OpCode::Mul => binary_op!(mul_nums),
-                OpCode::Div => binary_op!(div_nums),
+                OpCode::Sub => arithmetic_op!(sub_nums),
+                OpCode::Mul => arithmetic_op!(mul_nums),
+                OpCode::Div => arithmetic_op!(div_nums),
                 OpCode::And => {
                     let rhs = self.stack_pop();
                     let lhs = self.stack_pop();
@@ -362,14 +680,16 @@ impl VM {
                 OpCode::Less => binary_op!(is_less_than),
                 OpCode::LessEqual => binary_op!(is_less_equal_than),
                 OpCode::Print => {
-                    let string = self.stack_pop().display().green();
-                    println!("{string}");
+                    let string = self.stack_pop().display();
+                    if let Err(err) = writeln!(self.out, "{string}") {
+                        runtime_err!(err.to_string());
+                    }
                 }
             }
         }
     }
 
-    fn call(&mut self, arg_count: usize) {
+    fn call(&mut self, arg_count: usize) -> Result<(), String> {
         let slots = self.stack_top - arg_count;
         // dbg!(arg_count);
         let value = self.stack[slots];
@@ -379,37 +699,135 @@ impl VM {
         if let StackValue::Obj(obj) = value {
             match obj {
                 Object::Func(func) => {
+                    self.check_frame_count()?;
+
                     let frame = CallFrame {
                         ip: func.data.chunk.get_ptr(),
                         slots,
                         func,
+                        closure: None,
                     };
-
-                    unsafe { self.frames.as_mut_ptr().add(self.frame_count).write(frame) }
-                    self.frame_count += 1;
+                    self.frames.push(frame);
                 }
-                Object::Native(func) => unsafe {
-                    let args_ptr = self.stack.as_ptr().add(slots + 1);
-                    let args = std::slice::from_raw_parts(args_ptr, arg_count);
+                Object::Closure(closure) => {
+                    self.check_frame_count()?;
 
-                    let value = (func.data.func)(args, &mut self.heap);
+                    let func = closure.data.func;
+                    let frame = CallFrame {
+                        ip: func.data.chunk.get_ptr(),
+                        slots,
+                        func,
+                        closure: Some(closure),
+                    };
+                    self.frames.push(frame);
+                }
+                Object::Native(func) => {
+                    let args = &self.stack[slots + 1..slots + arg_count];
+                    let mut ctx = NatCtx {
+                        heap: &mut *self.heap,
+                        out: &mut *self.out,
+                        input: &mut *self.input,
+                        args: self.args,
+                        exit_code: None,
+                        stack: &self.stack[..self.stack_top],
+                        open_upvalues: &self.open_upvalues,
+                    };
+                    let result = (func.data.func)(args, &mut ctx);
+                    self.exit_code = ctx.exit_code;
+                    self.heap.clear_temp_roots();
+                    let value = result?;
 
                     self.stack_top = slots;
                     self.stack_push(value);
-                },
+                }
                 _ => unreachable!(),
             }
         } else {
             unreachable!()
         }
+        Ok(())
+    }
+
+    fn check_frame_count(&self) -> Result<(), String> {
+        if self.frames.len() == self.max_frames {
+            let msg = format!(
+                "Stack overflow, exceeded the maximum call depth of {}.",
+                self.max_frames
+            );
+            return Err(msg);
+        }
+        Ok(())
+    }
+
+    // reuses the upvalue of the variable at `location` if another closure already captured it
+    fn capture_upvalue(&mut self, location: usize) -> Gc<ObjUpvalue> {
+        let index = self
+            .open_upvalues
+            .partition_point(|upvalue| upvalue.data.location < location);
+        if let Some(upvalue) = self.open_upvalues.get(index) {
+            if upvalue.data.location == location {
+                return *upvalue;
+            }
+        }
+
+        let (_, upvalue) = self.heap.alloc(
+            ObjUpvalue::new(location),
+            Object::Upvalue,
+            &self.stack[..self.stack_top],
+            &self.open_upvalues,
+        );
+        self.open_upvalues.insert(index, upvalue);
+        upvalue
+    }
+
+    // moves the variables at `last` and above off the stack into the upvalues that captured them
+    fn close_upvalues(&mut self, last: usize) {
+        let start = self
+            .open_upvalues
+            .partition_point(|upvalue| upvalue.data.location < last);
+        for mut upvalue in self.open_upvalues.drain(start..) {
+            upvalue.data.closed = Some(self.stack[upvalue.data.location]);
+        }
+    }
+
+    // continues at the innermost catch body with the thrown value, the frames and values above
+    // its 'try' block are discarded
+    fn unwind(&mut self, value: StackValue) {
+        let handler = self.handlers.pop().unwrap();
+        self.close_upvalues(handler.stack_top);
+        self.frames.truncate(handler.frame + 1);
+        self.frames[handler.frame].ip = handler.catch_ip;
+
+        self.stack_top = handler.stack_top;
+        self.stack_push(value);
+    }
+
+    fn runtime_error(&self, msg: String) -> RuntimeErr {
+        let mut trace = Vec::with_capacity(self.frames.len());
+        for frame in self.frames.iter().rev() {
+            let chunk = &frame.func.data.chunk;
+            // ip already points past the instruction that is being executed
+            let offset = unsafe { frame.ip.offset_from(chunk.get_ptr()) } as usize;
+            let line = chunk.get_line(offset.saturating_sub(1));
+
+            trace.push((frame.func.data.get_name().clone(), line));
+        }
+        RuntimeErr::new(msg, trace)
+    }
+
+    #[inline(always)]
+    fn current_frame(&mut self) -> *mut CallFrame {
+        unsafe { self.frames.as_mut_ptr().add(self.frames.len() - 1) }
     }
 
     #[inline(always)]
     fn stack_push(&mut self, value: StackValue) {
-        unsafe {
-            *self.stack.get_unchecked_mut(self.stack_top) = value;
-            self.stack_top += 1;
+        if self.stack_top == self.stack.len() {
+            self.stack.push(value);
+        } else {
+            unsafe { *self.stack.get_unchecked_mut(self.stack_top) = value };
         }
+        self.stack_top += 1;
     }
 
     #[inline(always)]
@@ -431,11 +849,11 @@ impl VM {
     }
 
     unsafe fn debug_trace(&self, frame: *mut CallFrame) {
-        print!("          ");
+        let mut out = String::from("          ");
         for stack_index in 0..self.stack_top {
-            print!("[ {} ]", self.stack[stack_index].display())
+            out.push_str(&format!("[ {} ]", self.stack[stack_index].display()));
         }
-        println!();
+        out.push('\n');
 
         let ip = (*frame).ip;
         let func = (*frame).func;
@@ -443,7 +861,10 @@ impl VM {
         let offset = func.data.chunk.code.as_ptr();
         let debug_offset = ip.offset_from(offset) as usize;
 
-        func.data.chunk.disassemble_instruction(debug_offset);
+        func.data
+            .chunk
+            .disassemble_instruction(debug_offset, &mut out);
+        print!("{out}");
     }
 
     fn concatenate_strings(&mut self, lhs: Object, rhs: Object) -> StackValue {
@@ -457,9 +878,12 @@ impl VM {
         let mut new_str = lhs.data.clone();
         new_str.push_str(&rhs.data);
 
-        let (object, _) = self
-            .heap
-            .alloc(new_str, Object::Str, &mut self.stack, self.stack_top);
+        let (object, _) = self.heap.alloc(
+            new_str,
+            Object::Str,
+            &self.stack[..self.stack_top],
+            &self.open_upvalues,
+        );
 
         StackValue::Obj(object)
     }
@@ -0,0 +1,806 @@
+use crust::{
+    CrustErr, Interpreter, NatCtx, NatFuncData, NatStructData, Object, Repl, StackValue, ValueType,
+};
+
+#[test]
+fn captures_output() {
+    let mut interpreter = Interpreter::new();
+    let output = interpreter.capture_output();
+
+    let source = "fn main() { Vec<int> v = Vec([1]); v.push(2); v.print(); print(3); }";
+    interpreter.run(source).unwrap();
+
+    assert_eq!(output.take(), "[1, 2]\n3");
+    assert_eq!(output.contents(), "");
+}
+
+#[test]
+fn compile_once_run_twice() {
+    let mut interpreter = Interpreter::new();
+    let output = interpreter.capture_output();
+
+    let program = interpreter.compile("fn main() { pr 40 + 2; }").unwrap();
+    interpreter.execute(program).unwrap();
+    let program = interpreter
+        .compile("fn main() { println(\"hi\"); }")
+        .unwrap();
+    interpreter.execute(program).unwrap();
+
+    assert_eq!(output.contents(), "42\nhi\n");
+}
+
+#[test]
+fn typed_errors() {
+    let mut interpreter = Interpreter::new();
+    interpreter.capture_output();
+
+    assert!(matches!(
+        interpreter.run("fn main() { int a = 1 $ 2; }"),
+        Err(CrustErr::Scan(_))
+    ));
+    assert!(matches!(
+        interpreter.run("fn main() { int = 1; }"),
+        Err(CrustErr::Parse(_))
+    ));
+    assert!(matches!(
+        interpreter.run("fn main() { int a = true; }"),
+        Err(CrustErr::Sem(_))
+    ));
+
+    let source = "fn main() {\n    Map<str, int> m = Map();\n    m.get(\"a\");\n}";
+    match interpreter.run(source) {
+        Err(CrustErr::Runtime(err)) => {
+            assert_eq!(
+                (err.msg.as_str(), err.line),
+                ("The key 'a' isn't in the map.", 3)
+            )
+        }
+        _ => panic!("expected a runtime error for a missing key"),
+    }
+
+    match interpreter.run("fn main() {\n    int a = 1 / 0;\n}") {
+        Err(CrustErr::Runtime(err)) => {
+            assert_eq!(err.line, 2);
+            assert_eq!(
+                err.to_string(),
+                "[line 2] Runtime error: Attempted to divide '1' by zero.\n    [line 2] in main()"
+            );
+        }
+        _ => panic!("expected a runtime error"),
+    }
+}
+
+#[test]
+fn match_errors() {
+    let mut interpreter = Interpreter::new();
+    interpreter.capture_output();
+
+    let source = "enum Color { Red, Green, Blue, }\nfn main() {\n    match Color::Red {\n        Color::Red => pr 1,\n    }\n}";
+    match interpreter.run(source) {
+        Err(CrustErr::Sem(err)) => assert!(err.to_string().contains("Green, Blue")),
+        _ => panic!("expected a non-exhaustive match"),
+    }
+
+    let source = "fn main() {\n    int x = 1;\n    match 1 {\n        x => pr 1,\n    }\n}";
+    assert!(matches!(interpreter.run(source), Err(CrustErr::Sem(_))));
+
+    let source = "fn main() {\n    match \"a\" {\n        1 => pr 1,\n    }\n}";
+    assert!(matches!(interpreter.run(source), Err(CrustErr::Sem(_))));
+}
+
+#[test]
+fn data_enum_errors() {
+    let mut interpreter = Interpreter::new();
+    interpreter.capture_output();
+
+    let enum_decl = "enum Shape { Circle(double), Rect(double, double), }\n";
+    let source = format!("{enum_decl}fn main() {{\n    Shape s = Shape::Rect(1.);\n}}");
+    match interpreter.run(&source) {
+        Err(CrustErr::Sem(err)) => assert!(err.to_string().contains("Shape::Rect")),
+        _ => panic!("expected a wrong number of values"),
+    }
+
+    let source = format!("{enum_decl}fn main() {{\n    Shape s = Shape::Circle(\"a\");\n}}");
+    assert!(matches!(interpreter.run(&source), Err(CrustErr::Sem(_))));
+
+    let source = format!(
+        "{enum_decl}fn main() {{\n    match Shape::Circle(1.) {{\n        Shape::Circle(r) | Shape::Rect(r, _) => pr r,\n    }}\n}}"
+    );
+    assert!(matches!(interpreter.run(&source), Err(CrustErr::Sem(_))));
+
+    let source = "enum Color { Red, }\nfn main() {\n    Color c = Color::Red(1);\n}";
+    assert!(matches!(interpreter.run(source), Err(CrustErr::Sem(_))));
+
+    // the tag of a variant is stored in a byte
+    let variants: String = (0..257).map(|i| format!("V{i}(int), ")).collect();
+    match interpreter.run(&format!("enum E {{ {variants}}}\nfn main() {{}}")) {
+        Err(CrustErr::Sem(err)) => assert!(err.to_string().contains("256 variants")),
+        _ => panic!("expected an error for too many variants"),
+    }
+    let variants: String = (0..256).map(|i| format!("V{i}(int), ")).collect();
+    let source = format!(
+        "enum E {{ {variants}}}\nfn main() {{\n    match E::V255(2) {{\n        E::V255(x) => pr x,\n        _ => pr 0,\n    }}\n}}"
+    );
+    assert!(interpreter.run(&source).is_ok());
+}
+
+#[test]
+fn global_errors() {
+    let mut interpreter = Interpreter::new();
+    interpreter.capture_output();
+
+    let source = "const int MAX = 3;\nfn main() {\n    MAX += 1;\n}";
+    match interpreter.run(source) {
+        Err(CrustErr::Sem(err)) => assert!(err.to_string().contains("constant")),
+        _ => panic!("expected a constant reassignment"),
+    }
+
+    let source = "const int MAX;\nfn main() {}";
+    assert!(matches!(interpreter.run(source), Err(CrustErr::Parse(_))));
+
+    let source = "str name = 3;\nfn main() {}";
+    assert!(matches!(interpreter.run(source), Err(CrustErr::Sem(_))));
+
+    let source = "pr 3;\nfn main() {}";
+    assert!(matches!(interpreter.run(source), Err(CrustErr::Sem(_))));
+
+    // every global takes a local slot of 'main'
+    let globals: String = (0..300).map(|i| format!("int g{i} = {i};\n")).collect();
+    match interpreter.run(&format!("{globals}fn main() {{}}")) {
+        Err(CrustErr::Emit(err)) => {
+            assert_eq!(err.to_string(), "[line 255] Error: Too many globals.")
+        }
+        _ => panic!("expected an error for too many globals"),
+    }
+}
+
+#[test]
+fn closure_errors() {
+    let mut interpreter = Interpreter::new();
+    interpreter.capture_output();
+
+    let source = "fn main() {\n    int x = 3;\n    x(1);\n}";
+    match interpreter.run(source) {
+        Err(CrustErr::Sem(err)) => {
+            assert!(err.to_string().contains("Only functions can be called"))
+        }
+        _ => panic!("expected an error for calling an int"),
+    }
+
+    let source = "fn main() {\n    fn(int): int f = fn(str s): int { return 1; };\n}";
+    assert!(matches!(interpreter.run(source), Err(CrustErr::Sem(_))));
+
+    let source =
+        "fn main() {\n    fn(int): int f = fn(int x): int { return x; };\n    f(\"a\");\n}";
+    assert!(matches!(interpreter.run(source), Err(CrustErr::Sem(_))));
+
+    let source = "fn main() {\n    fn(): int f = fn(): int { int x = 1; };\n}";
+    assert!(matches!(interpreter.run(source), Err(CrustErr::Sem(_))));
+}
+
+#[test]
+fn generic_errors() {
+    let mut interpreter = Interpreter::new();
+    interpreter.capture_output();
+
+    let source = "fn main() {\n    Vec<int> v = Vec([1]);\n    v.push(\"a\");\n}";
+    match interpreter.run(source) {
+        Err(CrustErr::Sem(err)) => assert_eq!(err.line, 3),
+        _ => panic!("expected a type error for pushing a str"),
+    }
+
+    let source = "fn main() {\n    Vec<int> v = Vec([1]);\n    str s = v.get(0);\n}";
+    assert!(matches!(interpreter.run(source), Err(CrustErr::Sem(_))));
+
+    let source = "struct Box<T> {\n    T value;\n}\nfn main() {\n    Box b = Box(1);\n}";
+    match interpreter.run(source) {
+        Err(CrustErr::Sem(err)) => assert!(err.to_string().contains("type argument")),
+        _ => panic!("expected an error for a missing type argument"),
+    }
+
+    let source = "fn main() {\n    Vec<str> v = Vec([1]);\n}";
+    assert!(matches!(interpreter.run(source), Err(CrustErr::Sem(_))));
+
+    // an untyped 'Vec' could add elements of any type to a typed one, and the other way around
+    let source = "fn f(Vec v) {\n    v.push(\"oops\");\n}\nfn main() {\n    Vec<int> s = Vec([1]);\n    f(s);\n}";
+    match interpreter.run(source) {
+        Err(CrustErr::Sem(err)) => assert_eq!(err.line, 6),
+        _ => panic!("expected an error for passing a typed Vec as an untyped one"),
+    }
+    let source = "fn sum(Vec<int> v): int {\n    return v.get(0) + 1;\n}\nfn main() {\n    Vec s = Vec([\"a\"]);\n    sum(s);\n}";
+    assert!(matches!(interpreter.run(source), Err(CrustErr::Sem(_))));
+
+    let source = "fn zero<T>(int n): int { return 0; }\nfn main() {\n    zero(1);\n}";
+    match interpreter.run(source) {
+        Err(CrustErr::Sem(err)) => assert!(err.to_string().contains("Can't infer")),
+        _ => panic!("expected an error for an uninferred type parameter"),
+    }
+
+    let source = "fn pick<T>(T a, T b): T { return a; }\nfn main() {\n    pick(1, \"a\");\n}";
+    assert!(matches!(interpreter.run(source), Err(CrustErr::Sem(_))));
+
+    let source = "fn id<T>(T a): T { return a; }\nfn main() {\n    fn(int): int f = id;\n}";
+    assert!(matches!(interpreter.run(source), Err(CrustErr::Sem(_))));
+
+    let source = "struct Box<T> {\n    T value;\n    fn inc(self): T { return self.value + 1; }\n}\nfn main() {}";
+    assert!(matches!(interpreter.run(source), Err(CrustErr::Sem(_))));
+
+    let source = "fn grow<T>(T x, int n): int {\n    if n == 0 { return 0; }\n    return grow([x], n - 1);\n}\nfn main() {\n    grow(1, 3);\n}";
+    match interpreter.run(source) {
+        Err(CrustErr::Sem(err)) => assert!(err.to_string().contains("Too many nested")),
+        _ => panic!("expected an error for unbounded instantiation"),
+    }
+}
+
+#[test]
+fn str_method_errors() {
+    let mut interpreter = Interpreter::new();
+    interpreter.capture_output();
+
+    let source = "fn main() {\n    str s = \"abc\";\n    s.reverse();\n}";
+    match interpreter.run(source) {
+        Err(CrustErr::Sem(err)) => assert_eq!(err.line, 3),
+        _ => panic!("expected an error for an undefined method"),
+    }
+
+    let source = "fn main() {\n    int n = \"abc\".find(1);\n}";
+    assert!(matches!(interpreter.run(source), Err(CrustErr::Sem(_))));
+
+    let source = "fn main() {\n    str s = \"abc\".substring(2, 5);\n}";
+    match interpreter.run(source) {
+        Err(CrustErr::Runtime(err)) => assert_eq!(
+            err.msg,
+            "The range 2 to 5 is out of bounds for a string of length 3."
+        ),
+        _ => panic!("expected a runtime error for an invalid range"),
+    }
+
+    let source = "fn main() {\n    str s = \"abc\"[3];\n}";
+    assert!(matches!(interpreter.run(source), Err(CrustErr::Runtime(_))));
+}
+
+#[test]
+fn format_errors() {
+    let mut interpreter = Interpreter::new();
+    interpreter.capture_output();
+
+    let source = "fn main() {\n    pr f\"{missing}\";\n}";
+    match interpreter.run(source) {
+        Err(CrustErr::Sem(err)) => assert!(err.to_string().contains("'missing'")),
+        _ => panic!("expected an error for an undefined variable in an f-string"),
+    }
+
+    let source = "fn main() {\n    pr f\"{1\";\n}";
+    assert!(matches!(interpreter.run(source), Err(CrustErr::Scan(_))));
+
+    let source = "fn main() {\n    format(\"{} {}\", 1);\n}";
+    match interpreter.run(source) {
+        Err(CrustErr::Runtime(err)) => assert_eq!(
+            err.msg,
+            "The format string has more placeholders than the 1 values given."
+        ),
+        _ => panic!("expected a runtime error for a missing value"),
+    }
+
+    let source = "fn main() {\n    format(\"{:.2}\", 1);\n}";
+    assert!(matches!(interpreter.run(source), Err(CrustErr::Runtime(_))));
+}
+
+#[test]
+fn conversion_errors() {
+    let mut interpreter = Interpreter::new();
+    interpreter.capture_output();
+
+    let source = "fn main() {\n    str s = [1, 2] as str;\n}";
+    match interpreter.run(source) {
+        Err(CrustErr::Sem(err)) => assert_eq!(err.line, 2),
+        _ => panic!("expected an error for casting an array to a string"),
+    }
+
+    // 'display' has to take self and return a string
+    let source = "struct A {\n    int x;\n    fn display(): str { return \"a\"; }\n}\nfn main() {\n    str s = A(1) as str;\n}";
+    assert!(matches!(interpreter.run(source), Err(CrustErr::Sem(_))));
+
+    let source = "fn main() {\n    int n = \"1\" as int;\n}";
+    assert!(matches!(interpreter.run(source), Err(CrustErr::Sem(_))));
+
+    // f-strings convert their values like 'as str'
+    let source = "struct A {\n    int x;\n}\nfn main() {\n    str s = f\"{[1, 2]}\";\n    str t = f\"{A(1)}\";\n}";
+    match interpreter.run(source) {
+        Err(CrustErr::Sem(err)) => assert_eq!(err.line, 5),
+        _ => panic!("expected an error for an array in an f-string"),
+    }
+}
+
+#[test]
+fn result_errors() {
+    let mut interpreter = Interpreter::new();
+    interpreter.capture_output();
+
+    let source = "fn main() {\n    Result<int, str> r = err(\"bad\");\n    pr r.unwrap();\n}";
+    match interpreter.run(source) {
+        Err(CrustErr::Runtime(err)) => assert_eq!(
+            (err.msg.as_str(), err.line),
+            ("Called 'unwrap' on an error: \"bad\".", 3)
+        ),
+        _ => panic!("expected a runtime error for unwrapping an error"),
+    }
+
+    // the error of '?' has to fit the return type of the function
+    let source = "fn half(int n): Result<int, str> { return ok(n / 2); }\nfn f(): Result<int, int> {\n    return ok(half(4)?);\n}\nfn main() {}";
+    match interpreter.run(source) {
+        Err(CrustErr::Sem(err)) => assert_eq!(
+            err.to_string(),
+            "[line 3] Error: The '?' operator returns the error of 'struct Result<Int, String>' from the function, but it returns 'struct Result<Int, Int>'."
+        ),
+        _ => panic!("expected a semantic error"),
+    }
+
+    let source = "fn f(): int {\n    return some(1)?;\n}\nfn main() {}";
+    assert!(matches!(interpreter.run(source), Err(CrustErr::Sem(_))));
+
+    let source = "fn f(): Option<int> {\n    return some(1?);\n}\nfn main() {}";
+    match interpreter.run(source) {
+        Err(CrustErr::Sem(err)) => assert_eq!(
+            err.to_string(),
+            "[line 2] Error: The '?' operator can only be used on a Result or an Option, found 'Int'."
+        ),
+        _ => panic!("expected a semantic error"),
+    }
+
+    let source = "fn main() {\n    Option<int> o = Option();\n}";
+    assert!(matches!(interpreter.run(source), Err(CrustErr::Sem(_))));
+}
+
+#[test]
+fn uncaught_exceptions() {
+    let mut interpreter = Interpreter::new();
+    interpreter.capture_output();
+
+    let source = "fn fail() {\n    throw \"bad input\";\n}\nfn main() {\n    fail();\n}";
+    match interpreter.run(source) {
+        Err(CrustErr::Runtime(err)) => {
+            assert_eq!((err.msg.as_str(), err.line), ("bad input", 2));
+            assert_eq!(err.trace.len(), 2);
+        }
+        _ => panic!("expected an uncaught exception"),
+    }
+
+    // an error in the catch body isn't caught by the same 'try'
+    let source =
+        "fn main() {\n    try {\n        throw 1;\n    } catch (e) {\n        throw e;\n    }\n}";
+    match interpreter.run(source) {
+        Err(CrustErr::Runtime(err)) => assert_eq!((err.msg.as_str(), err.line), ("1", 5)),
+        _ => panic!("expected an uncaught exception"),
+    }
+
+    let source = "fn main() {\n    try {} \n}";
+    assert!(matches!(interpreter.run(source), Err(CrustErr::Parse(_))));
+}
+
+#[test]
+fn null_safety_errors() {
+    let mut interpreter = Interpreter::new();
+    interpreter.capture_output();
+
+    let source = "fn main() {\n    int x;\n    pr x;\n}";
+    match interpreter.run(source) {
+        Err(CrustErr::Sem(err)) => assert_eq!(
+            err.to_string(),
+            "[line 3] Error: The variable 'x' is read before it's assigned a value."
+        ),
+        _ => panic!("expected a semantic error"),
+    }
+
+    // only one branch assigns 'x'
+    let source = "fn main() {\n    int x;\n    if true {\n        x = 1;\n    }\n    pr x;\n}";
+    assert!(matches!(interpreter.run(source), Err(CrustErr::Sem(_))));
+
+    let source = "fn main() {\n    int? x = 1;\n    pr x + 1;\n}";
+    match interpreter.run(source) {
+        Err(CrustErr::Sem(err)) => assert_eq!(
+            err.to_string(),
+            "[line 3] Error: A value of type 'Int?' may be null, check it with '!= null' first."
+        ),
+        _ => panic!("expected a semantic error"),
+    }
+
+    let source = "fn main() {\n    int x = null;\n}";
+    assert!(matches!(interpreter.run(source), Err(CrustErr::Sem(_))));
+
+    let source = "fn f(): int {\n    return null;\n}\nfn main() {}";
+    assert!(matches!(interpreter.run(source), Err(CrustErr::Sem(_))));
+
+    let source = "int count;\nfn main() {}";
+    assert!(matches!(interpreter.run(source), Err(CrustErr::Sem(_))));
+
+    // the loop would use 'x' as checked again after it was set to null
+    let source = "fn main() {\n    int? x = 1;\n    if x != null {\n        while x > 0 {\n            x = null;\n        }\n    }\n}";
+    match interpreter.run(source) {
+        Err(CrustErr::Sem(err)) => assert_eq!(err.line, 5),
+        _ => panic!("expected a semantic error"),
+    }
+
+    // the lambda can set 'x' to null after the check
+    let source = "fn main() {\n    int? x = 1;\n    fn() f = fn() {\n        x = null;\n    };\n    if x != null {\n        f();\n        pr x + 1;\n    }\n}";
+    match interpreter.run(source) {
+        Err(CrustErr::Sem(err)) => assert_eq!(err.line, 8),
+        _ => panic!("expected a semantic error"),
+    }
+
+    // the lambda can be called after 'x' is set to null
+    let source = "fn main() {\n    int? x = 3;\n    if x != null {\n        fn(): int f = fn(): int { return x; };\n        x = null;\n        pr f() + 1;\n    }\n}";
+    match interpreter.run(source) {
+        Err(CrustErr::Sem(err)) => assert_eq!(err.line, 4),
+        _ => panic!("expected a semantic error"),
+    }
+}
+
+#[test]
+fn trait_errors() {
+    let mut interpreter = Interpreter::new();
+    interpreter.capture_output();
+
+    let shape = "trait Shape {\n    fn area(self): double;\n    fn name(self): str;\n}\nstruct Square {\n    double side;\n}\n";
+
+    let source = format!(
+        "{shape}impl Shape for Square {{\n    fn area(self): double {{\n        return self.side * self.side;\n    }}\n}}\nfn main() {{}}"
+    );
+    match interpreter.run(&source) {
+        Err(CrustErr::Sem(err)) => assert_eq!(
+            err.to_string(),
+            "[line 8] Error: The implementation of trait 'Shape' for 'Square' is missing the method 'name'."
+        ),
+        _ => panic!("expected a semantic error"),
+    }
+
+    // 'area' returns an int instead of a double
+    let source = format!(
+        "{shape}impl Shape for Square {{\n    fn area(self): int {{\n        return 1;\n    }}\n    fn name(self): str {{\n        return \"square\";\n    }}\n}}\nfn main() {{}}"
+    );
+    match interpreter.run(&source) {
+        Err(CrustErr::Sem(err)) => assert_eq!(
+            err.to_string(),
+            "[line 8] Error: Method 'area' doesn't match its signature in trait 'Shape'."
+        ),
+        _ => panic!("expected a semantic error"),
+    }
+
+    let source = "struct Square {\n    double side;\n}\nimpl Shape for Square {}\nfn main() {}";
+    match interpreter.run(source) {
+        Err(CrustErr::Sem(err)) => assert_eq!(
+            err.to_string(),
+            "[line 4] Error: Trait 'Shape' has not been defined."
+        ),
+        _ => panic!("expected a semantic error"),
+    }
+
+    // 'Square' doesn't implement 'Shape'
+    let source = format!("{shape}fn main() {{\n    Shape shape = Square(1.0);\n}}");
+    match interpreter.run(&source) {
+        Err(CrustErr::Sem(err)) => assert_eq!(err.line, 9),
+        _ => panic!("expected a semantic error"),
+    }
+
+    let source = format!("{shape}fn main() {{\n    Shape[] shapes = [Square(1.0)];\n}}");
+    assert!(matches!(interpreter.run(&source), Err(CrustErr::Sem(_))));
+}
+
+#[test]
+fn max_frames() {
+    let mut interpreter = Interpreter::new();
+    interpreter.capture_output();
+    interpreter.set_max_frames(8);
+
+    let source = "fn recurse(int n): int { return recurse(n + 1); }\nfn main() { recurse(0); }";
+    match interpreter.run(source) {
+        Err(CrustErr::Runtime(err)) => assert_eq!(err.trace.len(), 8),
+        _ => panic!("expected a stack overflow"),
+    }
+}
+
+fn shout(args: &[StackValue], ctx: &mut NatCtx) -> Result<StackValue, String> {
+    writeln!(ctx.out, "{}!", args[0]).map_err(|err| err.to_string())?;
+    Ok(StackValue::Null)
+}
+
+fn half(args: &[StackValue], _ctx: &mut NatCtx) -> Result<StackValue, String> {
+    match args[0] {
+        StackValue::I64(n) if n % 2 == 0 => Ok(StackValue::I64(n / 2)),
+        StackValue::I64(n) => Err(format!("{n} is odd.")),
+        _ => unreachable!(),
+    }
+}
+
+fn counter_next(args: &[StackValue], _ctx: &mut NatCtx) -> Result<StackValue, String> {
+    let StackValue::Obj(Object::Inst(mut inst)) = args[0] else {
+        unreachable!()
+    };
+    let StackValue::I64(count) = inst.data.fields[0] else {
+        unreachable!()
+    };
+    inst.data.fields[0] = StackValue::I64(count + 1);
+    Ok(StackValue::I64(count))
+}
+
+#[test]
+fn registered_funcs() {
+    let mut interpreter = Interpreter::new();
+    let output = interpreter.capture_output();
+    interpreter.register_func("shout", vec![ValueType::Any], ValueType::Null, shout);
+    interpreter.register_func("half", vec![ValueType::I64], ValueType::I64, half);
+
+    interpreter
+        .run("fn main() { shout(\"hi\"); println(half(8)); }")
+        .unwrap();
+    assert_eq!(output.take(), "hi!\n4\n");
+
+    let result = interpreter.run("fn main() { half(true); }");
+    assert!(matches!(result, Err(CrustErr::Sem(_))));
+
+    match interpreter.run("fn main() {\n    half(3);\n}") {
+        Err(CrustErr::Runtime(err)) => assert_eq!((err.msg.as_str(), err.line), ("3 is odd.", 2)),
+        _ => panic!("expected a runtime error"),
+    }
+}
+
+#[test]
+fn registered_struct() {
+    let mut interpreter = Interpreter::new();
+    let output = interpreter.capture_output();
+
+    let next = NatFuncData {
+        parameters: vec![],
+        func: counter_next,
+        return_ty: ValueType::I64,
+        use_self: true,
+    };
+    let counter = NatStructData {
+        fields: vec![(ValueType::I64, "count")],
+        methods: vec![("next", next)],
+        type_params: vec![],
+    };
+    interpreter.register_struct("Counter", counter);
+
+    let source = "fn main() { Counter c = Counter(5); c.next(); println(c.next()); c.count += 10; println(c.count); }";
+    interpreter.run(source).unwrap();
+    assert_eq!(output.take(), "6\n17\n");
+}
+
+#[test]
+fn repl_keeps_state() {
+    let mut interpreter = Interpreter::new();
+    let output = interpreter.capture_output();
+    let mut repl = Repl::new(interpreter);
+
+    repl.eval("int x = 3;").unwrap();
+    repl.eval("fn add(int a, int b): int {\n    return a + b;\n}")
+        .unwrap();
+    repl.eval("struct Point { int x; int y; }").unwrap();
+    repl.eval("Point p = Point(1, 2);").unwrap();
+    repl.eval("add(x, p.y)").unwrap();
+    repl.eval("x = 10;").unwrap();
+    repl.eval("println(\"x is\"); x").unwrap();
+    repl.eval("\"str\"").unwrap();
+    repl.eval("const int MAX = 5;").unwrap();
+    assert!(matches!(repl.eval("MAX = 6;"), Err(CrustErr::Sem(_))));
+
+    // declarations of earlier inputs can be extended and used with new types
+    repl.eval("trait Sum { fn sum(self): int; }").unwrap();
+    repl.eval("impl Sum for Point { fn sum(self): int { return self.x + self.y; } }")
+        .unwrap();
+    repl.eval("fn first<T>(T[] items): T { return items[0]; }")
+        .unwrap();
+    repl.eval("Sum s = first([Point(1, 2)]); s.sum()").unwrap();
+    repl.eval("first([\"a\", \"b\"])").unwrap();
+
+    assert_eq!(output.take(), "5\nx is\n10\n\"str\"\n3\n\"a\"\n");
+}
+
+#[test]
+fn repl_variables_are_globals() {
+    let mut interpreter = Interpreter::new();
+    let output = interpreter.capture_output();
+    let mut repl = Repl::new(interpreter);
+
+    repl.eval("str s = \"x\";").unwrap();
+    repl.eval("fn f(): str { return s; }").unwrap();
+    repl.eval("s = \"y\"; f()").unwrap();
+
+    // a lambda changes the global itself, not a copy of it
+    repl.eval("int count = 0;").unwrap();
+    repl.eval("fn() inc = fn() { count += 1; };").unwrap();
+    repl.eval("inc(); inc();").unwrap();
+    repl.eval("count").unwrap();
+
+    // locals of a block don't take the slot of a global
+    repl.eval("if true { int a = 5; int b = 6; pr a + b; } int c = 7; pr s; c")
+        .unwrap();
+    repl.eval("c + count").unwrap();
+
+    let globals: String = (0..300).map(|i| format!("int g{i} = {i}; ")).collect();
+    assert!(matches!(repl.eval(&globals), Err(CrustErr::Emit(_))));
+
+    assert_eq!(output.take(), "\"y\"\n2\n11\n\"y\"\n7\n9\n");
+}
+
+#[test]
+fn repl_recovers_from_errors() {
+    let mut interpreter = Interpreter::new();
+    let output = interpreter.capture_output();
+    let mut repl = Repl::new(interpreter);
+
+    repl.eval("int x = 1;").unwrap();
+    assert!(matches!(
+        repl.eval("int y = 1 $ 2;"),
+        Err(CrustErr::Scan(_))
+    ));
+    assert!(matches!(repl.eval("int y = ;"), Err(CrustErr::Parse(_))));
+    assert!(matches!(repl.eval("int y = true;"), Err(CrustErr::Sem(_))));
+    assert!(matches!(
+        repl.eval("fn f(): int { return y; }"),
+        Err(CrustErr::Sem(_))
+    ));
+    assert!(matches!(
+        repl.eval("x = 5; int z = x / 0;"),
+        Err(CrustErr::Runtime(_))
+    ));
+    assert!(matches!(repl.eval("z"), Err(CrustErr::Sem(_))));
+
+    repl.eval("int y = x + 1;").unwrap();
+    repl.eval("y").unwrap();
+    assert_eq!(output.take(), "6\n");
+}
+
+#[test]
+fn disassemble() {
+    let interpreter = Interpreter::new();
+    let source = "fn main() {\n    if true pr 1;\n    pr twice(2);\n}\nfn twice(int n): int {\n    return n * 2;\n}";
+    let disasm = interpreter.compile(source).unwrap().disassemble();
+
+    let main = "== main ==\n\
+        0000    2 OP_TRUE\n\
+        0001    | OP_JUMP_IF_FALSE   1 -> 11\n\
+        0004    | OP_POP\n\
+        0005    | OP_CONSTANT        0 '1'\n\
+        0007    | OP_PRINT\n\
+        0008    | OP_JUMP            8 -> 12\n\
+        0011    | OP_POP\n\
+        0012    3 OP_CONSTANT        1 'fn twice'\n\
+        0014    | OP_CONSTANT        2 '2'\n\
+        0016    | OP_CALL            2\n\
+        0018    | OP_PRINT\n";
+    assert!(disasm.starts_with(main), "{disasm}");
+    assert!(disasm.contains("== twice ==\n0000    6 OP_GET_LOCAL       1\n"));
+}
+
+#[test]
+fn module_errors() {
+    let mut interpreter = Interpreter::new();
+    interpreter.capture_output();
+    interpreter.set_source_path("tests/modules/cycle_a.crs");
+
+    let source = std::fs::read_to_string("tests/modules/cycle_a.crs").unwrap();
+    match interpreter.run(&source) {
+        Err(CrustErr::Parse(errs)) => assert_eq!(
+            errs[0].to_string(),
+            "[line 1 in tests/modules/cycle_b.crs] Error: Import cycle detected: tests/modules/cycle_a.crs -> tests/modules/cycle_b.crs -> tests/modules/cycle_a.crs."
+        ),
+        _ => panic!("expected an import cycle"),
+    }
+
+    interpreter.set_source_path("tests/modules/main.crs");
+    let result = interpreter.run("import \"broken.crs\";\nfn main() {}");
+    match result {
+        Err(CrustErr::Sem(err)) => {
+            assert_eq!(err.line, 3);
+            assert_eq!(err.file.as_deref(), Some("tests/modules/broken.crs"));
+        }
+        _ => panic!("expected a semantic error"),
+    }
+
+    let result = interpreter.run("import \"util.crs\";\nfn main() {\n    util::thrice(1);\n}");
+    match result {
+        Err(CrustErr::Parse(errs)) => assert_eq!(
+            errs[0].to_string(),
+            "[line 3] Error: Module 'util' has no function, struct, enum or global named 'thrice'."
+        ),
+        _ => panic!("expected a parse error"),
+    }
+
+    let result = interpreter.run("import \"missing.crs\";\nfn main() {}");
+    assert!(matches!(result, Err(CrustErr::Parse(_))));
+}
+
+#[test]
+fn input_args_and_exit() {
+    let mut interpreter = Interpreter::new();
+    let output = interpreter.capture_output();
+    interpreter.set_input(std::io::Cursor::new("first\r\nsecond\nrest\nof input"));
+    interpreter.set_args(vec!["-n".to_string(), "5".to_string()]);
+
+    let source = "fn main(str[] args) {
+        pr len(args);
+        pr args[0] + args[1];
+        pr read_line();
+        pr read_line();
+        pr read_all();
+        pr read_line() == null;
+        exit(3);
+        pr \"unreachable\";
+    }";
+    match interpreter.run(source) {
+        Err(CrustErr::Exit(code)) => assert_eq!(code, 3),
+        _ => panic!("expected the program to exit"),
+    }
+    assert_eq!(
+        output.take(),
+        "2\n\"-n5\"\n\"first\"\n\"second\"\n\"rest\\nof input\"\ntrue\n"
+    );
+
+    interpreter.set_args(vec![]);
+    interpreter
+        .run("fn main(str[] args) { pr len(args); }")
+        .unwrap();
+    assert_eq!(output.take(), "0\n");
+
+    match interpreter.run("fn main(int n) {}") {
+        Err(CrustErr::Sem(err)) => assert_eq!(
+            err.to_string(),
+            "[line 1] Error: The function 'main' can only take the command-line arguments, 'fn main(str[] args)'."
+        ),
+        _ => panic!("expected a semantic error"),
+    }
+}
+
+#[test]
+fn file_io() {
+    let dir = std::env::temp_dir().join(format!("crust_file_io_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let dir = dir.to_str().unwrap().replace('\\', "/");
+
+    let mut interpreter = Interpreter::new();
+    let output = interpreter.capture_output();
+
+    let source = format!(
+        "fn main() {{
+        str path = \"{dir}/notes.txt\";
+        pr file_exists(path);
+        write_file(path, \"one\");
+        append_file(path, \" two\");
+        append_file(\"{dir}/other.txt\", \"\");
+        pr file_exists(path);
+        pr read_file(path);
+        str[] names = list_dir(\"{dir}\");
+        pr len(names);
+        pr names[0];
+        pr names[1];
+        remove_file(path);
+        pr file_exists(path);
+    }}"
+    );
+    interpreter.run(&source).unwrap();
+    assert_eq!(
+        output.take(),
+        "false\ntrue\n\"one two\"\n2\n\"notes.txt\"\n\"other.txt\"\nfalse\n"
+    );
+
+    let source = format!("fn main() {{\n    read_file(\"{dir}/missing.txt\");\n}}");
+    match interpreter.run(&source) {
+        Err(CrustErr::Runtime(err)) => {
+            assert_eq!(err.line, 2);
+            assert!(err
+                .msg
+                .starts_with(&format!("Could not read file '{dir}/missing.txt': ")));
+        }
+        _ => panic!("expected a runtime error for a missing file"),
+    }
+
+    let source = format!("fn main() {{ remove_file(\"{dir}/missing.txt\"); }}");
+    assert!(matches!(
+        interpreter.run(&source),
+        Err(CrustErr::Runtime(_))
+    ));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -29,6 +29,109 @@ create_test!(break_loop, "break", "0123");
 create_test!(arr_print_2d, "2d_arr_print", "1234");
 create_test!(fields, "fields", "3 2\n1");
 create_test!(methods, "methods", "6\n4\n\"hoi\"");
+create_test!(method_locals, "method_locals", "3\n70");
 create_test!(vec, "vec", "2\n3\n[1, 2]\n[1, 2, 4]\n3");
 create_test!(mult_insts, "mult_insts", "0\n10\n1\n11");
 create_test!(enums, "enum", "0 = red\n1 = blue\n4");
+create_test!(
+    runtime_error,
+    "runtime_error",
+    "1\n[line 3] Runtime error: You tried to pop an element from an empty vec.\n    [line 3] in pop_all()\n    [line 9] in main()"
+);
+create_test!(
+    index_out_of_bounds,
+    "index_out_of_bounds",
+    "4\n[line 2] Runtime error: Index 2 out of bounds for length 2.\n    [line 2] in get()\n    [line 8] in main()"
+);
+create_test!(
+    negative_index,
+    "negative_index",
+    "[line 4] Runtime error: Index -1 out of bounds for length 3.\n    [line 4] in main()"
+);
+create_test!(
+    stack_overflow,
+    "stack_overflow",
+    "50005000\n[line 7] Runtime error: Stack overflow, exceeded the maximum call depth of 16384.\n    [line 7] in recurse() (repeated 16383 times)\n    [line 12] in main()"
+);
+create_test!(
+    overflow,
+    "overflow",
+    "18446744073709551615\nnull\n3\n-9223372036854775808\n[line 9] Runtime error: Integer overflow while evaluating '9223372036854775807 + 1'.\n    [line 9] in main()"
+);
+create_test!(
+    div_zero,
+    "div_zero",
+    "inf\n[line 2] Runtime error: Attempted to divide '7' by zero.\n    [line 2] in divide()\n    [line 7] in main()"
+);
+create_test!(
+    modules,
+    "modules/main",
+    "12\n14\n6\n4\nsquare\n9\n16\n25\n0\n2\ntrue"
+);
+create_test!(
+    module_runtime_error,
+    "modules/runtime_error",
+    "[line 10 in tests/modules/util.crs] Runtime error: Attempted to divide '3' by zero.\n    [line 10] in util::ratio()\n    [line 33] in geometry::aspect_ratio()\n    [line 5] in main()"
+);
+create_test!(
+    match_stmt,
+    "match",
+    "warm\ncold\nA\nB\nnone\nC\nbaked\nyes\n10"
+);
+create_test!(
+    data_enum,
+    "data_enum",
+    "12\n12\n0\ntrue\nfalse\nRect(3, 4)\nEmpty\ntrue\nfalse\ntrue\ntrue\nfalse\n5\nfalse\n1998"
+);
+create_test!(globals, "globals", "hello crust\n2\n10\n3\n12.56636\n3");
+create_test!(
+    closures,
+    "closures",
+    "3\n1\n7\n11\n16\n42\n6\n4\n42\n\"hello, crust\"\nfn square"
+);
+create_test!(
+    generics,
+    "generics",
+    "9\n1.5\n\"crust\"\ntrue\n\"b\"\n\"age\"\n43\nfalse"
+);
+create_test!(
+    map,
+    "map",
+    "3\n3\n{a: 3, b: 1, c: 1}\ntrue\n3\nfalse\n\"c\"\n1\n\"b\"\n1\n\"een\"\n1\n0\n{}\nfalse\n2\n3\n2"
+);
+create_test!(
+    str_methods,
+    "str_methods",
+    "\"Hello, Crust\"\n12\n12\n\"HELLO, CRUST\"\n\"hello, crust\"\n\"Crust\"\n7\n-1\ntrue\ntrue\nfalse\n\"HeLLo, Crust\"\n\"o\"\n4\n\"c\"\n5\n\"é\"\n3"
+);
+create_test!(
+    fstrings,
+    "fstrings",
+    "\"a = 3, b = 1.5\"\n\"sum = 4.5\"\n\"CRUST has 5 letters\"\n\"nested 33 and 2\"\n\"no expressions\"\n\"true\"\n\"point (1, 2)\"\n1 + 2 = 3\n[   42] [42   ] [   ab]\n3.14 -002.500 -0042\nabc|ab    |\n{} braces"
+);
+create_test!(
+    conversions,
+    "conversions",
+    "\"42\"\n\"1.5\"\n\"true\"\n\"already\"\n\"Circle(2)\"\n\"(1, 2)\"\n\"n = 3\"\n18\n5\ntrue\n7"
+);
+create_test!(
+    result,
+    "result",
+    "ok(80)\nerr(\"'http' isn't a number\")\nerr(\"0 is out of range\")\ntrue\n523\ntrue\n\"'x' isn't a number\"\n-1\nsome(1)\ntrue\n2\nnone\ntrue\nfalse\nok(3)\nok(\"999\")"
+);
+create_test!(
+    exceptions,
+    "exceptions",
+    "21\ninvalid age -1\nIndex 5 out of bounds for length 3.\nAttempted to divide '1' by zero.\nYou tried to pop an element from an empty vec.\n42\n\"rethrown\"\nafter the loop\nkept\n3\n3\n0\ncaught by main"
+);
+create_test!(
+    null_safety,
+    "null_safety",
+    "true\n2\ntrue\n\"nothing\"\n\"got 42\"\n4\n\"checked\"\n\"some\"\n7\n6\nfalse\ntrue\n0"
+);
+create_test!(
+    traits,
+    "traits",
+    "\"rect 6\"\n\"circle 3\"\n\"square 4\"\n13\n8\n\"rect\"\ntrue\n\"square\"\n\"rect\"\n\"circle\"\n12"
+);
+create_test!(typed_vec, "typed_vec", "8\n7\nhello crust\n4\n\"seven\"");